@@ -1,22 +1,15 @@
 import os
 import pandas as pd
 
-IP_TO_NAME = {
-    "192.168.42.42:8080": "Accelerated CNF",
-    "00.00.00.00:8080": "Private Cloud",
-    "00.00.00.00:8080": "Public Cloud",
-}
-
 def merge_csv_files(input_folder, output_file):
-    # List all CSV files in the input folder
-    csv_files = [f for f in os.listdir(input_folder) if f.endswith('.csv')]
+    # List all result CSV files in the input folder and its run directories, outage files have different columns
+    csv_files = sorted(os.path.join(root, f) for root, _, files in os.walk(input_folder) for f in files if f.startswith('rtt_times_') and f.endswith('.csv'))
     print(csv_files)
     # Initialize an empty list to hold dataframes
     dataframes = []
     
     # Read each CSV file and append to the list
-    for file in csv_files:
-        file_path = os.path.join(input_folder, file)
+    for file_path in csv_files:
         df = pd.read_csv(file_path)
         dataframes.append(df)
     
@@ -30,12 +23,9 @@ def group_by_column(input_file, output_file, group_column):
     # Read the input CSV file
     df = pd.read_csv(input_file)
     
-    # Group by the specified column
-    grouped_df = df.groupby(group_column).mean()
+    # Group by the specified columns, the server names come from the client config
+    grouped_df = df.groupby(group_column).mean(numeric_only=True)
 
-    # Rename the "Server Address" column to "Server Name"
-    grouped_df = grouped_df.rename(index=IP_TO_NAME)
-    
     # Save the grouped dataframe to a new CSV file
     grouped_df.to_csv(output_file)
 
@@ -78,12 +68,12 @@ if __name__ == "__main__":
     output_file = 'merged_rtt_times.csv'
     print('Merging CSV files...')
     merge_csv_files(input_folder, output_file)
-    print('Grouping by server address')
-    group_by_column(output_file, 'grouped_rtt_times.csv', ['Server Address', 'Speedtest-Mode'])
+    print('Grouping by server name')
+    group_by_column(output_file, 'grouped_rtt_times.csv', ['Server Name', 'Server Address', 'Speedtest-Mode'])
     print('Setting RTT time scale to milliseconds')
     set_rtt_time_scale('grouped_rtt_times.csv', 'scaled_rtt_times.csv', 1/1000)
-    print('Sorting the CSV file by server address')
-    sort_csv('scaled_rtt_times.csv', 'sorted_rtt_times.csv', 'Server Address')
+    print('Sorting the CSV file by server name')
+    sort_csv('scaled_rtt_times.csv', 'sorted_rtt_times.csv', 'Server Name')
     print('Transposing the CSV file')
     transpose_csv('sorted_rtt_times.csv', 'transposed_rtt_times.csv')
     print('Done!')
@@ -0,0 +1,384 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use log::{debug, info};
+use serde_json::{json, Value};
+use crate::client::file_name_part;
+use crate::histogram::LatencyHistogram;
+use crate::ledger::{Ledger, LEDGER_FILE, STATUS_FILE};
+use crate::manifest::RunManifest;
+use crate::stats::{percentile_column, RttSummary, SampleSummary, DEFAULT_PERCENTILES};
+
+/// Columns identifying a run rather than measuring it, left out when averaging.
+const ID_COLUMNS: [&str; 1] = ["Server Seed"];
+/// Columns the results are grouped by, as far as a results file has them.
+const KEY_COLUMNS: [&str; 3] = ["Server Name", "Server Address", "Speedtest-Mode"];
+
+/// Result rows of all CSV files in a results directory, grouped by server name, address and speedtest mode.
+pub struct ResultTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ResultTable {
+    /// Reads and merges all `rtt_times_*.csv` files written by the client into `results_dir` and its run
+    /// directories. The table has the columns of all files, and the cells of columns a file lacks are empty.
+    pub fn read_dir(results_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let paths = result_files(results_dir, &|path| {
+            path.extension().is_some_and(|ext| ext == "csv") && path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with("rtt_times_"))
+        })?;
+
+        let mut columns: Vec<String> = Vec::new();
+        let mut rows = Vec::new();
+        for path in paths {
+            debug!("Reading results file: {}", path.display());
+            let mut rdr = csv::Reader::from_path(&path)?;
+            // Files of other versions may have other columns, so the table has all of them
+            let positions = rdr
+                .headers()?
+                .iter()
+                .map(|header| match columns.iter().position(|column| column == header) {
+                    Some(position) => position,
+                    None => {
+                        columns.push(header.to_string());
+                        columns.len() - 1
+                    }
+                })
+                .collect::<Vec<_>>();
+            for record in rdr.records() {
+                let mut row = vec![String::new(); columns.len()];
+                for (position, value) in positions.iter().zip(record?.iter()) {
+                    row[*position] = value.to_string();
+                }
+                rows.push(row);
+            }
+        }
+        for row in &mut rows {
+            row.resize(columns.len(), String::new());
+        }
+        info!("Read {} result rows from {}", rows.len(), results_dir.display());
+        Ok(ResultTable { columns, rows })
+    }
+
+    /// Averages all numeric columns per server name, address and speedtest mode, like `evaluation.py` did.
+    ///
+    /// Empty cells, e.g. the RTTs of a run without responses, are skipped, so every column is averaged over
+    /// the runs that have a value for it. Columns without any value stay empty.
+    pub fn group_mean(&self) -> ResultTable {
+        let mut groups: BTreeMap<Vec<String>, Vec<(f64, usize)>> = BTreeMap::new();
+        let (key_columns, value_columns): (Vec<usize>, Vec<usize>) =
+            (0..self.columns.len()).filter(|i| !ID_COLUMNS.contains(&self.columns[*i].as_str())).partition(|i| KEY_COLUMNS.contains(&self.columns[*i].as_str()));
+        for row in &self.rows {
+            let key = key_columns.iter().map(|i| row.get(*i).cloned().unwrap_or_default()).collect();
+            let sums = groups.entry(key).or_insert_with(|| vec![(0.0, 0); value_columns.len()]);
+            for ((sum, count), i) in sums.iter_mut().zip(&value_columns) {
+                if let Some(value) = row.get(*i).and_then(|value| value.parse::<f64>().ok()) {
+                    *sum += value;
+                    *count += 1;
+                }
+            }
+        }
+
+        let rows = groups
+            .into_iter()
+            .map(|(mut row, sums)| {
+                row.extend(sums.iter().map(|(sum, count)| if *count > 0 { (sum / *count as f64).to_string() } else { String::new() }));
+                row
+            })
+            .collect();
+        let columns = key_columns.iter().chain(&value_columns).map(|i| self.columns[*i].clone()).collect();
+        ResultTable { columns, rows }
+    }
+
+    pub fn write_csv(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(&self.columns)?;
+        for row in &self.rows {
+            wtr.write_record(row)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// Files in `dir` and, recursively, its subdirectories that `keep` accepts, sorted by path.
+fn result_files(dir: &Path, keep: &dyn Fn(&Path) -> bool) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            paths.extend(result_files(&path, keep)?);
+        } else if keep(&path) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Merged RTT histogram of all runs against one named server in one speedtest mode.
+pub struct MergedHistogram {
+    pub server_name: String,
+    pub server_addr: String,
+    pub mode: String,
+    pub runs: usize,
+    pub histogram: LatencyHistogram,
+}
+
+/// Merges all `*.hlog` histogram logs written by the client into `results_dir` and its run directories per
+/// (server name, server address, speedtest mode). Logs without a name tag are named after the address.
+pub fn merge_histograms(results_dir: &Path) -> Result<Vec<MergedHistogram>, Box<dyn Error>> {
+    let paths = result_files(results_dir, &|path| {
+        path.extension().is_some_and(|ext| ext == "hlog") && !path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("merged_"))
+    })?;
+
+    let mut groups: BTreeMap<(String, String, String), MergedHistogram> = BTreeMap::new();
+    for path in paths {
+        debug!("Reading histogram log: {}", path.display());
+        let (histogram, tags) = LatencyHistogram::read_log(&path)?;
+        let tag = |key: &str| tags.iter().find(|(k, _)| k == key).map(|(_, value)| value.clone()).unwrap_or_default();
+        let server_addr = tag("server");
+        let server_name = Some(tag("name")).filter(|name| !name.is_empty()).unwrap_or_else(|| server_addr.clone());
+        let key = (server_name, server_addr, tag("mode"));
+        let merged = groups.entry(key.clone()).or_insert_with(|| MergedHistogram {
+            server_name: key.0,
+            server_addr: key.1,
+            mode: key.2,
+            runs: 0,
+            histogram: LatencyHistogram::new(histogram.precision()),
+        });
+        merged.histogram.merge(&histogram);
+        merged.runs += 1;
+    }
+    Ok(groups.into_values().collect())
+}
+
+fn write_histograms(histograms: &[MergedHistogram], output_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_path(output_dir.join("rtt_histogram_summary.csv"))?;
+    let mut header: Vec<String> = ["Server Name", "Server Address", "Speedtest-Mode", "Runs", "Samples", "Minimum RTT", "Average RTT", "Standard Deviation RTT"].iter().map(|column| column.to_string()).collect();
+    header.extend(DEFAULT_PERCENTILES.iter().map(|percentile| percentile_column(*percentile)));
+    header.push("Maximum RTT".to_string());
+    wtr.write_record(&header)?;
+    for merged in histograms {
+        let histogram = &merged.histogram;
+        let mut record = vec![merged.server_name.clone(), merged.server_addr.clone(), merged.mode.clone(), merged.runs.to_string(), histogram.len().to_string()];
+        match RttSummary::from_histogram(histogram, &DEFAULT_PERCENTILES) {
+            Some(summary) => {
+                record.extend([summary.min.to_string(), summary.mean.to_string(), summary.stddev.to_string()]);
+                record.extend(summary.percentiles.iter().map(|(_, value)| value.to_string()));
+                record.push(summary.max.to_string());
+            }
+            None => record.extend(std::iter::repeat_n(String::new(), 4 + DEFAULT_PERCENTILES.len())),
+        }
+        wtr.write_record(&record)?;
+        let file_name = output_dir.join(format!("merged_rtt_histogram_{}_{}.hlog", file_name_part(&merged.server_name), merged.mode));
+        let runs = merged.runs.to_string();
+        histogram.write_log(&file_name, &[("name", &merged.server_name), ("server", &merged.server_addr), ("mode", &merged.mode), ("runs", &runs)])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Columns of the aggregates that tell the groups apart.
+const GROUP_COLUMNS: [&str; 7] = ["Server Name", "Server Address", "Speedtest-Mode", "Payload Size", "Bitrate", "Interval", "Duration"];
+
+/// One metric of a group of runs aggregated across the iterations, a row of the tidy output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    /// Values of the [`GROUP_COLUMNS`]. The swept parameters are taken from the run's plan point and are empty
+    /// where the mode doesn't use them; runs outside a plan only have a payload size, if they have a manifest.
+    pub group: Vec<String>,
+    pub metric: String,
+    pub unit: &'static str,
+    pub summary: SampleSummary,
+}
+
+/// Unit of a metric in the aggregates and the factor from the unit of the result files, which hold times
+/// in microseconds.
+fn unit(metric: &str) -> (&'static str, f64) {
+    match metric {
+        "Variance RTT" => ("ms^2", 1e-6),
+        "Total Downtime" | "Longest Outage" => ("ms", 1e-3),
+        metric if metric.contains("RTT") && metric != "Responses" => ("ms", 1e-3),
+        _ => ("", 1.0),
+    }
+}
+
+/// Aggregates every metric of the runs in `results_dir` across the iterations, per server and parameter tuple
+/// of the experiment plan.
+///
+/// Servers keep the name they were tested under. Runs against unnamed servers, or from before servers had
+/// names, are named after `labels`, falling back to the labels in the config of the run's manifest and then
+/// to the address. Empty cells are skipped like in [`ResultTable::group_mean`].
+pub fn aggregate(results_dir: &Path, labels: &BTreeMap<String, String>) -> Result<Vec<Aggregate>, Box<dyn Error>> {
+    let paths = result_files(results_dir, &|path| {
+        path.extension().is_some_and(|ext| ext == "csv") && path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with("rtt_times_"))
+    })?;
+    let mut metrics: Vec<String> = Vec::new();
+    let mut groups: BTreeMap<Vec<String>, BTreeMap<usize, Vec<f64>>> = BTreeMap::new();
+    for path in paths {
+        let manifest = path.parent().and_then(|dir| RunManifest::read(dir).ok());
+        let mut rdr = csv::Reader::from_path(&path)?;
+        let headers = rdr.headers()?.iter().map(str::to_string).collect::<Vec<_>>();
+        let column = |name: &str| headers.iter().position(|column| column == name);
+        let (name_column, addr_column, mode_column) = (column("Server Name"), column("Server Address"), column("Speedtest-Mode"));
+        for record in rdr.records() {
+            let record = record?;
+            let cell = |column: Option<usize>| column.and_then(|column| record.get(column)).unwrap_or_default().to_string();
+            let server_addr = cell(addr_column);
+            let name = Some(cell(name_column))
+                .filter(|name| !name.is_empty() && *name != server_addr)
+                .or_else(|| labels.get(&server_addr).cloned())
+                .or_else(|| manifest.as_ref().and_then(|manifest| manifest.config["server_labels"][&server_addr].as_str().map(str::to_string)))
+                .unwrap_or_else(|| server_addr.clone());
+            let mut key = vec![name, server_addr, cell(mode_column)];
+            key.extend(parameters(manifest.as_ref()));
+            let values = groups.entry(key).or_default();
+            for (column, value) in headers.iter().zip(record.iter()) {
+                if ID_COLUMNS.contains(&column.as_str()) || KEY_COLUMNS.contains(&column.as_str()) {
+                    continue;
+                }
+                let metric = match metrics.iter().position(|metric| metric == column) {
+                    Some(metric) => metric,
+                    None => {
+                        metrics.push(column.clone());
+                        metrics.len() - 1
+                    }
+                };
+                if let Ok(value) = value.parse::<f64>() {
+                    values.entry(metric).or_default().push(value);
+                }
+            }
+        }
+    }
+
+    let mut aggregates = Vec::new();
+    for (group, values) in groups {
+        for (metric, values) in values {
+            let (unit, factor) = unit(&metrics[metric]);
+            let values = values.iter().map(|value| value * factor).collect::<Vec<_>>();
+            if let Some(summary) = SampleSummary::from_values(&values) {
+                aggregates.push(Aggregate { group: group.clone(), metric: metrics[metric].clone(), unit, summary });
+            }
+        }
+    }
+    Ok(aggregates)
+}
+
+/// Payload size, bitrate, interval and duration of a run as far as its manifest tells them.
+fn parameters(manifest: Option<&RunManifest>) -> [String; 4] {
+    let value = |value: &Value| match value {
+        Value::Null => String::new(),
+        value => value.to_string(),
+    };
+    match manifest {
+        Some(RunManifest { point: Some(point), .. }) => [value(&point["payload_size"]), value(&point["bitrate"]), value(&point["interval"]), value(&point["duration"])],
+        Some(manifest) => [value(&manifest.test["payload_size"]), String::new(), String::new(), String::new()],
+        None => Default::default(),
+    }
+}
+
+const AGGREGATE_COLUMNS: [&str; 8] = ["Metric", "Unit", "Runs", "Mean", "Median", "Standard Deviation", "95% CI Low", "95% CI High"];
+
+fn aggregate_cells(aggregate: &Aggregate) -> Vec<String> {
+    let float = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+    let summary = &aggregate.summary;
+    let mut cells = aggregate.group.clone();
+    cells.extend([aggregate.metric.clone(), aggregate.unit.to_string(), summary.count.to_string(), summary.mean.to_string(), summary.median.to_string()]);
+    cells.extend([float(summary.stddev), float(summary.ci95.map(|ci| ci.0)), float(summary.ci95.map(|ci| ci.1))]);
+    cells
+}
+
+/// Writes the aggregates as a tidy CSV file, one row per group and metric.
+pub fn write_aggregates_csv(aggregates: &[Aggregate], path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(GROUP_COLUMNS.iter().chain(AGGREGATE_COLUMNS.iter()))?;
+    for aggregate in aggregates {
+        wtr.write_record(aggregate_cells(aggregate))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Writes the aggregates as a JSON array of records with the columns of the CSV file as keys, numbers as
+/// numbers and undefined values as `null`.
+pub fn write_aggregates_json(aggregates: &[Aggregate], path: &Path) -> Result<(), Box<dyn Error>> {
+    let records = aggregates
+        .iter()
+        .map(|aggregate| {
+            let summary = &aggregate.summary;
+            let mut record = GROUP_COLUMNS.iter().zip(&aggregate.group).map(|(column, value)| (column.to_string(), json!(value))).collect::<serde_json::Map<_, _>>();
+            for (column, value) in AGGREGATE_COLUMNS.iter().zip([
+                json!(aggregate.metric),
+                json!(aggregate.unit),
+                json!(summary.count),
+                json!(summary.mean),
+                json!(summary.median),
+                json!(summary.stddev),
+                json!(summary.ci95.map(|ci| ci.0)),
+                json!(summary.ci95.map(|ci| ci.1)),
+            ]) {
+                record.insert(column.to_string(), value);
+            }
+            Value::Object(record)
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_writer_pretty(fs::File::create(path)?, &records)?;
+    Ok(())
+}
+
+/// Merges the results in `results_dir` and writes the merged and grouped tables and the aggregates across
+/// iterations into `output_dir`, naming the servers after `labels`.
+pub fn analyze(results_dir: &Path, output_dir: &Path, labels: &BTreeMap<String, String>) -> Result<(), Box<dyn Error>> {
+    let merged = ResultTable::read_dir(results_dir)?;
+    fs::create_dir_all(output_dir)?;
+    merged.write_csv(&output_dir.join("merged_rtt_times.csv"))?;
+    merged.group_mean().write_csv(&output_dir.join("grouped_rtt_times.csv"))?;
+    let aggregates = aggregate(results_dir, labels)?;
+    write_aggregates_csv(&aggregates, &output_dir.join("aggregated_results.csv"))?;
+    write_aggregates_json(&aggregates, &output_dir.join("aggregated_results.json"))?;
+    let histograms = merge_histograms(results_dir)?;
+    if !histograms.is_empty() {
+        write_histograms(&histograms, output_dir)?;
+    }
+    let ledger_path = results_dir.join(LEDGER_FILE);
+    if ledger_path.exists() {
+        Ledger::open(&ledger_path)?.write_status_csv(&output_dir.join(STATUS_FILE))?;
+    }
+    info!("Wrote merged and grouped results to {}", output_dir.display());
+    Ok(())
+}
+
+/// Prints the grouped results in `results_dir` as a table with times in milliseconds and the RTT variance in
+/// ms^2, like the aggregates, followed by the experiments that didn't complete if there is a campaign ledger.
+pub fn report(results_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let grouped = ResultTable::read_dir(results_dir)?.group_mean();
+    println!("{}", grouped.columns.join(" | "));
+    for row in &grouped.rows {
+        let values = row.iter().enumerate().map(|(i, value)| {
+            let (unit, factor) = unit(&grouped.columns[i]);
+            match value.parse::<f64>() {
+                Ok(value) if !unit.is_empty() => format!("{:.3} {}", value * factor, unit),
+                _ => value.clone(),
+            }
+        });
+        println!("{}", values.collect::<Vec<_>>().join(" | "));
+    }
+    let ledger_path = results_dir.join(LEDGER_FILE);
+    if ledger_path.exists() {
+        let ledger = Ledger::open(&ledger_path)?;
+        let incomplete = ledger.incomplete();
+        println!();
+        if incomplete.is_empty() {
+            println!("All {} experiments completed", ledger.points().len());
+        } else {
+            println!("Incomplete experiments ({} of {}):", incomplete.len(), ledger.points().len());
+            for (iteration, point, status) in incomplete {
+                println!("{} | {} | {} after {} attempts", iteration, point, status.name(), ledger.attempts(iteration, &point));
+            }
+        }
+    }
+    Ok(())
+}
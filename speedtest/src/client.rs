@@ -0,0 +1,1900 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+use log::{debug, info, warn, error};
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::fs::OpenOptions;
+use threadpool::ThreadPool;
+use std::io;
+use crate::udp_application::{UDPApplication, UDPApplicationEnum};
+use std::sync::{mpsc, Mutex};
+use crate::config::{ClientConfig, ExperimentTarget};
+use crate::collector::StatsCollector;
+use crate::delivery::DeliveryStats;
+use crate::events::{Event, EventImpact, EventLog, MarkerListener, MarkerSource};
+use crate::histogram::LatencyHistogram;
+use crate::hooks::{run_hook, Hook, HookStage, ScheduledHooks};
+use crate::interval::{IntervalReport, IntervalReporter};
+use crate::iperf;
+use crate::jitter::JitterStats;
+use crate::manifest::RunManifest;
+use crate::outage::{OutageConfig, OutageReport};
+use crate::ledger::{run_campaign, Ledger, PointStatus, STATUS_FILE};
+use crate::plan::{ExperimentPlan, PlanPoint};
+use crate::stats::{self, RttSummary, DEFAULT_PERCENTILES};
+use crate::timeline::{TimelineEntry, TimelineWriter};
+use crate::transport::{Network, SystemNetwork, ThreadRole, Transport};
+
+/// Directory the results of the configured tests are written to.
+pub const RESULTS_DIR: &str = "results";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpeedtestEnum {
+    ByDurationCustomBitrate,
+    ByDuration,
+    ByPacketCount,
+    Ping,
+}
+
+impl SpeedtestEnum {
+    pub fn from_string(speedtest_mode: &str) -> SpeedtestEnum {
+        match speedtest_mode {
+            "duration_custom_bitrate" => SpeedtestEnum::ByDurationCustomBitrate,
+            "duration" => SpeedtestEnum::ByDuration,
+            "packet_count" => SpeedtestEnum::ByPacketCount,
+            "ping" => SpeedtestEnum::Ping,
+            _ => SpeedtestEnum::ByDuration,
+        }
+    }
+    pub fn to_string(&self) -> &str {
+        match self {
+            SpeedtestEnum::ByDurationCustomBitrate => "duration_custom_bitrate",
+            SpeedtestEnum::ByDuration => "duration",
+            SpeedtestEnum::ByPacketCount => "packet_count",
+            SpeedtestEnum::Ping => "ping",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PacketStats {
+    pub session_id: u16,
+    pub sent_time: u128,
+    pub received_time: u128,
+    /// Server receive and send time of the request, 0 if the server didn't report them.
+    pub server_received_time: u128,
+    pub server_sent_time: u128,
+    /// Extra copies of the response received after the first one.
+    pub duplicates: u32,
+}
+
+/// Server receive and send time of a request in microseconds since the Unix epoch.
+pub type ServerTimestamps = (u128, u128);
+
+/// Send time of the latest probe per session ID, shared by the sender and receiver threads of a test so
+/// that the receivers can time every response as it arrives, in constant memory.
+struct SendTimes {
+    /// Send time per session ID with the `ANSWERED` and `DUPLICATED` flags, 0 before the first probe.
+    slots: Vec<AtomicU64>,
+}
+
+/// Response to a lookup in the [`SendTimes`].
+enum Answer {
+    /// First answer to the probe sent at the given time.
+    First(u128),
+    /// Another answer to an answered probe, `true` for the first extra copy.
+    Duplicate(bool),
+    Unsolicited,
+}
+
+impl SendTimes {
+    const ANSWERED: u64 = 1 << 63;
+    const DUPLICATED: u64 = 1 << 62;
+    const TIME: u64 = Self::DUPLICATED - 1;
+
+    fn new() -> Self {
+        SendTimes { slots: (0..=u16::MAX).map(|_| AtomicU64::new(0)).collect() }
+    }
+
+    fn sent(&self, session_id: u16, sent_time: u128) {
+        self.slots[session_id as usize].store(sent_time as u64 & Self::TIME, Ordering::Relaxed);
+    }
+
+    fn answer(&self, session_id: u16) -> Answer {
+        let slot = &self.slots[session_id as usize];
+        let previous = slot.fetch_or(Self::ANSWERED, Ordering::Relaxed);
+        if previous & Self::TIME == 0 {
+            Answer::Unsolicited
+        } else if previous & Self::ANSWERED == 0 {
+            Answer::First((previous & Self::TIME) as u128)
+        } else {
+            Answer::Duplicate(slot.fetch_or(Self::DUPLICATED, Ordering::Relaxed) & Self::DUPLICATED == 0)
+        }
+    }
+}
+
+impl fmt::Debug for SendTimes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendTimes").finish_non_exhaustive()
+    }
+}
+
+/// Timestamps recorded by a single sender or receiver thread without any locking, see [`RTTTimes::from_logs`].
+///
+/// The logs of a [`PacketRecorder`] time the responses right away into a histogram and only keep the
+/// timestamps of every packet if the test keeps a packet log, see [`TestSpec::packet_log`].
+#[derive(Debug, Clone)]
+pub struct PacketLog {
+    sent: Vec<(u16, u128)>,
+    received: Vec<(u16, u128, Option<ServerTimestamps>)>,
+    keep_packets: bool,
+    /// Live consumers the timestamps are streamed to as well, e.g. a timeline.
+    sinks: Vec<mpsc::Sender<TimelineEntry>>,
+    send_times: Option<Arc<SendTimes>>,
+    /// RTTs of the responses this thread received.
+    histogram: LatencyHistogram,
+    /// Probes sent and responses received by this thread.
+    delivery: DeliveryStats,
+}
+
+impl Default for PacketLog {
+    fn default() -> Self {
+        PacketLog {
+            sent: Vec::new(),
+            received: Vec::new(),
+            keep_packets: true,
+            sinks: Vec::new(),
+            send_times: None,
+            histogram: LatencyHistogram::new(TestSpec::default().histogram_precision),
+            delivery: DeliveryStats::default(),
+        }
+    }
+}
+
+impl PacketLog {
+    pub fn sent(&mut self, session_id: u16, sent_time: u128) {
+        self.delivery.sent += 1;
+        if let Some(send_times) = &self.send_times {
+            send_times.sent(session_id, sent_time);
+        }
+        if self.keep_packets {
+            self.sent.push((session_id, sent_time));
+        }
+        self.stream(TimelineEntry::Sent { session_id, time: sent_time });
+    }
+
+    pub fn received(&mut self, session_id: u16, received_time: u128) {
+        self.received_with_server_timestamps(session_id, received_time, None);
+    }
+
+    /// Records a response together with the server's receive and send time of the request.
+    pub fn received_with_server_timestamps(&mut self, session_id: u16, received_time: u128, server_timestamps: Option<ServerTimestamps>) {
+        if let Some(send_times) = &self.send_times {
+            match send_times.answer(session_id) {
+                Answer::First(sent_time) => {
+                    self.delivery.delivered += 1;
+                    self.histogram.record(received_time.saturating_sub(sent_time) as u64);
+                }
+                Answer::Duplicate(first) => {
+                    self.delivery.duplicated += first as u64;
+                    self.delivery.duplicates += 1;
+                }
+                Answer::Unsolicited => self.delivery.unsolicited += 1,
+            }
+        }
+        if self.keep_packets {
+            self.received.push((session_id, received_time, server_timestamps));
+        }
+        self.stream(TimelineEntry::Received { session_id, time: received_time, server_timestamps });
+    }
+
+    fn response(&mut self, response: &UDPApplication, received_time: u128) {
+        self.received_with_server_timestamps(response.session_id, received_time, response.server_timestamps());
+    }
+
+    fn stream(&self, entry: TimelineEntry) {
+        for sink in &self.sinks {
+            let _ = sink.send(entry.clone());
+        }
+    }
+}
+
+/// Collects the packet logs of the threads of a test, streaming the timestamps to live consumers like a
+/// timeline or interval reports.
+#[derive(Debug, Clone)]
+pub struct PacketRecorder {
+    logs: Arc<Mutex<Vec<PacketLog>>>,
+    sinks: Vec<mpsc::Sender<TimelineEntry>>,
+    send_times: Arc<SendTimes>,
+    histogram_precision: u8,
+    keep_packets: bool,
+}
+
+/// What the threads of a test recorded, see [`PacketRecorder::finish`].
+#[derive(Debug, Clone)]
+pub struct RecordedPackets {
+    /// RTTs of all answered probes.
+    pub histogram: LatencyHistogram,
+    /// Probes sent, answered, lost and duplicated, without the reordering.
+    pub delivery: DeliveryStats,
+    /// Timestamps of every packet, empty unless the test keeps a packet log.
+    pub rtt_times: RTTTimes,
+}
+
+impl PacketRecorder {
+    pub fn new(spec: &TestSpec, sinks: Vec<mpsc::Sender<TimelineEntry>>) -> Self {
+        PacketRecorder {
+            logs: Arc::default(),
+            sinks,
+            send_times: Arc::new(SendTimes::new()),
+            histogram_precision: spec.histogram_precision,
+            keep_packets: spec.keeps_packets(),
+        }
+    }
+
+    /// Log for a new sender or receiver thread.
+    pub fn log(&self) -> PacketLog {
+        PacketLog {
+            keep_packets: self.keep_packets,
+            sinks: self.sinks.clone(),
+            send_times: Some(self.send_times.clone()),
+            histogram: LatencyHistogram::new(self.histogram_precision),
+            ..PacketLog::default()
+        }
+    }
+
+    /// Hands in the log of a finished thread.
+    pub fn push(&self, mut log: PacketLog) {
+        log.sinks.clear();
+        self.logs.lock().unwrap().push(log);
+    }
+
+    /// Merges the logs handed in by the threads and closes the streams to the live consumers.
+    pub fn finish(self) -> RecordedPackets {
+        let logs = std::mem::take(&mut *self.logs.lock().unwrap());
+        let mut histogram = LatencyHistogram::new(self.histogram_precision);
+        let mut delivery = DeliveryStats::default();
+        for log in &logs {
+            histogram.merge(&log.histogram);
+            delivery.merge(&log.delivery);
+        }
+        delivery.lost = delivery.sent.saturating_sub(delivery.delivered);
+        let rtt_times = if self.keep_packets { RTTTimes::from_logs(logs) } else { RTTTimes::new() };
+        RecordedPackets { histogram, delivery, rtt_times }
+    }
+}
+
+/// Per-packet timestamps of a test, in the order the packets were sent.
+///
+/// Session IDs wrap around after 65536 packets, so every lookup by session ID refers to the latest packet
+/// sent with that ID.
+#[derive(Debug, Clone, Default)]
+pub struct RTTTimes {
+    pub rtt_times: Vec<PacketStats>,
+    /// Position of the latest packet per session ID in `rtt_times`.
+    index: HashMap<u16, usize>,
+}
+
+impl RTTTimes {
+    pub fn new() -> Self {
+        RTTTimes {
+            rtt_times: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Merges the timestamps recorded by the sender and receiver threads of a test.
+    ///
+    /// Each response is matched to the latest request with its session ID sent before it arrived. Only the
+    /// first copy of a duplicated response is timed, later copies are counted in `duplicates`. Responses
+    /// without a request are added without a sent time.
+    pub fn from_logs(logs: Vec<PacketLog>) -> Self {
+        let mut events = Vec::with_capacity(logs.iter().map(|log| log.sent.len() + log.received.len()).sum());
+        for log in logs {
+            events.extend(log.sent.into_iter().map(|(session_id, time)| (time, false, session_id, None)));
+            events.extend(log.received.into_iter().map(|(session_id, time, server_timestamps)| (time, true, session_id, server_timestamps)));
+        }
+        // Requests sort before responses with the same timestamp
+        events.sort_by_key(|(time, received, _, _)| (*time, *received));
+
+        let mut rtt_times = RTTTimes::new();
+        for (time, received, session_id, server_timestamps) in events {
+            if !received {
+                rtt_times.add(session_id, time, 0);
+            } else {
+                match rtt_times.index.get(&session_id) {
+                    Some(&i) if rtt_times.rtt_times[i].received_time == 0 => {
+                        let packet = &mut rtt_times.rtt_times[i];
+                        packet.received_time = time;
+                        if let Some((server_received_time, server_sent_time)) = server_timestamps {
+                            packet.server_received_time = server_received_time;
+                            packet.server_sent_time = server_sent_time;
+                        }
+                    }
+                    Some(&i) => rtt_times.rtt_times[i].duplicates += 1,
+                    None => rtt_times.add(session_id, 0, time),
+                }
+            }
+        }
+        rtt_times
+    }
+
+    pub fn is_session_id_present(&self, session_id: u16) -> bool {
+        self.index.contains_key(&session_id)
+    }
+
+    pub fn add(&mut self, session_id: u16, sent_time: u128, received_time: u128) {
+        self.index.insert(session_id, self.rtt_times.len());
+        self.rtt_times.push(PacketStats {
+            session_id,
+            sent_time,
+            received_time,
+            server_received_time: 0,
+            server_sent_time: 0,
+            duplicates: 0,
+        });
+    }
+
+    pub fn set_received_time(&mut self, session_id: u16, received_time: u128) {
+        if let Some(&i) = self.index.get(&session_id) {
+            self.rtt_times[i].received_time = received_time;
+        }
+    }
+
+    pub fn set_sent_time(&mut self, session_id: u16, sent_time: u128) {
+        if let Some(&i) = self.index.get(&session_id) {
+            self.rtt_times[i].sent_time = sent_time;
+        }
+    }
+
+    pub fn get_rtt(&self, session_id: u16) -> u128 {
+        match self.index.get(&session_id).map(|&i| &self.rtt_times[i]) {
+            Some(packet) if packet.sent_time != 0 && packet.received_time != 0 => packet.received_time - packet.sent_time,
+            _ => 0,
+        }
+    }
+
+    pub fn get_rtts(&self) -> Vec<u128> {
+        let mut rtt_values = Vec::new();
+        for stats in &self.rtt_times {
+            if stats.received_time == 0 || stats.sent_time == 0 {
+                continue;
+            }
+            let rtt = stats.received_time - stats.sent_time;
+            rtt_values.push(rtt);
+        }
+        rtt_values
+    }
+
+    /// Mean RTT of the answered packets, `None` if no packet was answered.
+    pub fn get_average_rtt(&self) -> Option<f64> {
+        stats::mean(&self.get_rtts())
+    }
+
+    pub fn get_median_rtt(&self) -> Option<u128> {
+        self.get_percentile_rtt(50.0)
+    }
+
+    pub fn get_min_rtt(&self) -> Option<u128> {
+        self.get_rtts().into_iter().min()
+    }
+
+    pub fn get_max_rtt(&self) -> Option<u128> {
+        self.get_rtts().into_iter().max()
+    }
+
+    /// Population variance of the RTTs of the answered packets.
+    pub fn get_variance_rtt(&self) -> Option<f64> {
+        stats::variance(&self.get_rtts())
+    }
+
+    pub fn get_stddev_rtt(&self) -> Option<f64> {
+        stats::stddev(&self.get_rtts())
+    }
+
+    /// Nearest-rank percentile of the RTTs, with `percentile` in percent, e.g. 99.9.
+    pub fn get_percentile_rtt(&self, percentile: f64) -> Option<u128> {
+        let mut rtt_values = self.get_rtts();
+        rtt_values.sort_unstable();
+        stats::percentile(&rtt_values, percentile)
+    }
+
+    /// Summary of the exact RTTs, see [`TestResult::summary`] for the constant-memory variant.
+    pub fn summary(&self, percentiles: &[f64]) -> Option<RttSummary> {
+        RttSummary::from_samples(&self.get_rtts(), percentiles)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitrateScale {
+    Bps = 1,
+    Kbps = 1024,
+    Mbps = 1048576,
+    Gbps = 1073741824,
+    
+}
+
+pub fn get_bitrate_scale(bitrate_scale: &str) -> BitrateScale {
+    match bitrate_scale {
+        "bps" => BitrateScale::Bps,
+        "kbps" => BitrateScale::Kbps,
+        "Kbps" => BitrateScale::Kbps,
+        "K" => BitrateScale::Kbps,
+        "mbps" => BitrateScale::Mbps,
+        "Mbps" => BitrateScale::Mbps,
+        "M" => BitrateScale::Mbps,
+        "gbps" => BitrateScale::Gbps,
+        "Gbps" => BitrateScale::Gbps,
+        "G" => BitrateScale::Gbps,
+        _ => BitrateScale::Bps,
+    }
+}
+
+fn resolve(addr: &str) -> io::Result<SocketAddr> {
+    addr.to_socket_addrs()?.next().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Couldn't resolve {}", addr)))
+}
+
+/// Asks the server for the seed of its impairment RNG, so a suspicious run can be replayed.
+pub(crate) fn query_server_seed(spec: &TestSpec, network: &dyn Network) -> Option<u64> {
+    let clock = network.clock();
+    let _guard = clock.register_thread(ThreadRole::Foreground).enter();
+    let server_addr = resolve(&spec.server_addr).ok()?;
+    let socket = network.bind(&spec.client_addr).ok()?;
+    socket.set_read_timeout(Some(spec.receive_timeout.min(Duration::from_secs(1)))).ok()?;
+    let request_packet = UDPApplication {
+        type_field: UDPApplicationEnum::INFO as u16,
+        session_id: 0,
+        test_payload: Vec::new(),
+    };
+
+    let mut buf = [0; 1024];
+    'attempts: for _ in 0..3 {
+        socket.send_to(&request_packet.to_bytes(), server_addr).ok()?;
+        while let Ok((len, _addr)) = socket.recv_from(&mut buf) {
+            if len < 4 {
+                continue;
+            }
+            let packet = UDPApplication::new(&buf[..len]);
+            if packet.type_field == UDPApplicationEnum::SEED as u16 && packet.test_payload.len() == 8 {
+                let mut seed = [0; 8];
+                seed.copy_from_slice(&packet.test_payload);
+                return Some(u64::from_be_bytes(seed));
+            }
+            // Servers without seed support echo the request as a regular response, asking again won't help
+            break 'attempts;
+        }
+    }
+    info!("{} at {} didn't report its RNG seed", spec.server_name(), spec.server_addr);
+    None
+}
+
+fn speedtest_simple_ping(spec: &TestSpec, network: &dyn Network, recorder: &PacketRecorder) -> io::Result<(usize, usize)> {
+    info!("Starting UDP Speedtest client in duration mode");
+    info!("Sending packets with payload size {} bytes for {:?}", spec.payload_size, spec.duration);
+
+    let clock = network.clock();
+    // Keep the clock from advancing until all threads are started
+    let setup = clock.register_thread(ThreadRole::Foreground).enter();
+
+    // Create a thread pool
+    let pool = ThreadPool::new(2); // Adjust the number of threads based on your CPU cores
+    let counters = spawn_ping(spec, network, recorder, &pool, clock.now_micros())?;
+
+    drop(setup);
+    pool.join();
+
+    let total_time_value = counters.total_time.load(Ordering::Relaxed);
+    let total_bytes_sent_value = counters.bytes_sent.load(Ordering::Relaxed);
+    let total_bytes_received_value = counters.bytes_received.load(Ordering::Relaxed);
+
+    let throughput_sent = total_bytes_sent_value as f64 / total_time_value as f64;
+    let throughput_received = total_bytes_received_value as f64 / total_time_value as f64;
+
+    debug!("Total bytes sent: {}", total_bytes_sent_value);
+    debug!("Total bytes received: {}", total_bytes_received_value);
+    debug!("Throughput sent: {:.2} bytes/sec ({:.2} MBps)", throughput_sent, throughput_sent / 1024.0 / 1024.0);
+    debug!("Throughput received: {:.2} bytes/sec ({:.2} MBps)", throughput_received, throughput_received / 1024.0 / 1024.0);
+
+    Ok((total_bytes_sent_value, total_bytes_received_value))
+
+}
+
+/// Traffic counters of the sender and receiver thread of a ping test.
+#[derive(Debug, Default)]
+pub(crate) struct PingCounters {
+    pub total_time: AtomicUsize,
+    pub bytes_sent: AtomicUsize,
+    pub bytes_received: AtomicUsize,
+}
+
+/// Binds a socket of its own for the ping test of `spec` and starts its receiver and sender thread on `pool`.
+///
+/// The sender probes at `start_time` plus multiples of the interval, so tests started with the same
+/// `start_time` send their probes at the same time. The caller keeps the clock from advancing until all
+/// threads are started and joins the pool.
+pub(crate) fn spawn_ping(spec: &TestSpec, network: &dyn Network, recorder: &PacketRecorder, pool: &ThreadPool, start_time: u128) -> io::Result<Arc<PingCounters>> {
+    let duration = spec.duration;
+    let receive_timeout = spec.receive_timeout;
+    let interval = spec.interval;
+
+    let server_addr = resolve(&spec.server_addr)?;
+    let client_addr = spec.client_addr.clone();
+
+    info!("Connecting to {} at {}", spec.server_name(), server_addr);
+
+    // Bind the client socket
+    debug!("Binding to client address: {}", client_addr);
+    let socket: Arc<dyn Transport> = Arc::from(network.bind(&client_addr)?);
+    let clock = network.clock();
+    info!("Socket bound to address: {}", client_addr);
+    socket.set_read_timeout(Some(spec.receive_timeout))?;
+
+    let payload = vec![0u8; spec.payload_size];
+
+    let request_type = spec.request_type();
+    let counters = Arc::new(PingCounters::default());
+
+    // Get the list of available CPU cores
+    let cores = core_affinity::get_core_ids().expect("Couldn't get core IDs");
+
+    // Thread for receiving packets
+    for (_, core_id) in cores.iter().enumerate().take(1) {
+        let core_id = *core_id; // Copy the core ID to move into the thread
+        let socket_clone = Arc::clone(&socket);
+        let counters_clone = Arc::clone(&counters);
+        let recorder_clone = recorder.clone();
+
+        debug!("Starting receiving thread for core: {:?}", core_id);
+
+        let clock_clone = Arc::clone(&clock);
+        let token = clock.register_thread(ThreadRole::Foreground);
+        pool.execute(move || {
+            let _guard = token.enter();
+            let mut packet_log = recorder_clone.log();
+            // Pin this thread to a specific core
+            core_affinity::set_for_current(core_id);
+
+            let mut buf = [0; 131072];
+            while clock_clone.elapsed_since(start_time) < duration + receive_timeout {
+                debug!("Waiting to receive data...");
+                match socket_clone.recv_from(&mut buf) {
+                    Ok((len, _addr)) => {
+                        debug!("Received data: {} bytes", len);
+                        // debug!("Received response: {:?}", &buf[..len]);
+                        let response_packet = UDPApplication::new(&buf[..len]);
+                        debug!("Received response: {}", response_packet.summary());
+                        counters_clone.bytes_received.fetch_add(len, Ordering::Relaxed);
+                        let receive_time = clock_clone.now_micros();
+                        debug!("Receive time: {}", receive_time);
+                        packet_log.response(&response_packet, receive_time);
+                    }
+                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        // Responses still in flight are collected until the server stays silent after the test
+                        if clock_clone.elapsed_since(start_time) >= duration {
+                            break;
+                        }
+                        debug!("No data received yet, continuing...");
+                    }
+                    Err(e) => {
+                        error!("Error receiving packet: {:?}", e);
+                    }
+                }
+            }
+            recorder_clone.push(packet_log);
+        });
+    }
+
+    let counters_clone = Arc::clone(&counters);
+    
+    let session_id_counter = Arc::new(AtomicUsize::new(0));
+    let recorder_clone = recorder.clone();
+
+    // Thread for sending packets
+    let clock_clone = Arc::clone(&clock);
+    let token = clock.register_thread(ThreadRole::Foreground);
+    pool.execute(move || {
+        let _guard = token.enter();
+        let mut packet_log = recorder_clone.log();
+        let mut next_send_offset = Duration::ZERO;
+        while clock_clone.elapsed_since(start_time) < duration {
+            let session_id_counter_clone = Arc::clone(&session_id_counter);
+            
+            let request_packet = UDPApplication {
+                type_field: request_type,
+                session_id: session_id_counter_clone.fetch_add(1, Ordering::SeqCst) as u16, // Session ID can be set as needed
+                test_payload: payload.clone(),
+            };
+            let packet_start_time = clock_clone.now_micros();
+            let now = clock_clone.now_micros();
+            debug!("Sending request: {}", request_packet.summary());
+            let packet_bytes = request_packet.to_bytes();
+            packet_log.sent(request_packet.session_id, now);
+            socket.send_to(&packet_bytes, server_addr).expect("Couldn't send data");
+
+            let elapsed_time = clock_clone.elapsed_since(packet_start_time).as_micros() as usize;
+            counters_clone.total_time.fetch_add(elapsed_time, Ordering::Relaxed);
+            counters_clone.bytes_sent.fetch_add(packet_bytes.len(), Ordering::Relaxed);
+
+            // Sleep until the next send time to control the bitrate without accumulating drift
+            next_send_offset += interval;
+            let elapsed = clock_clone.elapsed_since(start_time);
+            if next_send_offset > elapsed {
+                clock_clone.sleep(next_send_offset - elapsed);
+            }
+        }
+        recorder_clone.push(packet_log);
+    });
+
+    Ok(counters)
+}
+
+fn speedtest_by_duration(spec: &TestSpec, network: &dyn Network, recorder: &PacketRecorder) -> io::Result<(usize, usize)> {
+    let duration = spec.duration;
+    let receive_timeout = spec.receive_timeout;
+    let payload_size = spec.payload_size;
+    info!("Starting UDP Speedtest client in duration mode");
+    info!("Sending packets with payload size {} bytes for {:?}", payload_size, duration);
+
+    let server_addr = resolve(&spec.server_addr)?;
+    let client_addr = spec.client_addr.clone();
+
+    info!("Connecting to {} at {}", spec.server_name(), server_addr);
+    
+    // Bind the client socket
+    debug!("Binding to client address: {}", client_addr);
+    let socket: Arc<dyn Transport> = Arc::from(network.bind(&client_addr)?);
+    let clock = network.clock();
+    // Keep the clock from advancing until all threads are started
+    let setup = clock.register_thread(ThreadRole::Foreground).enter();
+    socket.set_read_timeout(Some(spec.receive_timeout))?;
+
+    let payload = vec![0u8; payload_size];
+
+    let request_type = spec.request_type();
+    let total_time = Arc::new(AtomicUsize::new(0));
+    let total_bytes_sent = Arc::new(AtomicUsize::new(0));
+    let total_bytes_received = Arc::new(AtomicUsize::new(0));
+    let session_id_counter = Arc::new(AtomicUsize::new(0));
+
+    // Create a thread pool
+    let pool = ThreadPool::new(8); // Adjust the number of threads based on your CPU cores
+
+    // Get the list of available CPU cores
+    let cores = core_affinity::get_core_ids().expect("Couldn't get core IDs");
+
+    // Thread for receiving packets
+    for (_, core_id) in cores.iter().enumerate().take(4) {
+        let core_id = *core_id; // Copy the core ID to move into the thread
+        let socket_clone = Arc::clone(&socket);
+        let total_bytes_received_clone = Arc::clone(&total_bytes_received);
+        let total_time_clone = Arc::clone(&total_time);
+        let recorder_clone = recorder.clone();
+    
+        let clock_clone = Arc::clone(&clock);
+        let token = clock.register_thread(ThreadRole::Foreground);
+        pool.execute(move || {
+            let _guard = token.enter();
+            let mut packet_log = recorder_clone.log();
+            // Pin this thread to a specific core
+            core_affinity::set_for_current(core_id);
+    
+            let mut buf = [0; 131072];
+            let start_time = clock_clone.now_micros();
+            while clock_clone.elapsed_since(start_time) < duration + receive_timeout {
+                match socket_clone.recv_from(&mut buf) {
+                    Ok((len, _addr)) => {
+                        let response_packet = UDPApplication::new(&buf[..len]);
+                        let receive_time = clock_clone.now_micros();
+                        packet_log.response(&response_packet, receive_time);
+                        total_bytes_received_clone.fetch_add(len, Ordering::Relaxed);
+                    }
+                    
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        if clock_clone.elapsed_since(start_time) >= duration {
+                            break;
+                        }
+                        // Handle the WouldBlock error by retrying
+                        clock_clone.sleep(Duration::from_millis(10)); // Optional: Add a small sleep to avoid busy-waiting
+                    }
+                    Err(e) => {
+                        error!("Error receiving data: {}", e);
+                        break;
+                    }
+                }
+            
+            total_time_clone.fetch_add(clock_clone.elapsed_since(start_time).as_secs() as usize, Ordering::SeqCst);
+            }
+            recorder_clone.push(packet_log);
+        });
+    }
+
+    for (_, core_id) in cores.iter().enumerate().take(4) {
+        let core_id = *core_id; // Copy the core ID to move into the thread
+        let socket_clone = Arc::clone(&socket);
+        let total_bytes_sent_clone = Arc::clone(&total_bytes_sent);
+        let total_time_clone = Arc::clone(&total_time);
+        let server_addr_clone = server_addr;
+        let payload_clone = payload.clone(); // Clone payload for each thread
+        let session_id_counter_clone = Arc::clone(&session_id_counter); // Clone session_id_counter for each thread
+        let recorder_clone = recorder.clone();
+    
+        let clock_clone = Arc::clone(&clock);
+        let token = clock.register_thread(ThreadRole::Foreground);
+        pool.execute(move || {
+            let _guard = token.enter();
+            let mut packet_log = recorder_clone.log();
+            // Pin this thread to a specific core
+            core_affinity::set_for_current(core_id);
+    
+            let start_time = clock_clone.now_micros();
+            while clock_clone.elapsed_since(start_time) < duration {
+                let mut batch = vec![];
+                for _ in 0..10 { // Batch size of 10
+                    let request_packet = UDPApplication {
+                        type_field: request_type,
+                        session_id: session_id_counter_clone.fetch_add(1, Ordering::SeqCst) as u16, // Session ID can be set as needed
+                        test_payload: payload_clone.clone(),
+                    };
+                    let now = clock_clone.now_micros();
+                    packet_log.sent(request_packet.session_id, now);
+                    batch.push(request_packet.to_bytes());
+                }
+    
+                for packet_bytes in batch {
+                    let packet_start_time = clock_clone.now_micros();
+                    socket_clone.send_to(&packet_bytes, server_addr_clone).expect("Couldn't send data");
+                    let elapsed_time = clock_clone.elapsed_since(packet_start_time).as_micros() as usize;
+                    total_time_clone.fetch_add(elapsed_time, Ordering::Relaxed);
+                    total_bytes_sent_clone.fetch_add(packet_bytes.len(), Ordering::Relaxed);
+                }
+            }
+            recorder_clone.push(packet_log);
+        });
+    }
+
+    drop(setup);
+    pool.join();
+
+
+    // let total_time_value = total_time.load(Ordering::Relaxed);
+    let total_bytes_sent_value = total_bytes_sent.load(Ordering::Relaxed);
+    let total_bytes_received_value = total_bytes_received.load(Ordering::Relaxed);
+
+    let throughput_sent = total_bytes_sent_value as f64 / duration.as_secs_f64();
+    let throughput_received = total_bytes_received_value as f64 / duration.as_secs_f64();
+
+    debug!("Total bytes sent: {}", total_bytes_sent_value);
+    debug!("Total bytes received: {}", total_bytes_received_value);
+    debug!("Throughput sent: {:.2} bytes/sec ({:.2} MBps)", throughput_sent, throughput_sent / 1024.0 / 1024.0);
+    debug!("Throughput received: {:.2} bytes/sec ({:.2} MBps)", throughput_received, throughput_received / 1024.0 / 1024.0);
+
+    Ok((total_bytes_sent_value, total_bytes_received_value))
+}
+
+fn speedtest_bitrate_by_duration(spec: &TestSpec, network: &dyn Network, recorder: &PacketRecorder) -> io::Result<(usize, usize)> {
+    let duration = spec.duration;
+    let receive_timeout = spec.receive_timeout;
+    let payload_size = spec.payload_size;
+    debug!("Starting UDP Speedtest client in duration mode");
+    debug!("Sending packets with payload size {} bytes for {:?}", payload_size, duration);
+
+    let server_addr = resolve(&spec.server_addr)?;
+    let client_addr = spec.client_addr.clone();
+
+    debug!("Connecting to {} at {}", spec.server_name(), server_addr);
+    
+    // Bind the client socket
+    debug!("Binding to client address: {}", client_addr);
+    let socket: Arc<dyn Transport> = Arc::from(network.bind(&client_addr)?);
+    let clock = network.clock();
+    // Keep the clock from advancing until all threads are started
+    let setup = clock.register_thread(ThreadRole::Foreground).enter();
+    socket.set_read_timeout(Some(spec.receive_timeout))?;
+
+    let payload = vec![0u8; payload_size];
+
+    let request_type = spec.request_type();
+    let total_time = Arc::new(AtomicUsize::new(0));
+    let total_bytes_sent = Arc::new(AtomicUsize::new(0));
+    let total_bytes_received = Arc::new(AtomicUsize::new(0));
+
+    // let socket_clone = Arc::clone(&socket);
+    let total_time_clone = Arc::clone(&total_time);
+    // let total_bytes_received_clone = Arc::clone(&total_bytes_received);
+
+    // Create a thread pool
+    let pool = ThreadPool::new(8); // Adjust the number of threads based on your CPU cores
+
+    // Get the list of available CPU cores
+    let cores = core_affinity::get_core_ids().expect("Couldn't get core IDs");
+
+    // Thread for receiving packets
+    for (_, core_id) in cores.iter().enumerate().take(4) {
+        let core_id = *core_id; // Copy the core ID to move into the thread
+        let socket_clone = Arc::clone(&socket);
+        let total_bytes_received_clone = Arc::clone(&total_bytes_received);
+        let total_time_clone = Arc::clone(&total_time);
+        let recorder_clone = recorder.clone();
+    
+        let clock_clone = Arc::clone(&clock);
+        let token = clock.register_thread(ThreadRole::Foreground);
+        pool.execute(move || {
+            let _guard = token.enter();
+            let mut packet_log = recorder_clone.log();
+            // Pin this thread to a specific core
+            core_affinity::set_for_current(core_id);
+    
+            let mut buf = [0; 131072];
+            let start_time = clock_clone.now_micros();
+            while clock_clone.elapsed_since(start_time) < duration + receive_timeout {
+                match socket_clone.recv_from(&mut buf) {
+                    Ok((len, _addr)) => {
+                        let response_packet = UDPApplication::new(&buf[..len]);
+                        let receive_time = clock_clone.now_micros();
+                        debug!("Received response: {}", response_packet.summary());
+                        packet_log.response(&response_packet, receive_time);
+                        total_bytes_received_clone.fetch_add(len, Ordering::Relaxed);
+                    }
+                    
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        if clock_clone.elapsed_since(start_time) >= duration {
+                            break;
+                        }
+                        // Handle the WouldBlock error by retrying
+                        clock_clone.sleep(Duration::from_millis(10)); // Optional: Add a small sleep to avoid busy-waiting
+                    }
+                    Err(e) => {
+                        error!("Error receiving data: {}", e);
+                        break;
+                    }
+                }
+            
+            total_time_clone.fetch_add(clock_clone.elapsed_since(start_time).as_secs() as usize, Ordering::SeqCst);
+            }
+            recorder_clone.push(packet_log);
+        });
+    }
+
+    let total_bytes_sent_clone = Arc::clone(&total_bytes_sent);
+
+    let bitrate = spec.bitrate * spec.bitrate_scale as i64;
+    debug!("Bitrate: {} bps", bitrate);
+    // Calculate the interval between sending packets
+    let packet_size_bits = (payload_size * 8) as u64; // Convert payload size to bits
+    debug!("Packet size in bits: {}", packet_size_bits);
+    let interval = Duration::from_secs_f64(packet_size_bits as f64 / bitrate as f64);
+    debug!("Interval between packets: {:?}", interval);
+
+    let session_id_counter = Arc::new(AtomicUsize::new(0));
+    let recorder_clone = recorder.clone();
+
+    // Thread for sending packets
+    let clock_clone = Arc::clone(&clock);
+    let token = clock.register_thread(ThreadRole::Foreground);
+    pool.execute(move || {
+        let _guard = token.enter();
+        let mut packet_log = recorder_clone.log();
+        let start_time = clock_clone.now_micros();
+        let mut next_send_offset = Duration::ZERO;
+        while clock_clone.elapsed_since(start_time) < duration {
+            let session_id_counter_clone = Arc::clone(&session_id_counter);
+            
+            let request_packet = UDPApplication {
+                type_field: request_type,
+                session_id: session_id_counter_clone.fetch_add(1, Ordering::SeqCst) as u16, // Session ID can be set as needed
+                test_payload: payload.clone(),
+            };
+            let packet_start_time = clock_clone.now_micros();
+            let now = clock_clone.now_micros();
+            debug!("Sending request: {}", request_packet.summary());
+            let packet_bytes = request_packet.to_bytes();
+            packet_log.sent(request_packet.session_id, now);
+            socket.send_to(&packet_bytes, server_addr).expect("Couldn't send data");
+
+            let elapsed_time = clock_clone.elapsed_since(packet_start_time).as_micros() as usize;
+            total_time_clone.fetch_add(elapsed_time, Ordering::Relaxed);
+            total_bytes_sent_clone.fetch_add(packet_bytes.len(), Ordering::Relaxed);
+
+            // Sleep until the next send time to control the bitrate without accumulating drift
+            next_send_offset += interval;
+            let elapsed = clock_clone.elapsed_since(start_time);
+            if next_send_offset > elapsed {
+                clock_clone.sleep(next_send_offset - elapsed);
+            }
+        }
+        recorder_clone.push(packet_log);
+    });
+
+    drop(setup);
+    pool.join();
+
+
+    // let total_time_value = total_time.load(Ordering::Relaxed);
+    let total_bytes_sent_value = total_bytes_sent.load(Ordering::Relaxed);
+    let total_bytes_received_value = total_bytes_received.load(Ordering::Relaxed);
+
+    let throughput_sent = total_bytes_sent_value as f64 * 8_f64 / duration.as_secs_f64();
+    let throughput_received = total_bytes_received_value as f64 * 8_f64 / duration.as_secs_f64();
+
+    debug!("Total bytes sent: {}", total_bytes_sent_value);
+    debug!("Total bytes received: {}", total_bytes_received_value);
+    debug!("Throughput sent: {:.2} bytes/sec ({:.2} MBps)", throughput_sent, throughput_sent / 1024.0 / 1024.0);
+    debug!("Throughput received: {:.2} bytes/sec ({:.2} MBps)", throughput_received, throughput_received / 1024.0 / 1024.0);
+
+    Ok((total_bytes_sent_value, total_bytes_received_value))
+}
+
+fn speedtest_by_packet_count(spec: &TestSpec, network: &dyn Network, recorder: &PacketRecorder) -> io::Result<(usize, usize)> {
+    let payload_size = spec.payload_size;
+    let packet_count = spec.packet_count;
+    debug!("Starting UDP Speedtest client in packet count mode");
+    debug!("Sending {} packets with payload size {} bytes", packet_count, payload_size);
+
+    let server_addr = resolve(&spec.server_addr)?;
+    let client_addr = spec.client_addr.clone();
+
+    debug!("Connecting to {} at {}", spec.server_name(), server_addr);
+    
+    // Bind the client socket
+    debug!("Binding to client address: {}", client_addr);
+    let socket: Arc<dyn Transport> = Arc::from(network.bind(&client_addr)?);
+    let clock = network.clock();
+    // Keep the clock from advancing until all threads are started
+    let setup = clock.register_thread(ThreadRole::Foreground).enter();
+    socket.set_read_timeout(Some(spec.receive_timeout))?;
+
+    let payload = vec![0u8; payload_size];
+
+    let request_type = spec.request_type();
+    let total_time = Arc::new(AtomicUsize::new(0));
+    let total_bytes_sent = Arc::new(AtomicUsize::new(0));
+    let total_bytes_received = Arc::new(AtomicUsize::new(0));
+
+    let socket_clone = Arc::clone(&socket);
+    let total_time_clone = Arc::clone(&total_time);
+    let total_bytes_received_clone = Arc::clone(&total_bytes_received);
+    let recorder_clone = recorder.clone();
+
+    // Create a thread pool
+    let pool = ThreadPool::new(4); // Adjust the number of threads based on your CPU cores
+
+    // Thread for receiving packets
+    let clock_clone = Arc::clone(&clock);
+    let token = clock.register_thread(ThreadRole::Foreground);
+    pool.execute(move || {
+        let _guard = token.enter();
+        let mut packet_log = recorder_clone.log();
+        let mut buf = [0; 131072];
+        let mut received_packets = 0;
+        while received_packets < packet_count {
+            match socket_clone.recv_from(&mut buf) {
+                Ok((len, _addr)) => {
+                    let packet = UDPApplication::new(&buf[..len]);
+                    if packet.is_response() {
+                        let receive_time = clock_clone.now_micros();
+                        packet_log.response(&packet, receive_time);
+                        received_packets += 1;
+                        total_bytes_received_clone.fetch_add(len, Ordering::Relaxed);
+                    }
+                }
+                Err(e) => {
+                    // Lost responses never arrive, so stop once the server stays silent for the receive timeout
+                    debug!("Error receiving packet: {:?}", e);
+                    break;
+                }
+            }
+        }
+        recorder_clone.push(packet_log);
+    });
+
+    let total_bytes_sent_clone = Arc::clone(&total_bytes_sent);
+    let recorder_clone = recorder.clone();
+
+    // Thread for sending packets
+    let clock_clone = Arc::clone(&clock);
+    let token = clock.register_thread(ThreadRole::Foreground);
+    pool.execute(move || {
+        let _guard = token.enter();
+        let mut packet_log = recorder_clone.log();
+        for i in 0..packet_count {
+            let request_packet = UDPApplication {
+                type_field: request_type,
+                session_id: i as u16,
+                test_payload: payload.clone(),
+            };
+    
+            let start_time = clock_clone.now_micros();
+            let now = clock_clone.now_micros();
+            debug!("Sending request: {}", request_packet.summary());
+            let packet_bytes = request_packet.to_bytes();
+            packet_log.sent(request_packet.session_id, now);
+            socket.send_to(&packet_bytes, server_addr).expect("Couldn't send data");
+    
+            let elapsed_time = clock_clone.elapsed_since(start_time).as_micros() as usize;
+            total_time_clone.fetch_add(elapsed_time, Ordering::Relaxed);
+            total_bytes_sent_clone.fetch_add(packet_bytes.len(), Ordering::Relaxed);
+        }
+        recorder_clone.push(packet_log);
+    });
+
+    drop(setup);
+    // Wait for the thread pool to finish
+    pool.join();
+
+    let total_time_value = total_time.load(Ordering::Relaxed);
+    let average_time = total_time_value / packet_count.max(1);
+    debug!("Average round-trip time: {:?}", average_time);
+
+    let total_bytes_sent_value = total_bytes_sent.load(Ordering::Relaxed);
+    let total_bytes_received_value = total_bytes_received.load(Ordering::Relaxed);
+
+    let throughput_sent = total_bytes_sent_value as f64 / total_time_value as f64;
+    let throughput_received = total_bytes_received_value as f64 / total_time_value as f64;
+
+    debug!("Total bytes sent: {}", total_bytes_sent_value);
+    debug!("Total bytes received: {}", total_bytes_received_value);
+    debug!("Throughput sent: {:.2} bytes/sec ({:.2} MBps)", throughput_sent, throughput_sent / 1024.0 / 1024.0);
+    debug!("Throughput received: {:.2} bytes/sec ({:.2} MBps)", throughput_received, throughput_received / 1024.0 / 1024.0);
+
+    Ok((total_bytes_sent_value, total_bytes_received_value))
+}
+
+/// `name` with whitespace and path separators replaced by `-`, to be used as part of a file name.
+pub fn file_name_part(name: &str) -> String {
+    name.replace(|c: char| c.is_whitespace() || c == '/' || c == '\\', "-")
+}
+
+/// Parameters of a single test run against one server.
+#[derive(Debug, Clone)]
+pub struct TestSpec {
+    pub server_addr: String,
+    /// Human-readable name of the server in result files and logs, the address if not set.
+    pub server_name: Option<String>,
+    pub client_addr: String,
+    pub mode: SpeedtestEnum,
+    pub payload_size: usize,
+    pub duration: Duration,
+    pub packet_count: usize,
+    /// Interval between two probes in ping mode.
+    pub interval: Duration,
+    pub bitrate: i64,
+    pub bitrate_scale: BitrateScale,
+    /// How long the receiver waits for a response before giving up.
+    pub receive_timeout: Duration,
+    /// Significant decimal digits of the RTT histogram.
+    pub histogram_precision: u8,
+    /// Percentiles of the RTTs to report, in percent.
+    pub percentiles: Vec<f64>,
+    /// When consecutive failed probes count as an outage.
+    pub outage: OutageConfig,
+    /// External sources of event markers to listen on during the test.
+    pub markers: Vec<MarkerSource>,
+    /// Commands to run before, during and after the test.
+    pub hooks: Vec<Hook>,
+    /// Directory to export the per-packet timeline of the test to, see [`TimelineWriter`].
+    pub timeline: Option<PathBuf>,
+    /// Interval of the traffic reports while the test is running, see [`IntervalReporter`].
+    pub report_interval: Option<Duration>,
+    /// Keep the timestamps of every packet in [`TestResult::rtt_times`]. The statistics are computed while
+    /// the test runs either way, so a long test without it runs in constant memory.
+    pub packet_log: bool,
+}
+
+impl Default for TestSpec {
+    fn default() -> Self {
+        TestSpec {
+            server_addr: "127.0.0.1:8080".to_string(),
+            server_name: None,
+            client_addr: "0.0.0.0:0".to_string(),
+            mode: SpeedtestEnum::Ping,
+            payload_size: 500,
+            duration: Duration::from_secs(10),
+            packet_count: 1000,
+            interval: Duration::from_secs(1),
+            bitrate: 100,
+            bitrate_scale: BitrateScale::Mbps,
+            receive_timeout: Duration::from_secs(5),
+            histogram_precision: 3,
+            percentiles: DEFAULT_PERCENTILES.to_vec(),
+            outage: OutageConfig::default(),
+            markers: Vec::new(),
+            hooks: Vec::new(),
+            timeline: None,
+            report_interval: None,
+            packet_log: false,
+        }
+    }
+}
+
+impl TestSpec {
+    /// Builds the spec of one test from the client config, for the given target and payload size, applying
+    /// the target's overrides.
+    pub fn from_config(config: &ClientConfig, target: &ExperimentTarget, payload_size: usize) -> Self {
+        TestSpec {
+            server_addr: target.address.clone(),
+            server_name: Some(target.name.clone()),
+            client_addr: config.client_addr.clone(),
+            mode: SpeedtestEnum::from_string(target.speedtest_mode.as_deref().unwrap_or(&config.speedtest_mode)),
+            payload_size,
+            duration: config.speedtest_duration,
+            packet_count: config.packet_count,
+            interval: config.ping_interval,
+            bitrate: target.bitrate.unwrap_or(config.bitrate),
+            bitrate_scale: get_bitrate_scale(target.bitrate_scale.as_deref().unwrap_or(&config.bitrate_scale)),
+            histogram_precision: config.histogram_precision,
+            percentiles: config.percentiles.clone(),
+            outage: OutageConfig {
+                min_probes: config.outage_min_probes,
+                rtt_threshold: config.outage_rtt_threshold,
+            },
+            markers: config.event_markers.clone(),
+            hooks: config.hooks.clone(),
+            timeline: if config.timeline { Some(PathBuf::from(RESULTS_DIR)) } else { None },
+            report_interval: config.report_interval,
+            packet_log: config.packet_log,
+            ..TestSpec::default()
+        }
+    }
+
+    /// Name of the server, its address if it has none.
+    pub fn server_name(&self) -> &str {
+        self.server_name.as_deref().unwrap_or(&self.server_addr)
+    }
+
+    /// The server name as part of file names, see [`file_name_part`].
+    pub fn server_file_name(&self) -> String {
+        file_name_part(self.server_name())
+    }
+
+    /// Whether the test keeps the timestamps of every packet: if asked to, or for the impact of the
+    /// events that markers and hooks record, see [`TestResult::event_impacts`].
+    pub(crate) fn keeps_packets(&self) -> bool {
+        self.packet_log || !self.markers.is_empty() || !self.hooks.is_empty()
+    }
+
+    /// Type of the requests: the timeline asks the server for its timestamps, other tests get plain echoes.
+    pub(crate) fn request_type(&self) -> u16 {
+        if self.timeline.is_some() {
+            UDPApplicationEnum::TIMED_REQUEST as u16
+        } else {
+            UDPApplicationEnum::REQUEST as u16
+        }
+    }
+
+    /// The parameters of the test, durations in seconds and the bitrate in bits per second.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "server_name": self.server_name(),
+            "server_addr": self.server_addr,
+            "client_addr": self.client_addr,
+            "mode": self.mode.to_string(),
+            "payload_size": self.payload_size,
+            "duration": self.duration.as_secs_f64(),
+            "packet_count": self.packet_count,
+            "interval": self.interval.as_secs_f64(),
+            "bitrate": self.bitrate * self.bitrate_scale as i64,
+            "receive_timeout": self.receive_timeout.as_secs_f64(),
+            "histogram_precision": self.histogram_precision,
+            "percentiles": self.percentiles,
+            "outage_min_probes": self.outage.min_probes,
+            "outage_rtt_threshold": self.outage.rtt_threshold.map(|threshold| threshold.as_secs_f64()),
+            "report_interval": self.report_interval.map(|interval| interval.as_secs_f64()),
+        })
+    }
+}
+
+/// Outcome of a test run: the traffic counters and the statistics of the packets.
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    pub spec: TestSpec,
+    pub started_at: SystemTime,
+    pub elapsed: Duration,
+    pub bytes_sent: usize,
+    pub bytes_received: usize,
+    /// Timestamps of every packet, only kept if [`TestSpec::packet_log`] is set or the test has markers
+    /// or hooks.
+    pub rtt_times: RTTTimes,
+    /// RTTs of all answered requests in microseconds.
+    pub histogram: LatencyHistogram,
+    /// Seed of the server's impairment RNG, if the server reported it.
+    pub server_seed: Option<u64>,
+    /// Loss, duplication and reordering of the requests.
+    pub delivery: DeliveryStats,
+    /// Interarrival jitter and delay variation of the responses.
+    pub jitter: JitterStats,
+    /// Windows of consecutive lost or slow probes.
+    pub outages: OutageReport,
+    /// Events marked during the test, ordered by time.
+    pub events: Vec<Event>,
+    /// Per-packet timeline of the test, if one was exported.
+    pub timeline: Option<PathBuf>,
+    /// Traffic per reporting interval, empty without a report interval.
+    pub intervals: Vec<IntervalReport>,
+}
+
+impl TestResult {
+    /// Time the client was sending: the configured duration, or the whole run in packet count mode.
+    pub fn send_duration(&self) -> Duration {
+        match self.spec.mode {
+            SpeedtestEnum::ByPacketCount => self.elapsed,
+            _ => self.spec.duration,
+        }
+    }
+
+    /// Sent throughput in bits per second.
+    pub fn throughput_sent(&self) -> f64 {
+        self.bytes_sent as f64 * 8.0 / self.send_duration().as_secs_f64()
+    }
+
+    /// Received throughput in bits per second.
+    pub fn throughput_received(&self) -> f64 {
+        self.bytes_received as f64 * 8.0 / self.send_duration().as_secs_f64()
+    }
+
+    /// Whether any response arrived, i.e. whether there are RTTs to evaluate.
+    pub fn has_rtts(&self) -> bool {
+        !self.histogram.is_empty()
+    }
+
+    /// Time from every event to the first loss after it and to the recovery from that loss.
+    pub fn event_impacts(&self) -> Vec<EventImpact> {
+        self.events.iter().map(|event| EventImpact::of(event, &self.rtt_times)).collect()
+    }
+
+    /// Summary of the RTTs over the answered packets, `None` if no packet was answered.
+    pub fn summary(&self) -> Option<RttSummary> {
+        RttSummary::from_histogram(&self.histogram, &self.spec.percentiles)
+    }
+
+    pub fn evaluate_rtt(&self) {
+        debug!("RTT times: {:?}", self.rtt_times.get_rtts());
+        let name = self.spec.server_name();
+        let delivery = &self.delivery;
+        info!(
+            "{}: sent {} requests: {} lost ({:.2}%), {} duplicated ({:.2}%), {} reordered ({:.2}%, max extent {}, max n-reordering {})",
+            name,
+            delivery.sent,
+            delivery.lost,
+            delivery.loss_rate().unwrap_or_default() * 100.0,
+            delivery.duplicated,
+            delivery.duplicate_rate().unwrap_or_default() * 100.0,
+            delivery.reordered,
+            delivery.reordered_ratio().unwrap_or_default() * 100.0,
+            delivery.max_reordering_extent,
+            delivery.max_n_reordering()
+        );
+        for n in 1..=delivery.max_n_reordering() {
+            debug!("{}-reordering ratio: {:.4}", n, delivery.n_reordering_ratio(n).unwrap_or_default());
+        }
+        let started_at = self.started_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_micros();
+        for outage in &self.outages.outages {
+            info!(
+                "{}: outage from {:.3} s to {:.3} s ({:.3} s, {} lost, {} slow{})",
+                name,
+                outage.start.saturating_sub(started_at) as f64 / 1e6,
+                outage.end.saturating_sub(started_at) as f64 / 1e6,
+                outage.duration().as_secs_f64(),
+                outage.lost,
+                outage.slow,
+                if outage.recovered { "" } else { ", not recovered" }
+            );
+        }
+        if let Some(longest) = self.outages.longest() {
+            info!("{}: {} outages, total downtime {:.3} s, longest {:.3} s", name, self.outages.outages.len(), self.outages.total_downtime().as_secs_f64(), longest.duration().as_secs_f64());
+        }
+        for (event, impact) in self.events.iter().zip(self.event_impacts()) {
+            let seconds = |duration: Option<Duration>| duration.map_or("-".to_string(), |duration| format!("{:.3} s", duration.as_secs_f64()));
+            info!(
+                "{}: event `{}` at {:.3} s: first loss after {}, recovered after {}",
+                name,
+                event.label,
+                event.time.saturating_sub(started_at) as f64 / 1e6,
+                seconds(impact.time_to_first_loss),
+                seconds(impact.time_to_recovery)
+            );
+        }
+        if delivery.unsolicited > 0 {
+            warn!("{}: received {} responses without a matching request", name, delivery.unsolicited);
+        }
+        let summary = match self.summary() {
+            Some(summary) => summary,
+            None => {
+                warn!("No responses from {} at {}, no RTTs to evaluate", name, self.spec.server_addr);
+                return;
+            }
+        };
+        let percentiles = summary.percentiles.iter().map(|(percentile, value)| format!("p{} {:.2}", percentile, *value as f64 / 1000.0)).collect::<Vec<_>>().join(", ");
+        info!("{}: RTT of {} responses (ms): min {:.2}, avg {:.2}, {}, max {:.2}", name, summary.count, summary.min as f64 / 1000.0, summary.mean / 1000.0, percentiles, summary.max as f64 / 1000.0);
+        debug!("Average RTT: {:.2} microseconds ({:.2} ms)", summary.mean, summary.mean / 1000.0);
+        debug!("Median RTT: {:.2} microseconds ({:.2} ms)", summary.median, summary.median as f64 / 1000.0);
+        debug!("Minimum RTT: {:.2} microseconds ({:.2} ms)", summary.min, summary.min as f64 / 1000.0);
+        debug!("Maximum RTT: {:.2} microseconds ({:.2} ms)", summary.max, summary.max as f64 / 1000.0);
+        debug!("Variance RTT: {:.2} microseconds^2", summary.variance);
+        debug!("Standard Deviation RTT: {:.2} microseconds ({:.2} ms)", summary.stddev, summary.stddev / 1000.0);
+        for (percentile, value) in &summary.percentiles {
+            debug!("{}: {} microseconds ({:.2} ms)", stats::percentile_column(*percentile), value, *value as f64 / 1000.0);
+        }
+        let jitter = &self.jitter;
+        info!(
+            "{}: jitter (ms): interarrival {:.3}, mean absolute IPDV {:.3}, 99th percentile PDV {:.3}",
+            name,
+            jitter.interarrival_jitter.unwrap_or_default() / 1000.0,
+            jitter.mean_absolute_ipdv.unwrap_or_default() / 1000.0,
+            jitter.pdv_99th_percentile.unwrap_or_default() as f64 / 1000.0
+        );
+    }
+
+    pub fn write_raw_data_to_csv(&self, path: &Path) -> io::Result<()> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        for rtt in self.rtt_times.get_rtts() {
+            wtr.write_record([rtt.to_string()])?;
+        }
+        wtr.flush()
+    }
+
+    /// Appends the evaluated RTTs as one row to `rtt_times_<server>_<mode>.csv` in `results_dir`.
+    pub fn write_evaluated_data_to_csv(&self, results_dir: &Path) -> io::Result<()> {
+        if !results_dir.exists() {
+            fs::create_dir_all(results_dir)?;
+        }
+        let server_addr = &self.spec.server_addr;
+        let speedtest_mode = &self.spec.mode;
+        let file_name = results_dir.join(format!("rtt_times_{}_{}.csv", self.spec.server_file_name(), speedtest_mode.to_string()));
+
+        let summary = self.summary();
+        let percentiles = &self.spec.percentiles;
+
+        // Check if the file exists and append entry to the file
+        let file_exists = file_name.exists();
+
+        let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file_name)?;
+        let mut wtr = csv::Writer::from_writer(file);
+        if !file_exists {
+            let mut header: Vec<String> = ["Server Name", "Server Address", "Speedtest-Mode", "Responses", "Average RTT", "Median RTT", "Minimum RTT", "Maximum RTT", "Variance RTT", "Standard Deviation RTT"].iter().map(|column| column.to_string()).collect();
+            header.extend(percentiles.iter().map(|percentile| stats::percentile_column(*percentile)));
+            header.extend(["Interarrival Jitter (RTT)", "Mean IPDV (RTT)", "Mean Absolute IPDV (RTT)", "Maximum Absolute IPDV (RTT)", "99th Percentile PDV (RTT)", "Maximum PDV (RTT)"].iter().map(|column| column.to_string()));
+            header.extend(["Sent Packets", "Lost Packets", "Loss Rate", "Duplicate Rate", "Reordered Ratio", "Max Reordering Extent", "Mean Reordering Extent", "Max n-Reordering", "Outages", "Total Downtime", "Longest Outage", "Server Seed"].iter().map(|column| column.to_string()));
+            wtr.write_record(&header)?;
+        }
+        // Without responses the statistics are undefined and their cells are left empty.
+        let mut record = vec![self.spec.server_name().to_string(), server_addr.to_string(), speedtest_mode.to_string().into(), self.histogram.len().to_string()];
+        match &summary {
+            Some(summary) => {
+                record.extend([summary.mean.to_string(), summary.median.to_string(), summary.min.to_string(), summary.max.to_string(), summary.variance.to_string(), summary.stddev.to_string()]);
+                record.extend(summary.percentiles.iter().map(|(_, value)| value.to_string()));
+            }
+            None => record.extend(std::iter::repeat_n(String::new(), 6 + percentiles.len())),
+        }
+        let jitter = &self.jitter;
+        let value = |value: Option<u128>| value.map(|value| value.to_string()).unwrap_or_default();
+        let float = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+        record.extend([float(jitter.interarrival_jitter), float(jitter.mean_ipdv), float(jitter.mean_absolute_ipdv), value(jitter.max_absolute_ipdv), value(jitter.pdv_99th_percentile), value(jitter.max_pdv)]);
+        let delivery = &self.delivery;
+        record.extend([delivery.sent.to_string(), delivery.lost.to_string(), float(delivery.loss_rate()), float(delivery.duplicate_rate()), float(delivery.reordered_ratio())]);
+        record.extend([delivery.max_reordering_extent.to_string(), float(delivery.mean_reordering_extent()), delivery.max_n_reordering().to_string()]);
+        let outages = &self.outages;
+        record.extend([outages.outages.len().to_string(), outages.total_downtime().as_micros().to_string(), outages.longest().map(|outage| outage.duration().as_micros().to_string()).unwrap_or_default()]);
+        record.push(self.server_seed.map(|seed| seed.to_string()).unwrap_or_default());
+        wtr.write_record(&record)?;
+        wtr.flush()
+    }
+
+    /// Appends one row per outage to `outages_<server>_<mode>.csv` in `results_dir`, times in microseconds.
+    pub fn write_outages_to_csv(&self, results_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(results_dir)?;
+        let file_name = results_dir.join(format!("outages_{}_{}.csv", self.spec.server_file_name(), self.spec.mode.to_string()));
+        let file_exists = file_name.exists();
+        let file = OpenOptions::new().create(true).append(true).open(file_name)?;
+        let mut wtr = csv::Writer::from_writer(file);
+        if !file_exists {
+            wtr.write_record(["Server Name", "Server Address", "Speedtest-Mode", "Test Start", "Outage Start", "Outage End", "Duration", "Lost Probes", "Slow Probes", "Recovered"])?;
+        }
+        let started_at = self.started_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_micros().to_string();
+        for outage in &self.outages.outages {
+            wtr.write_record([
+                self.spec.server_name().to_string(),
+                self.spec.server_addr.clone(),
+                self.spec.mode.to_string().into(),
+                started_at.clone(),
+                outage.start.to_string(),
+                outage.end.to_string(),
+                outage.duration().as_micros().to_string(),
+                outage.lost.to_string(),
+                outage.slow.to_string(),
+                outage.recovered.to_string(),
+            ])?;
+        }
+        wtr.flush()
+    }
+
+    /// Appends one row per event to `events_<server>_<mode>.csv` in `results_dir`, times in microseconds.
+    pub fn write_events_to_csv(&self, results_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(results_dir)?;
+        let file_name = results_dir.join(format!("events_{}_{}.csv", self.spec.server_file_name(), self.spec.mode.to_string()));
+        let file_exists = file_name.exists();
+        let file = OpenOptions::new().create(true).append(true).open(file_name)?;
+        let mut wtr = csv::Writer::from_writer(file);
+        if !file_exists {
+            wtr.write_record(["Server Name", "Server Address", "Speedtest-Mode", "Test Start", "Event Time", "Source", "Label", "Time To First Loss", "Time To Recovery", "Finished", "Exit Status", "Output"])?;
+        }
+        let started_at = self.started_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_micros().to_string();
+        let micros = |duration: Option<Duration>| duration.map(|duration| duration.as_micros().to_string()).unwrap_or_default();
+        for (event, impact) in self.events.iter().zip(self.event_impacts()) {
+            wtr.write_record([
+                self.spec.server_name().to_string(),
+                self.spec.server_addr.clone(),
+                self.spec.mode.to_string().into(),
+                started_at.clone(),
+                event.time.to_string(),
+                event.source.clone(),
+                event.label.clone(),
+                micros(impact.time_to_first_loss),
+                micros(impact.time_to_recovery),
+                event.hook.as_ref().map(|hook| hook.finished.to_string()).unwrap_or_default(),
+                event.hook.as_ref().and_then(|hook| hook.status).map(|status| status.to_string()).unwrap_or_default(),
+                event.hook.as_ref().map(|hook| hook.output.clone()).unwrap_or_default(),
+            ])?;
+        }
+        wtr.flush()
+    }
+
+    /// Writes the interval reports to `intervals_<server>_<mode>_<start>.csv` in `results_dir`, times in
+    /// seconds since the test start, throughput in bits per second and RTTs in microseconds.
+    pub fn write_intervals_to_csv(&self, results_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(results_dir)?;
+        let started_at = self.started_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_micros();
+        let file_name = results_dir.join(format!("intervals_{}_{}_{}.csv", self.spec.server_file_name(), self.spec.mode.to_string(), started_at));
+        let mut wtr = csv::Writer::from_path(file_name)?;
+        wtr.write_record([
+            "Server Name", "Server Address", "Speedtest-Mode", "Start", "End", "Sent Packets", "Sent Bytes", "Throughput Sent", "Received Packets", "Received Bytes", "Throughput Received",
+            "Lost Packets", "Loss Rate", "Interarrival Jitter (RTT)", "Minimum RTT", "Average RTT", "Maximum RTT", "99th Percentile RTT",
+        ])?;
+        for interval in &self.intervals {
+            let rtt = interval.rtt.as_ref();
+            wtr.write_record([
+                self.spec.server_name().to_string(),
+                self.spec.server_addr.clone(),
+                self.spec.mode.to_string().into(),
+                interval.start.as_secs_f64().to_string(),
+                interval.end.as_secs_f64().to_string(),
+                interval.packets_sent.to_string(),
+                interval.bytes_sent.to_string(),
+                interval.throughput_sent().to_string(),
+                interval.packets_received.to_string(),
+                interval.bytes_received.to_string(),
+                interval.throughput_received().to_string(),
+                interval.lost.to_string(),
+                interval.loss_rate().map(|rate| rate.to_string()).unwrap_or_default(),
+                interval.jitter.map(|jitter| jitter.to_string()).unwrap_or_default(),
+                rtt.map(|rtt| rtt.min.to_string()).unwrap_or_default(),
+                rtt.map(|rtt| rtt.mean.to_string()).unwrap_or_default(),
+                rtt.map(|rtt| rtt.max.to_string()).unwrap_or_default(),
+                interval.rtt_99th_percentile().map(|value| value.to_string()).unwrap_or_default(),
+            ])?;
+        }
+        wtr.flush()
+    }
+
+    /// Writes the result in the structure of `iperf3 -J` to `iperf3_<server>_<mode>_<start>.json` in
+    /// `results_dir`, see [`iperf::to_json`].
+    pub fn write_iperf3_json(&self, results_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(results_dir)?;
+        let started_at = self.started_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_micros();
+        let file_name = results_dir.join(format!("iperf3_{}_{}_{}.json", self.spec.server_file_name(), self.spec.mode.to_string(), started_at));
+        let file = fs::File::create(file_name)?;
+        serde_json::to_writer_pretty(file, &iperf::to_json(self)).map_err(io::Error::from)
+    }
+
+    /// Writes the RTT histogram to `rtt_histogram_<server>_<mode>_<start>.hlog` in `results_dir`, to be
+    /// merged across iterations by `analyze`.
+    pub fn write_histogram_log(&self, results_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(results_dir)?;
+        let started_at = self.started_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_micros();
+        let file_name = results_dir.join(format!("rtt_histogram_{}_{}_{}.hlog", self.spec.server_file_name(), self.spec.mode.to_string(), started_at));
+        let started_at = started_at.to_string();
+        self.histogram.write_log(&file_name, &[("name", self.spec.server_name()), ("server", &self.spec.server_addr), ("mode", self.spec.mode.to_string()), ("started_at", &started_at)])
+    }
+}
+
+/// Builder for a [`Client`], starting from the [`TestSpec`] defaults.
+#[derive(Clone)]
+pub struct ClientBuilder {
+    spec: TestSpec,
+    network: Arc<dyn Network>,
+    events: EventLog,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        ClientBuilder {
+            spec: TestSpec::default(),
+            network: Arc::new(SystemNetwork),
+            events: EventLog::new(),
+        }
+    }
+}
+
+impl fmt::Debug for ClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientBuilder").field("spec", &self.spec).finish_non_exhaustive()
+    }
+}
+
+impl ClientBuilder {
+    pub fn server_addr(mut self, server_addr: impl Into<String>) -> Self {
+        self.spec.server_addr = server_addr.into();
+        self
+    }
+
+    pub fn server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.spec.server_name = Some(server_name.into());
+        self
+    }
+
+    pub fn client_addr(mut self, client_addr: impl Into<String>) -> Self {
+        self.spec.client_addr = client_addr.into();
+        self
+    }
+
+    pub fn mode(mut self, mode: SpeedtestEnum) -> Self {
+        self.spec.mode = mode;
+        self
+    }
+
+    pub fn payload_size(mut self, payload_size: usize) -> Self {
+        self.spec.payload_size = payload_size;
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.spec.duration = duration;
+        self
+    }
+
+    pub fn packet_count(mut self, packet_count: usize) -> Self {
+        self.spec.packet_count = packet_count;
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.spec.interval = interval;
+        self
+    }
+
+    pub fn bitrate(mut self, bitrate: i64, bitrate_scale: BitrateScale) -> Self {
+        self.spec.bitrate = bitrate;
+        self.spec.bitrate_scale = bitrate_scale;
+        self
+    }
+
+    pub fn receive_timeout(mut self, receive_timeout: Duration) -> Self {
+        self.spec.receive_timeout = receive_timeout;
+        self
+    }
+
+    pub fn histogram_precision(mut self, histogram_precision: u8) -> Self {
+        self.spec.histogram_precision = histogram_precision;
+        self
+    }
+
+    /// Percentiles of the RTTs to report, in percent, e.g. `[50.0, 99.0, 99.9]`.
+    pub fn percentiles(mut self, percentiles: &[f64]) -> Self {
+        self.spec.percentiles = percentiles.to_vec();
+        self
+    }
+
+    /// When consecutive lost or slow probes count as an outage.
+    pub fn outage(mut self, outage: OutageConfig) -> Self {
+        self.spec.outage = outage;
+        self
+    }
+
+    /// Listens for event markers from `source` during the test.
+    pub fn marker(mut self, source: MarkerSource) -> Self {
+        self.spec.markers.push(source);
+        self
+    }
+
+    /// Runs `hook` before, during or after the test, see [`HookStage`].
+    pub fn hook(mut self, hook: Hook) -> Self {
+        self.spec.hooks.push(hook);
+        self
+    }
+
+    /// Exports a per-packet timeline of the test to `timeline_<server>_<mode>_<start>.csv` in `dir`.
+    pub fn timeline(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.spec.timeline = Some(dir.into());
+        self
+    }
+
+    /// Reports the traffic and RTTs every `interval` while the test is running, like iperf3 `-i`.
+    pub fn report_interval(mut self, interval: Duration) -> Self {
+        self.spec.report_interval = Some(interval);
+        self
+    }
+
+    /// Keeps the timestamps of every packet in [`TestResult::rtt_times`].
+    pub fn packet_log(mut self, packet_log: bool) -> Self {
+        self.spec.packet_log = packet_log;
+        self
+    }
+
+    /// Log the events of the test are taken from, to mark events from within the process. Keeps the
+    /// timestamps of every packet for the impact of the events.
+    pub fn event_log(mut self, events: EventLog) -> Self {
+        self.events = events;
+        self.spec.packet_log = true;
+        self
+    }
+
+    /// Network the client sends through, the host's UDP stack by default.
+    pub fn network(mut self, network: Arc<dyn Network>) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn build(self) -> Client {
+        Client { spec: self.spec, network: self.network, events: self.events }
+    }
+}
+
+/// UDP speedtest client running a [`TestSpec`] against an echo server.
+#[derive(Clone)]
+pub struct Client {
+    spec: TestSpec,
+    network: Arc<dyn Network>,
+    events: EventLog,
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client").field("spec", &self.spec).finish_non_exhaustive()
+    }
+}
+
+impl Client {
+    pub fn new(spec: TestSpec) -> Self {
+        Client { spec, network: Arc::new(SystemNetwork), events: EventLog::new() }
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    pub fn spec(&self) -> &TestSpec {
+        &self.spec
+    }
+
+    /// Runs the test and blocks until it is finished.
+    pub fn run(&self) -> io::Result<TestResult> {
+        let network = self.network.as_ref();
+        let server_seed = query_server_seed(&self.spec, network);
+        if let Some(seed) = server_seed {
+            info!("{}: server RNG seed {}", self.spec.server_name(), seed);
+        }
+        let clock = self.network.clock();
+        let markers = MarkerListener::start(&self.spec.markers, &self.events, clock.clone())?;
+        let hook_env = vec![
+            ("UDPBENCHMARK_SERVER".to_string(), self.spec.server_addr.clone()),
+            ("UDPBENCHMARK_SERVER_NAME".to_string(), self.spec.server_name().to_string()),
+            ("UDPBENCHMARK_MODE".to_string(), self.spec.mode.to_string().to_string()),
+        ];
+        let run_start = clock.now_micros();
+        for hook in self.spec.hooks.iter().filter(|hook| hook.stage == HookStage::Before) {
+            self.events.record(run_hook(hook, clock.as_ref(), &hook_env));
+        }
+        let start_time = clock.now_micros();
+        let scheduled_hooks = ScheduledHooks::start(&self.spec.hooks, start_time, &self.events, clock.clone(), &hook_env);
+        let timeline = match &self.spec.timeline {
+            Some(dir) => {
+                let path = dir.join(format!("timeline_{}_{}_{}.csv", self.spec.server_file_name(), self.spec.mode.to_string(), start_time));
+                Some(TimelineWriter::create(&path, self.spec.server_name(), self.spec.payload_size, self.spec.receive_timeout)?)
+            }
+            None => None,
+        };
+        let packet_size = self.spec.payload_size + 4;
+        let reporter = self.spec.report_interval.map(|interval| IntervalReporter::start(self.spec.server_name(), interval, start_time, packet_size, self.spec.receive_timeout));
+        let collector = StatsCollector::start(self.spec.receive_timeout, &self.spec.outage);
+        let sinks = timeline.iter().map(TimelineWriter::sender).chain(reporter.iter().map(IntervalReporter::sender)).chain([collector.sender()]).collect();
+        let recorder = PacketRecorder::new(&self.spec, sinks);
+        let (bytes_sent, bytes_received) = match self.spec.mode {
+            SpeedtestEnum::ByDuration => speedtest_by_duration(&self.spec, network, &recorder)?,
+            SpeedtestEnum::ByDurationCustomBitrate => speedtest_bitrate_by_duration(&self.spec, network, &recorder)?,
+            SpeedtestEnum::ByPacketCount => speedtest_by_packet_count(&self.spec, network, &recorder)?,
+            SpeedtestEnum::Ping => speedtest_simple_ping(&self.spec, network, &recorder)?,
+        };
+        let end_time = clock.now_micros();
+        drop(scheduled_hooks);
+        for hook in self.spec.hooks.iter().filter(|hook| hook.stage == HookStage::After) {
+            self.events.record(run_hook(hook, clock.as_ref(), &hook_env));
+        }
+        drop(markers);
+        let recorded = recorder.finish();
+        let collected = collector.finish();
+        let intervals = reporter.map(|reporter| reporter.finish(end_time)).unwrap_or_default();
+        let events = self.events.between(run_start, clock.now_micros());
+        let timeline = match timeline {
+            Some(timeline) => Some(timeline.finish(&events)?),
+            None => None,
+        };
+        let mut delivery = recorded.delivery;
+        delivery.merge(&collected.delivery);
+        Ok(TestResult {
+            spec: self.spec.clone(),
+            started_at: SystemTime::UNIX_EPOCH + Duration::from_micros(start_time as u64),
+            elapsed: Duration::from_micros((end_time - start_time) as u64),
+            bytes_sent,
+            bytes_received,
+            jitter: collected.jitter.finish(&recorded.histogram),
+            histogram: recorded.histogram,
+            rtt_times: recorded.rtt_times,
+            server_seed,
+            delivery,
+            outages: collected.outages,
+            events,
+            timeline,
+            intervals,
+        })
+    }
+}
+
+/// Runs a test and writes its results and manifest into a directory of its own in [`RESULTS_DIR`], named
+/// after the iteration, server, mode, payload size and start time. The manifest is tagged with `point`, if
+/// the test is part of an experiment plan.
+///
+/// Returns the run directory, or the error if the test failed or its manifest couldn't be written.
+fn run_speedtest(mut spec: TestSpec, config: &ClientConfig, iteration: usize, point: Option<&PlanPoint>) -> io::Result<PathBuf> {
+    let started = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+    let run_dir = Path::new(RESULTS_DIR).join(format!("run_{}_{}_{}_{}B_{}", iteration, spec.server_file_name(), spec.mode.to_string(), spec.payload_size, started));
+    if spec.timeline.is_some() {
+        spec.timeline = Some(run_dir.clone());
+    }
+    let results_dir = run_dir.as_path();
+    let server_name = spec.server_name().to_string();
+    match Client::new(spec).run() {
+        Ok(result) => {
+            result.evaluate_rtt();
+            if let Err(e) = result.write_evaluated_data_to_csv(results_dir) {
+                error!("Couldn't write results: {}", e);
+            }
+            if !result.outages.outages.is_empty() {
+                if let Err(e) = result.write_outages_to_csv(results_dir) {
+                    error!("Couldn't write outages: {}", e);
+                }
+            }
+            if !result.events.is_empty() {
+                if let Err(e) = result.write_events_to_csv(results_dir) {
+                    error!("Couldn't write events: {}", e);
+                }
+            }
+            if config.json_output {
+                if let Err(e) = result.write_iperf3_json(results_dir) {
+                    error!("Couldn't write JSON result: {}", e);
+                }
+            }
+            if !result.intervals.is_empty() {
+                if let Err(e) = result.write_intervals_to_csv(results_dir) {
+                    error!("Couldn't write interval reports: {}", e);
+                }
+            }
+            if result.has_rtts() {
+                if let Err(e) = result.write_histogram_log(results_dir) {
+                    error!("Couldn't write histogram log: {}", e);
+                }
+            }
+            let mut manifest = RunManifest::new(&result, config, iteration);
+            if let Some(point) = point {
+                manifest = manifest.point(point);
+            }
+            match manifest.write(results_dir) {
+                Ok(()) => info!("Wrote results to {}", results_dir.display()),
+                Err(e) => {
+                    error!("Couldn't write manifest: {}", e);
+                    return Err(e);
+                }
+            }
+            // A dead or unreachable server fails the test, so that a resumed campaign retries it
+            if !result.has_rtts() {
+                error!("Speedtest against {} got no responses to {} requests", server_name, result.delivery.sent);
+                return Err(io::Error::other(format!("no responses from {}", server_name)));
+            }
+            Ok(run_dir)
+        }
+        Err(e) => {
+            error!("Speedtest against {} failed: {}", server_name, e);
+            Err(e)
+        }
+    }
+}
+
+/// Runs the client as configured, i.e. a single test or the experiment loop if `experiment_mode` is set.
+pub fn run(config: &ClientConfig) {
+    if config.experiment_mode {
+        run_experiments(config);
+    } else {
+        run_single(config);
+    }
+}
+
+/// Runs a single test against `server_addr` with the configured mode, named after `server_labels`.
+pub fn run_single(config: &ClientConfig) {
+    info!("Starting single mode");
+    let target = ExperimentTarget::new(config.server_name(&config.server_addr), config.server_addr.clone());
+    let spec = TestSpec::from_config(config, &target, config.payload_size);
+    if spec.mode == SpeedtestEnum::ByDurationCustomBitrate {
+        debug!("Bitrate scale is {:?}", spec.bitrate_scale);
+    }
+    let _ = run_speedtest(spec, config, 0, None);
+}
+
+/// Runs the [`ExperimentPlan`]: `experiment_count` iterations over all `experiment_servers` and the swept
+/// modes, payload sizes, bitrates, intervals and durations, in the order of `experiment_schedule`. The order
+/// is written to `plan_<start>.csv` in [`RESULTS_DIR`].
+///
+/// Progress is recorded in the ledger at `experiment_ledger`: completed points of an earlier, interrupted run
+/// of the same campaign are skipped, and failed points are retried up to `experiment_retries` times. The
+/// points that are still incomplete are logged, and the status of every point is written to
+/// [`STATUS_FILE`] next to the ledger.
+pub fn run_experiments(config: &ClientConfig) {
+    let mut ledger = match Ledger::open(&config.experiment_ledger) {
+        Ok(ledger) => ledger,
+        Err(e) => {
+            error!("Couldn't read experiment ledger {}: {}", config.experiment_ledger.display(), e);
+            return;
+        }
+    };
+    let mut config = config.clone();
+    // A resumed campaign keeps the random schedule it was planned with
+    if config.experiment_seed.is_none() {
+        config.experiment_seed = ledger.seed();
+    }
+    let plan = ExperimentPlan::expand(&config);
+    info!("Starting experiment mode: {} tests, estimated {} s", plan.points.len(), plan.estimated_duration().as_secs());
+    // Record the seed the schedule was drawn with in the manifests, also if it was chosen randomly
+    config.experiment_seed = plan.seed.or(config.experiment_seed);
+    let config = &config;
+    let started = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+    let plan_file = Path::new(RESULTS_DIR).join(format!("plan_{}.csv", started));
+    match plan.write_csv(&plan_file) {
+        Ok(()) => info!("Scheduled the tests {} ({}), see {}", plan.schedule.name(), plan.seed.map_or("no seed".to_string(), |seed| format!("seed {}", seed)), plan_file.display()),
+        Err(e) => error!("Couldn't write experiment plan: {}", e),
+    }
+
+    let completed = plan.points.iter().filter(|point| ledger.status(point.iteration, &point.id()) == Some(PointStatus::Completed)).count();
+    if completed > 0 {
+        info!("Resuming the campaign in {}: {} of {} tests already completed", ledger.path().display(), completed, plan.points.len());
+    }
+    let mut iteration = None;
+    let result = run_campaign(&plan, &mut ledger, config.experiment_retries, |point| {
+        if iteration != Some(point.iteration) {
+            info!("Starting experiment iteration: {}", point.iteration);
+            iteration = Some(point.iteration);
+        }
+        info!("Starting experiment {}/{} for {} ({}): {}", point.position + 1, plan.points.len() / config.experiment_count.max(1), point.target.name, point.target.address, point.id());
+        let result = run_speedtest(point.spec(config), config, point.iteration, Some(point));
+        thread::sleep(plan.pause);
+        result
+    });
+    match result {
+        Ok(incomplete) if !incomplete.is_empty() => {
+            let points = incomplete.iter().map(|(iteration, point, _)| format!("{} (iteration {})", point, iteration)).collect::<Vec<_>>();
+            error!("{} experiments didn't complete: {}", incomplete.len(), points.join(", "));
+        }
+        Ok(_) => {}
+        Err(e) => error!("Couldn't record experiment progress in {}: {}", ledger.path().display(), e),
+    }
+    let status_file = ledger.path().with_file_name(STATUS_FILE);
+    match ledger.write_status_csv(&status_file) {
+        Ok(()) => info!("Wrote the status of every experiment to {}", status_file.display()),
+        Err(e) => error!("Couldn't write campaign status: {}", e),
+    }
+}
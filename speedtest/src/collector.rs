@@ -0,0 +1,87 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::delivery::{DeliveryStats, ReorderingTracker};
+use crate::jitter::JitterTracker;
+use crate::outage::{OutageConfig, OutageDetector, OutageReport};
+use crate::timeline::{ProbeTracker, TimelineEntry};
+
+/// Statistics of a test that depend on the order of its packets.
+#[derive(Debug, Clone)]
+pub struct CollectedStats {
+    /// Only the reordering metrics, the counts are taken on the receiver threads.
+    pub delivery: DeliveryStats,
+    pub jitter: JitterTracker,
+    pub outages: OutageReport,
+}
+
+/// Computes the reordering, jitter and outages of a running test from its packet timestamps.
+///
+/// Responses are matched to probes like in the timeline, so only the probes within the loss timeout are
+/// kept, however long the test runs. Answers arriving after the loss timeout still count as reordered,
+/// but their probes count as lost for the IPDV and the outages.
+pub struct StatsCollector {
+    sender: Sender<TimelineEntry>,
+    thread: JoinHandle<Collector>,
+}
+
+impl StatsCollector {
+    pub fn start(loss_timeout: Duration, outage: &OutageConfig) -> Self {
+        let collector = Collector {
+            tracker: ProbeTracker::new(loss_timeout).retiring(),
+            reordering: ReorderingTracker::default(),
+            delivery: DeliveryStats::default(),
+            jitter: JitterTracker::default(),
+            outages: OutageDetector::new(outage),
+        };
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || collector.run(receiver));
+        StatsCollector { sender, thread }
+    }
+
+    /// Sender for the packet timestamps.
+    pub fn sender(&self) -> Sender<TimelineEntry> {
+        self.sender.clone()
+    }
+
+    /// Counts the probes that are still pending and returns the statistics.
+    ///
+    /// Blocks until every other sender of the collector is dropped.
+    pub fn finish(self) -> CollectedStats {
+        drop(self.sender);
+        let mut collector = self.thread.join().expect("stats collector panicked");
+        collector.tracker.finish();
+        collector.retire();
+        CollectedStats { delivery: collector.delivery, jitter: collector.jitter, outages: collector.outages.finish() }
+    }
+}
+
+struct Collector {
+    tracker: ProbeTracker,
+    reordering: ReorderingTracker,
+    delivery: DeliveryStats,
+    jitter: JitterTracker,
+    outages: OutageDetector,
+}
+
+impl Collector {
+    fn run(mut self, receiver: Receiver<TimelineEntry>) -> Self {
+        for entry in receiver {
+            for record in self.tracker.track(&entry) {
+                if let (Some(sequence), Some(rtt)) = (record.sequence, record.rtt()) {
+                    self.reordering.arrived(&mut self.delivery, sequence);
+                    self.jitter.answered(rtt);
+                }
+            }
+            self.retire();
+        }
+        self
+    }
+
+    fn retire(&mut self) {
+        for probe in self.tracker.take_retired() {
+            self.jitter.probe(probe.rtt);
+            self.outages.probe(probe.sent, probe.rtt);
+        }
+    }
+}
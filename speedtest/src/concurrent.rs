@@ -0,0 +1,262 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use log::{error, info, warn};
+use threadpool::ThreadPool;
+use crate::client::{query_server_seed, spawn_ping, PacketRecorder, SpeedtestEnum, TestResult, TestSpec, RESULTS_DIR};
+use crate::collector::StatsCollector;
+use crate::config::ClientConfig;
+use crate::stats::SampleSummary;
+use crate::transport::{Network, SystemNetwork, ThreadRole};
+
+/// Name of the file with the pairwise latency differences in the directory of a concurrent probe.
+pub const DIFFERENCES_FILE: &str = "latency_differences.csv";
+
+/// Pings several servers at the same time, each through a socket and a sender and receiver thread of its
+/// own, so that their latencies are measured over the same time window.
+///
+/// All targets are probed with the interval and duration of the first spec, starting at the same instant,
+/// so the n-th probes of all targets are sent together and can be compared pairwise. Hooks, markers, the
+/// timeline and interval reports of the specs are not supported.
+#[derive(Clone)]
+pub struct ConcurrentProbe {
+    specs: Vec<TestSpec>,
+    network: Arc<dyn Network>,
+}
+
+impl ConcurrentProbe {
+    pub fn new(specs: Vec<TestSpec>) -> Self {
+        ConcurrentProbe { specs, network: Arc::new(SystemNetwork) }
+    }
+
+    pub fn network(mut self, network: Arc<dyn Network>) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Probes all targets and blocks until the last receiver gave up waiting for responses.
+    pub fn run(&self) -> io::Result<ConcurrentResult> {
+        let first = self.specs.first().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no targets to probe"))?;
+        let specs = self
+            .specs
+            .iter()
+            // The latency differences pair the probes of the targets, so every packet is kept
+            .map(|spec| TestSpec { mode: SpeedtestEnum::Ping, interval: first.interval, duration: first.duration, packet_log: true, ..spec.clone() })
+            .collect::<Vec<_>>();
+        let network = self.network.as_ref();
+        let server_seeds = specs.iter().map(|spec| query_server_seed(spec, network)).collect::<Vec<_>>();
+        let clock = network.clock();
+        info!("Probing {} targets at the same time every {:?} for {:?}", specs.len(), first.interval, first.duration);
+
+        // Keep the clock from advancing until the threads of all targets are started
+        let setup = clock.register_thread(ThreadRole::Foreground).enter();
+        let pool = ThreadPool::new(2 * specs.len());
+        let start_time = clock.now_micros();
+        let mut probes = Vec::with_capacity(specs.len());
+        for spec in &specs {
+            let collector = StatsCollector::start(spec.receive_timeout, &spec.outage);
+            let recorder = PacketRecorder::new(spec, vec![collector.sender()]);
+            let counters = spawn_ping(spec, network, &recorder, &pool, start_time)?;
+            probes.push((recorder, collector, counters));
+        }
+        drop(setup);
+        pool.join();
+        let end_time = clock.now_micros();
+
+        let started_at = SystemTime::UNIX_EPOCH + Duration::from_micros(start_time as u64);
+        let elapsed = Duration::from_micros((end_time - start_time) as u64);
+        let results = specs
+            .into_iter()
+            .zip(probes)
+            .zip(server_seeds)
+            .map(|((spec, (recorder, collector, counters)), server_seed)| {
+                let recorded = recorder.finish();
+                let collected = collector.finish();
+                let mut delivery = recorded.delivery;
+                delivery.merge(&collected.delivery);
+                TestResult {
+                    started_at,
+                    elapsed,
+                    bytes_sent: counters.bytes_sent.load(Ordering::Relaxed),
+                    bytes_received: counters.bytes_received.load(Ordering::Relaxed),
+                    jitter: collected.jitter.finish(&recorded.histogram),
+                    histogram: recorded.histogram,
+                    server_seed,
+                    delivery,
+                    outages: collected.outages,
+                    rtt_times: recorded.rtt_times,
+                    events: Vec::new(),
+                    timeline: None,
+                    intervals: Vec::new(),
+                    spec,
+                }
+            })
+            .collect();
+        Ok(ConcurrentResult { started_at, window: first.duration, results })
+    }
+}
+
+/// Outcome of a [`ConcurrentProbe`]: one result per target, all over the same time window.
+#[derive(Debug, Clone)]
+pub struct ConcurrentResult {
+    /// Start of the window, the time the first probe of every target was due.
+    pub started_at: SystemTime,
+    /// Length of the window the probes were sent in.
+    pub window: Duration,
+    /// Results in the order of the targets.
+    pub results: Vec<TestResult>,
+}
+
+impl ConcurrentResult {
+    /// Latency differences of every pair of targets, in the order of the targets.
+    pub fn differences(&self) -> Vec<LatencyDifference> {
+        let mut differences = Vec::new();
+        for (i, first) in self.results.iter().enumerate() {
+            for second in &self.results[i + 1..] {
+                differences.push(LatencyDifference::between(first, second));
+            }
+        }
+        differences
+    }
+
+    /// Logs the statistics of every target and the pairwise differences.
+    pub fn evaluate(&self) {
+        for result in &self.results {
+            result.evaluate_rtt();
+        }
+        for difference in self.differences() {
+            match &difference.summary {
+                Some(summary) => info!(
+                    "{} - {}: RTT difference (ms) mean {:.3}, median {:.3} over {} probe pairs",
+                    difference.first,
+                    difference.second,
+                    summary.mean / 1000.0,
+                    summary.median / 1000.0,
+                    summary.count
+                ),
+                None => warn!("{} - {}: no probe answered by both", difference.first, difference.second),
+            }
+        }
+    }
+
+    /// Writes the results of every target and the latency differences to `dir`.
+    pub fn write_to_dir(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for result in &self.results {
+            result.write_evaluated_data_to_csv(dir)?;
+            if result.has_rtts() {
+                result.write_histogram_log(dir)?;
+            }
+        }
+        self.write_differences_csv(&dir.join(DIFFERENCES_FILE))
+    }
+
+    /// Writes one row per pair of targets with the summary of their RTT differences in microseconds.
+    pub fn write_differences_csv(&self, path: &Path) -> io::Result<()> {
+        let start = self.started_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_micros();
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(["Window Start", "Window End", "First Server", "Second Server", "Probe Pairs", "Mean Difference", "Median Difference", "Standard Deviation", "95% CI Low", "95% CI High"])?;
+        let float = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+        for difference in self.differences() {
+            let summary = difference.summary.as_ref();
+            wtr.write_record([
+                start.to_string(),
+                (start + self.window.as_micros()).to_string(),
+                difference.first,
+                difference.second,
+                summary.map_or(0, |summary| summary.count).to_string(),
+                float(summary.map(|summary| summary.mean)),
+                float(summary.map(|summary| summary.median)),
+                float(summary.and_then(|summary| summary.stddev)),
+                float(summary.and_then(|summary| summary.ci95).map(|ci| ci.0)),
+                float(summary.and_then(|summary| summary.ci95).map(|ci| ci.1)),
+            ])?;
+        }
+        wtr.flush()
+    }
+}
+
+/// RTT of the first target minus that of the second, compared probe by probe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyDifference {
+    pub first: String,
+    pub second: String,
+    /// Summary of the differences in microseconds over the probes both targets answered, `None` if there
+    /// are none.
+    pub summary: Option<SampleSummary>,
+}
+
+impl LatencyDifference {
+    /// Pairs the n-th probe of `first` with the n-th probe of `second`, which were sent at the same time.
+    pub fn between(first: &TestResult, second: &TestResult) -> Self {
+        let rtts = |result: &TestResult| {
+            result
+                .rtt_times
+                .rtt_times
+                .iter()
+                .filter(|packet| packet.sent_time != 0)
+                .map(|packet| (packet.received_time != 0).then(|| packet.received_time - packet.sent_time))
+                .collect::<Vec<_>>()
+        };
+        let differences = rtts(first)
+            .into_iter()
+            .zip(rtts(second))
+            .filter_map(|pair| match pair {
+                (Some(first), Some(second)) => Some(first as f64 - second as f64),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        LatencyDifference {
+            first: first.spec.server_name().to_string(),
+            second: second.spec.server_name().to_string(),
+            summary: SampleSummary::from_values(&differences),
+        }
+    }
+}
+
+/// Specs to probe all `experiment_servers` at the same time with the configured ping interval, duration and
+/// payload size. Every target gets a socket with an ephemeral port on the IP of `client_addr`.
+pub fn specs(config: &ClientConfig) -> Vec<TestSpec> {
+    config
+        .experiment_servers
+        .iter()
+        .map(|target| {
+            let mut spec = TestSpec::from_config(config, target, config.payload_size);
+            spec.client_addr = ephemeral(&spec.client_addr);
+            spec.timeline = None;
+            spec.report_interval = None;
+            spec
+        })
+        .collect()
+}
+
+/// Probes all `experiment_servers` at the same time, see [`specs`], and writes the results to
+/// `results/concurrent_<start ms>/`.
+///
+/// Returns the directory the results were written to.
+pub fn run(config: &ClientConfig) -> io::Result<PathBuf> {
+    let started = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+    let dir = Path::new(RESULTS_DIR).join(format!("concurrent_{}", started));
+    let result = match ConcurrentProbe::new(specs(config)).run() {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Concurrent probing failed: {}", e);
+            return Err(e);
+        }
+    };
+    result.evaluate();
+    result.write_to_dir(&dir)?;
+    info!("Wrote results to {}", dir.display());
+    Ok(dir)
+}
+
+/// `addr` with port 0, so that every target gets a port of its own.
+fn ephemeral(addr: &str) -> String {
+    match addr.rsplit_once(':') {
+        Some((host, _port)) => format!("{}:0", host),
+        None => format!("{}:0", addr),
+    }
+}
@@ -0,0 +1,538 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::client::{file_name_part, RESULTS_DIR};
+use crate::events::MarkerSource;
+use crate::failover::FailoverConfig;
+use crate::hooks::{Hook, HookStage};
+use crate::ledger::LEDGER_FILE;
+use crate::placement::{PlacementCriterion, PlacementMetric, PlacementPolicy};
+use crate::plan::Schedule;
+use crate::stats::DEFAULT_PERCENTILES;
+use serde_json::json;
+use yaml_rust::{Yaml, YamlLoader};
+
+/// Client configuration shipped with the binary, used when no `--config` is given.
+pub const DEFAULT_CLIENT_CONFIG: &str = include_str!("../client_config.yaml");
+/// Server configuration shipped with the binary, used when no `--config` is given.
+pub const DEFAULT_SERVER_CONFIG: &str = include_str!("../server_config.yaml");
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(yaml_rust::ScanError),
+    Missing(&'static str),
+    Invalid(&'static str, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "couldn't read config file: {}", e),
+            ConfigError::Parse(e) => write!(f, "couldn't parse config file: {}", e),
+            ConfigError::Missing(key) => write!(f, "missing config key `{}`", key),
+            ConfigError::Invalid(key, value) => write!(f, "invalid value `{}` for config key `{}`", value, key),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<yaml_rust::ScanError> for ConfigError {
+    fn from(e: yaml_rust::ScanError) -> Self {
+        ConfigError::Parse(e)
+    }
+}
+
+/// Loads the YAML document at `path`, falling back to the built-in `default` document.
+pub fn load_yaml(path: Option<&Path>, default: &str) -> Result<Yaml, ConfigError> {
+    let content = match path {
+        Some(path) => fs::read_to_string(path)?,
+        None => default.to_string(),
+    };
+    let mut docs = YamlLoader::load_from_str(&content)?;
+    if docs.is_empty() {
+        return Ok(Yaml::Null);
+    }
+    Ok(docs.swap_remove(0))
+}
+
+fn require_str(doc: &Yaml, key: &'static str) -> Result<String, ConfigError> {
+    match &doc[key] {
+        Yaml::BadValue => Err(ConfigError::Missing(key)),
+        value => value.as_str().map(str::to_string).ok_or_else(|| ConfigError::Invalid(key, format!("{:?}", value))),
+    }
+}
+
+fn maybe_u64(doc: &Yaml, key: &'static str) -> Result<Option<u64>, ConfigError> {
+    match &doc[key] {
+        Yaml::BadValue | Yaml::Null => Ok(None),
+        Yaml::Integer(value) if *value >= 0 => Ok(Some(*value as u64)),
+        value => Err(ConfigError::Invalid(key, format!("{:?}", value))),
+    }
+}
+
+/// A seed as logged, i.e. any u64. YAML integers are only 64-bit signed, so larger seeds are read as
+/// reals and taken from their text.
+fn maybe_seed(doc: &Yaml, key: &'static str) -> Result<Option<u64>, ConfigError> {
+    match &doc[key] {
+        Yaml::Real(text) => text.parse().map(Some).map_err(|_| ConfigError::Invalid(key, format!("{:?}", doc[key]))),
+        _ => maybe_u64(doc, key),
+    }
+}
+
+fn optional_u64(doc: &Yaml, key: &'static str, default: u64) -> Result<u64, ConfigError> {
+    Ok(maybe_u64(doc, key)?.unwrap_or(default))
+}
+
+fn optional_f64(doc: &Yaml, key: &'static str, default: f64) -> Result<f64, ConfigError> {
+    match &doc[key] {
+        Yaml::BadValue | Yaml::Null => Ok(default),
+        Yaml::Integer(value) => Ok(*value as f64),
+        Yaml::Real(_) => doc[key].as_f64().ok_or_else(|| ConfigError::Invalid(key, format!("{:?}", doc[key]))),
+        value => Err(ConfigError::Invalid(key, format!("{:?}", value))),
+    }
+}
+
+/// Reads the list at `key` with `parse`, `None` if there is none.
+fn optional_list<T>(doc: &Yaml, key: &'static str, parse: impl Fn(&Yaml) -> Option<T>) -> Result<Option<Vec<T>>, ConfigError> {
+    match &doc[key] {
+        Yaml::BadValue | Yaml::Null => Ok(None),
+        Yaml::Array(values) => values.iter().map(|value| parse(value).ok_or_else(|| ConfigError::Invalid(key, format!("{:?}", value)))).collect::<Result<Vec<_>, _>>().map(Some),
+        value => Err(ConfigError::Invalid(key, format!("{:?}", value))),
+    }
+}
+
+/// Seconds as a non-negative integer or float.
+fn seconds(value: &Yaml) -> Option<Duration> {
+    value.as_f64().or_else(|| value.as_i64().map(|value| value as f64)).filter(|value| *value >= 0.0).map(Duration::from_secs_f64)
+}
+
+fn optional_bool(doc: &Yaml, key: &'static str, default: bool) -> Result<bool, ConfigError> {
+    match &doc[key] {
+        Yaml::BadValue | Yaml::Null => Ok(default),
+        Yaml::Boolean(value) => Ok(*value),
+        value => Err(ConfigError::Invalid(key, format!("{:?}", value))),
+    }
+}
+
+/// A server the experiments run against, with optional overrides of the client config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentTarget {
+    /// Human-readable name used in result files and logs.
+    pub name: String,
+    pub address: String,
+    /// Overrides `experiment_payload_sizes`.
+    pub payload_sizes: Option<Vec<usize>>,
+    /// Overrides `bitrate` and `bitrate_scale`.
+    pub bitrate: Option<i64>,
+    pub bitrate_scale: Option<String>,
+    /// Overrides `speedtest_mode`.
+    pub speedtest_mode: Option<String>,
+}
+
+impl ExperimentTarget {
+    /// A target without overrides.
+    pub fn new(name: impl Into<String>, address: impl Into<String>) -> Self {
+        ExperimentTarget { name: name.into(), address: address.into(), payload_sizes: None, bitrate: None, bitrate_scale: None, speedtest_mode: None }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "name": self.name,
+            "address": self.address,
+            "payload_sizes": self.payload_sizes,
+            "bitrate": self.bitrate,
+            "bitrate_scale": self.bitrate_scale,
+            "speedtest_mode": self.speedtest_mode,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub server_addr: String,
+    pub client_addr: String,
+    pub payload_size: usize,
+    pub packet_count: usize,
+    pub speedtest_duration: Duration,
+    pub ping_interval: Duration,
+    pub bitrate: i64,
+    pub bitrate_scale: String,
+    pub speedtest_mode: String,
+    pub experiment_mode: bool,
+    pub experiment_count: usize,
+    pub experiment_interval: Duration,
+    pub experiment_servers: Vec<ExperimentTarget>,
+    pub experiment_payload_sizes: Vec<usize>,
+    /// Speedtest modes, bitrates (in `bitrate_scale`), ping intervals and test durations swept in experiment
+    /// mode, by default the single configured value.
+    pub experiment_modes: Vec<String>,
+    pub experiment_bitrates: Vec<i64>,
+    pub experiment_intervals: Vec<Duration>,
+    pub experiment_durations: Vec<Duration>,
+    /// Order of the tests within every iteration.
+    pub experiment_schedule: Schedule,
+    /// Seed of the random schedule, a random one is chosen if not set.
+    pub experiment_seed: Option<u64>,
+    /// How often a failed test is retried.
+    pub experiment_retries: usize,
+    /// Completion ledger of the campaign, used to resume it.
+    pub experiment_ledger: PathBuf,
+    /// Significant decimal digits of the RTT histograms.
+    pub histogram_precision: u8,
+    /// Percentiles of the RTTs to report, in percent.
+    pub percentiles: Vec<f64>,
+    /// Consecutive failed probes that make up an outage.
+    pub outage_min_probes: usize,
+    /// Probes slower than this count as failed in outage detection.
+    pub outage_rtt_threshold: Option<Duration>,
+    /// Sources of event markers to listen on during the tests.
+    pub event_markers: Vec<MarkerSource>,
+    /// Commands to run before, during and after every test.
+    pub hooks: Vec<Hook>,
+    /// Whether to export a per-packet timeline of every test.
+    pub timeline: bool,
+    /// Interval of the traffic reports while a test is running, `None` for no reports.
+    pub report_interval: Option<Duration>,
+    /// Whether to keep the timestamps of every packet in memory, see [`crate::client::TestSpec::packet_log`].
+    pub packet_log: bool,
+    /// Whether to also write every result in the JSON structure of `iperf3 -J`.
+    pub json_output: bool,
+    /// Human-readable names of the servers by address, for `server_addr` and targets given as bare addresses.
+    pub server_labels: BTreeMap<String, String>,
+    /// Composite score and SLA thresholds the placement advisor ranks the targets by.
+    pub placement: PlacementPolicy,
+    /// Ordered servers and health-check rules of the failover client.
+    pub failover: FailoverConfig,
+}
+
+impl ClientConfig {
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        Self::from_yaml(&load_yaml(path, DEFAULT_CLIENT_CONFIG)?)
+    }
+
+    pub fn from_yaml(doc: &Yaml) -> Result<Self, ConfigError> {
+        let server_addr = require_str(doc, "server_addr")?;
+        let server_labels = server_labels(&doc["server_labels"])?;
+        let name = |address: &str| server_labels.get(address).cloned().unwrap_or_else(|| address.to_string());
+        let experiment_servers = match doc["experiment_servers"].as_vec() {
+            Some(servers) => servers.iter().map(|server| experiment_target(server, &name)).collect::<Result<Vec<_>, _>>()?,
+            None => vec![ExperimentTarget::new(name(&server_addr), server_addr.clone())],
+        };
+        unique_target_names(&experiment_servers)?;
+        let failover = failover(&doc["failover"], &name, &experiment_servers)?;
+        let payload_size = optional_u64(doc, "payload_size", 500)? as usize;
+        let experiment_payload_sizes = match doc["experiment_payload_sizes"].as_vec() {
+            Some(sizes) => sizes
+                .iter()
+                .map(|size| size.as_i64().filter(|size| *size >= 0).map(|size| size as usize).ok_or_else(|| ConfigError::Invalid("experiment_payload_sizes", format!("{:?}", size))))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![payload_size],
+        };
+        let speedtest_mode = doc["speedtest_mode"].as_str().unwrap_or("duration").to_lowercase();
+        let bitrate = optional_u64(doc, "bitrate", 100)? as i64;
+        let speedtest_duration = Duration::from_secs_f64(optional_f64(doc, "speedtest_duration", 10.0)?);
+        let ping_interval = Duration::from_secs_f64(optional_f64(doc, "ping_interval", 1.0)?);
+        let percentiles = match doc["percentiles"].as_vec() {
+            Some(percentiles) => percentiles
+                .iter()
+                .map(|percentile| {
+                    percentile.as_f64().or_else(|| percentile.as_i64().map(|percentile| percentile as f64))
+                        .filter(|percentile| (0.0..=100.0).contains(percentile))
+                        .ok_or_else(|| ConfigError::Invalid("percentiles", format!("{:?}", percentile)))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => DEFAULT_PERCENTILES.to_vec(),
+        };
+
+        Ok(ClientConfig {
+            server_addr,
+            client_addr: require_str(doc, "client_addr")?,
+            payload_size,
+            packet_count: optional_u64(doc, "packet_count", 1000)? as usize,
+            speedtest_duration,
+            ping_interval,
+            bitrate,
+            bitrate_scale: doc["bitrate_scale"].as_str().unwrap_or("M").to_string(),
+            experiment_modes: optional_list(doc, "experiment_modes", |mode| mode.as_str().map(str::to_lowercase))?.unwrap_or_else(|| vec![speedtest_mode.clone()]),
+            experiment_bitrates: optional_list(doc, "experiment_bitrates", |bitrate| bitrate.as_i64().filter(|bitrate| *bitrate >= 0))?.unwrap_or_else(|| vec![bitrate]),
+            experiment_intervals: optional_list(doc, "experiment_intervals", seconds)?.unwrap_or_else(|| vec![ping_interval]),
+            experiment_durations: optional_list(doc, "experiment_durations", seconds)?.unwrap_or_else(|| vec![speedtest_duration]),
+            speedtest_mode,
+            experiment_schedule: match doc["experiment_schedule"].as_str() {
+                Some(schedule) => Schedule::from_string(&schedule.to_lowercase()).ok_or_else(|| ConfigError::Invalid("experiment_schedule", schedule.to_string()))?,
+                None => Schedule::Sequential,
+            },
+            experiment_seed: maybe_seed(doc, "experiment_seed")?,
+            experiment_retries: optional_u64(doc, "experiment_retries", 1)? as usize,
+            experiment_ledger: doc["experiment_ledger"].as_str().map_or_else(|| Path::new(RESULTS_DIR).join(LEDGER_FILE), PathBuf::from),
+            experiment_mode: optional_bool(doc, "experiment_mode", false)?,
+            experiment_count: optional_u64(doc, "experiment_count", 1)? as usize,
+            experiment_interval: Duration::from_secs_f64(optional_f64(doc, "experiment_interval", 1.0)?),
+            experiment_servers,
+            experiment_payload_sizes,
+            histogram_precision: optional_u64(doc, "histogram_precision", 3)?.clamp(1, 5) as u8,
+            percentiles,
+            outage_min_probes: optional_u64(doc, "outage_min_probes", 3)?.max(1) as usize,
+            outage_rtt_threshold: match optional_f64(doc, "outage_rtt_threshold", 0.0)? {
+                threshold if threshold > 0.0 => Some(Duration::from_secs_f64(threshold / 1000.0)),
+                _ => None,
+            },
+            event_markers: event_markers(&doc["event_markers"])?,
+            hooks: hooks(&doc["hooks"])?,
+            timeline: optional_bool(doc, "timeline", false)?,
+            packet_log: optional_bool(doc, "packet_log", false)?,
+            json_output: optional_bool(doc, "json_output", false)?,
+            server_labels,
+            placement: placement(&doc["placement"])?,
+            failover,
+            report_interval: match optional_f64(doc, "report_interval", 0.0)? {
+                interval if interval > 0.0 => Some(Duration::from_secs_f64(interval)),
+                _ => None,
+            },
+        })
+    }
+
+    /// The resolved config with the keys and units of the YAML file, e.g. for run manifests.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut markers = serde_json::Map::new();
+        for marker in &self.event_markers {
+            match marker {
+                MarkerSource::Signal => markers.insert("signal".to_string(), json!(true)),
+                MarkerSource::Socket(path) => markers.insert("socket".to_string(), json!(path)),
+                MarkerSource::Pipe(path) => markers.insert("pipe".to_string(), json!(path)),
+            };
+        }
+        let commands = |stage: HookStage| self.hooks.iter().filter(|hook| hook.stage == stage).map(|hook| json!(hook.command)).collect::<Vec<_>>();
+        let during = self
+            .hooks
+            .iter()
+            .filter_map(|hook| match hook.stage {
+                HookStage::During(offset) => Some(json!({ "command": hook.command, "offset": offset.as_secs_f64() })),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "server_addr": self.server_addr,
+            "client_addr": self.client_addr,
+            "payload_size": self.payload_size,
+            "packet_count": self.packet_count,
+            "speedtest_duration": self.speedtest_duration.as_secs_f64(),
+            "ping_interval": self.ping_interval.as_secs_f64(),
+            "bitrate": self.bitrate,
+            "bitrate_scale": self.bitrate_scale,
+            "speedtest_mode": self.speedtest_mode,
+            "experiment_mode": self.experiment_mode,
+            "experiment_count": self.experiment_count,
+            "experiment_interval": self.experiment_interval.as_secs_f64(),
+            "experiment_servers": self.experiment_servers.iter().map(ExperimentTarget::to_json).collect::<Vec<_>>(),
+            "experiment_payload_sizes": self.experiment_payload_sizes,
+            "experiment_modes": self.experiment_modes,
+            "experiment_bitrates": self.experiment_bitrates,
+            "experiment_intervals": self.experiment_intervals.iter().map(Duration::as_secs_f64).collect::<Vec<_>>(),
+            "experiment_durations": self.experiment_durations.iter().map(Duration::as_secs_f64).collect::<Vec<_>>(),
+            "experiment_schedule": self.experiment_schedule.name(),
+            "experiment_seed": self.experiment_seed,
+            "experiment_retries": self.experiment_retries,
+            "experiment_ledger": self.experiment_ledger,
+            "histogram_precision": self.histogram_precision,
+            "percentiles": self.percentiles,
+            "outage_min_probes": self.outage_min_probes,
+            "outage_rtt_threshold": self.outage_rtt_threshold.map(|threshold| threshold.as_secs_f64() * 1000.0),
+            "event_markers": markers,
+            "hooks": { "before": commands(HookStage::Before), "during": during, "after": commands(HookStage::After) },
+            "report_interval": self.report_interval.map(|interval| interval.as_secs_f64()),
+            "timeline": self.timeline,
+            "packet_log": self.packet_log,
+            "json_output": self.json_output,
+            "server_labels": self.server_labels,
+            "placement": self.placement.to_json(),
+            "failover": self.failover.to_json(),
+        })
+    }
+}
+
+impl ClientConfig {
+    /// Name of the server at `address`, from `server_labels` or else the address itself.
+    pub fn server_name(&self, address: &str) -> String {
+        self.server_labels.get(address).cloned().unwrap_or_else(|| address.to_string())
+    }
+}
+
+/// Reads a target either as a bare address, named after `name`, or as an object with `name`, `address`
+/// and optional `payload_sizes`, `bitrate`, `bitrate_scale` and `speedtest_mode`.
+fn experiment_target(doc: &Yaml, name: &dyn Fn(&str) -> String) -> Result<ExperimentTarget, ConfigError> {
+    if let Some(address) = doc.as_str() {
+        return Ok(ExperimentTarget::new(name(address), address));
+    }
+    if doc.as_hash().is_none() {
+        return Err(ConfigError::Invalid("experiment_servers", format!("{:?}", doc)));
+    }
+    let address = require_str(doc, "address")?;
+    let payload_sizes = match &doc["payload_sizes"] {
+        Yaml::BadValue | Yaml::Null => None,
+        Yaml::Array(sizes) => Some(
+            sizes
+                .iter()
+                .map(|size| size.as_i64().filter(|size| *size >= 0).map(|size| size as usize).ok_or_else(|| ConfigError::Invalid("payload_sizes", format!("{:?}", size))))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        value => return Err(ConfigError::Invalid("payload_sizes", format!("{:?}", value))),
+    };
+    Ok(ExperimentTarget {
+        name: doc["name"].as_str().map(str::to_string).unwrap_or_else(|| name(&address)),
+        payload_sizes,
+        bitrate: maybe_u64(doc, "bitrate")?.map(|bitrate| bitrate as i64),
+        bitrate_scale: doc["bitrate_scale"].as_str().map(str::to_string),
+        speedtest_mode: doc["speedtest_mode"].as_str().map(str::to_lowercase),
+        address,
+    })
+}
+
+/// Rejects targets whose names are equal as part of file names, as their results and ledger entries
+/// would be mixed up.
+fn unique_target_names(targets: &[ExperimentTarget]) -> Result<(), ConfigError> {
+    for (i, target) in targets.iter().enumerate() {
+        if let Some(other) = targets[..i].iter().find(|other| file_name_part(&other.name) == file_name_part(&target.name)) {
+            return Err(ConfigError::Invalid("experiment_servers", format!("{} ({}) has the same name as {} ({})", target.name, target.address, other.name, other.address)));
+        }
+    }
+    Ok(())
+}
+
+fn server_labels(doc: &Yaml) -> Result<BTreeMap<String, String>, ConfigError> {
+    match doc {
+        Yaml::BadValue | Yaml::Null => Ok(BTreeMap::new()),
+        Yaml::Hash(labels) => labels
+            .iter()
+            .map(|(address, label)| match (address.as_str(), label.as_str()) {
+                (Some(address), Some(label)) => Ok((address.to_string(), label.to_string())),
+                _ => Err(ConfigError::Invalid("server_labels", format!("{:?}: {:?}", address, label))),
+            })
+            .collect(),
+        value => Err(ConfigError::Invalid("server_labels", format!("{:?}", value))),
+    }
+}
+
+fn event_markers(doc: &Yaml) -> Result<Vec<MarkerSource>, ConfigError> {
+    let mut markers = Vec::new();
+    if optional_bool(doc, "signal", false)? {
+        markers.push(MarkerSource::Signal);
+    }
+    if let Some(path) = doc["socket"].as_str() {
+        markers.push(MarkerSource::Socket(PathBuf::from(path)));
+    }
+    if let Some(path) = doc["pipe"].as_str() {
+        markers.push(MarkerSource::Pipe(PathBuf::from(path)));
+    }
+    Ok(markers)
+}
+
+/// Reads `before` and `after` as lists of commands and `during` as a list of `command`/`offset` pairs, with the
+/// offset in seconds.
+fn hooks(doc: &Yaml) -> Result<Vec<Hook>, ConfigError> {
+    let commands = |key: &'static str| match &doc[key] {
+        Yaml::BadValue | Yaml::Null => Ok(Vec::new()),
+        Yaml::Array(commands) => commands.iter().map(|command| command.as_str().map(str::to_string).ok_or_else(|| ConfigError::Invalid("hooks", format!("{:?}", command)))).collect(),
+        value => Err(ConfigError::Invalid("hooks", format!("{:?}", value))),
+    };
+    let mut hooks = commands("before")?.into_iter().map(|command| Hook::new(HookStage::Before, command)).collect::<Vec<_>>();
+    for hook in doc["during"].as_vec().map(Vec::as_slice).unwrap_or_default() {
+        let command = require_str(hook, "command")?;
+        let offset = Duration::from_secs_f64(optional_f64(hook, "offset", 0.0)?.max(0.0));
+        hooks.push(Hook::new(HookStage::During(offset), command));
+    }
+    hooks.extend(commands("after")?.into_iter().map(|command| Hook::new(HookStage::After, command)));
+    Ok(hooks)
+}
+
+/// Reads `criteria` as a list of `metric`/`weight`/`sla` entries and `interval` in seconds, the defaults of
+/// [`PlacementPolicy`] for missing keys.
+fn placement(doc: &Yaml) -> Result<PlacementPolicy, ConfigError> {
+    let default = PlacementPolicy::default();
+    let criteria = match &doc["criteria"] {
+        Yaml::BadValue | Yaml::Null => default.criteria,
+        Yaml::Array(criteria) => criteria
+            .iter()
+            .map(|criterion| {
+                let metric = require_str(criterion, "metric")?;
+                Ok(PlacementCriterion {
+                    metric: PlacementMetric::from_string(&metric.to_lowercase()).ok_or(ConfigError::Invalid("placement.criteria", metric))?,
+                    weight: Some(optional_f64(criterion, "weight", 1.0)?).filter(|weight| *weight >= 0.0).ok_or_else(|| ConfigError::Invalid("weight", format!("{:?}", criterion["weight"])))?,
+                    sla: Some(optional_f64(criterion, "sla", 0.0)?).filter(|sla| *sla > 0.0).ok_or_else(|| ConfigError::Invalid("sla", format!("{:?}", criterion["sla"])))?,
+                })
+            })
+            .collect::<Result<Vec<_>, ConfigError>>()?,
+        value => return Err(ConfigError::Invalid("placement.criteria", format!("{:?}", value))),
+    };
+    if criteria.iter().map(|criterion| criterion.weight).sum::<f64>() <= 0.0 {
+        return Err(ConfigError::Invalid("placement.criteria", "no criterion with a positive weight".to_string()));
+    }
+    Ok(PlacementPolicy { criteria, interval: Duration::from_secs_f64(optional_f64(doc, "interval", default.interval.as_secs_f64())?.max(0.0)) })
+}
+
+/// Reads `servers` like `experiment_servers`, which they default to, the intervals in seconds, `timeout` and
+/// `rtt_threshold` in milliseconds, and the defaults of [`FailoverConfig`] for the other missing keys.
+fn failover(doc: &Yaml, name: &dyn Fn(&str) -> String, experiment_servers: &[ExperimentTarget]) -> Result<FailoverConfig, ConfigError> {
+    let default = FailoverConfig::default();
+    let millis = |key: &'static str, default: Duration| -> Result<Duration, ConfigError> { Ok(Duration::from_secs_f64(optional_f64(doc, key, default.as_secs_f64() * 1000.0)?.max(0.0) / 1000.0)) };
+    let secs = |key: &'static str, default: Duration| -> Result<Duration, ConfigError> { Ok(Duration::from_secs_f64(optional_f64(doc, key, default.as_secs_f64())?.max(0.0))) };
+    Ok(FailoverConfig {
+        servers: match doc["servers"].as_vec() {
+            Some(servers) => servers.iter().map(|server| experiment_target(server, name)).collect::<Result<Vec<_>, _>>()?,
+            None => experiment_servers.to_vec(),
+        },
+        interval: secs("interval", default.interval)?,
+        health_interval: secs("health_interval", default.health_interval)?,
+        timeout: millis("timeout", default.timeout)?,
+        max_losses: optional_u64(doc, "max_losses", default.max_losses as u64)?.max(1) as usize,
+        rtt_threshold: Some(millis("rtt_threshold", Duration::ZERO)?).filter(|threshold| !threshold.is_zero()),
+        failback: optional_bool(doc, "failback", default.failback)?,
+        recovery_probes: optional_u64(doc, "recovery_probes", default.recovery_probes as u64)?.max(1) as usize,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub address: String,
+    pub qos_profile: String,
+    /// Seed of the impairment RNG, a random one is chosen if not set.
+    pub seed: Option<u64>,
+    pub jitter: u64,
+    pub delay: u64,
+    pub loss: u64,
+    pub duplicate: u64,
+    pub reorder: u64,
+    pub reorder_delay: u64,
+}
+
+impl ServerConfig {
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        Self::from_yaml(&load_yaml(path, DEFAULT_SERVER_CONFIG)?)
+    }
+
+    pub fn from_yaml(doc: &Yaml) -> Result<Self, ConfigError> {
+        let server = &doc["server"];
+        let qos = &doc["qos_profile_config"];
+        Ok(ServerConfig {
+            address: require_str(server, "address")?,
+            qos_profile: server["qos_profile"].as_str().unwrap_or("default").to_string(),
+            seed: maybe_seed(server, "seed")?,
+            jitter: optional_u64(qos, "jitter", 0)?,
+            delay: optional_u64(qos, "delay", 0)?,
+            loss: optional_u64(qos, "loss", 0)?,
+            duplicate: optional_u64(qos, "duplicate", 0)?,
+            reorder: optional_u64(qos, "reorder", 0)?,
+            reorder_delay: optional_u64(qos, "reorder_delay", 0)?,
+        })
+    }
+}
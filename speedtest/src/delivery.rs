@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+use crate::client::RTTTimes;
+
+/// Number of the latest probes whose answers are kept for the reordering metrics. Session IDs wrap
+/// around after as many probes, so older probes can't be told apart from newer ones anyway.
+const REORDERING_WINDOW: u64 = 1 << 16;
+
+/// Loss, duplication and reordering of the probes of a test.
+///
+/// Probes are numbered in the order they were sent. Reordering follows RFC 4737 on the first answer of
+/// every probe: a probe is reordered if it arrives after a probe with a higher number. Its reordering
+/// extent is the number of answers between the earliest such answer and its own one, and it is
+/// n-reordered if the n answers right before it all belong to higher-numbered probes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeliveryStats {
+    pub sent: u64,
+    pub delivered: u64,
+    pub lost: u64,
+    /// Probes answered more than once.
+    pub duplicated: u64,
+    /// Extra copies of answers in total.
+    pub duplicates: u64,
+    pub reordered: u64,
+    /// Answers that didn't match any probe.
+    pub unsolicited: u64,
+    pub max_reordering_extent: u64,
+    pub total_reordering_extent: u64,
+    /// Number of n-reordered probes at index n - 1. A probe that is n-reordered is also m-reordered for
+    /// every m < n.
+    pub n_reordering: Vec<u64>,
+}
+
+impl DeliveryStats {
+    pub fn from_rtt_times(rtt_times: &RTTTimes) -> Self {
+        let mut stats = DeliveryStats::default();
+        // Sequence numbers of the answered probes in the order their first answers arrived
+        let mut arrivals = Vec::new();
+        for packet in &rtt_times.rtt_times {
+            if packet.sent_time == 0 {
+                stats.unsolicited += 1;
+                continue;
+            }
+            let sequence = stats.sent;
+            stats.sent += 1;
+            if packet.received_time == 0 {
+                continue;
+            }
+            arrivals.push((packet.received_time, sequence));
+            stats.delivered += 1;
+            if packet.duplicates > 0 {
+                stats.duplicated += 1;
+                stats.duplicates += packet.duplicates as u64;
+            }
+        }
+        stats.lost = stats.sent - stats.delivered;
+        arrivals.sort_unstable();
+
+        let mut reordering = ReorderingTracker::default();
+        for (_, sequence) in arrivals {
+            reordering.arrived(&mut stats, sequence);
+        }
+        stats
+    }
+
+    /// Adds the counts of `other`, e.g. of another thread of the same test.
+    pub fn merge(&mut self, other: &DeliveryStats) {
+        self.sent += other.sent;
+        self.delivered += other.delivered;
+        self.lost += other.lost;
+        self.duplicated += other.duplicated;
+        self.duplicates += other.duplicates;
+        self.reordered += other.reordered;
+        self.unsolicited += other.unsolicited;
+        self.max_reordering_extent = self.max_reordering_extent.max(other.max_reordering_extent);
+        self.total_reordering_extent += other.total_reordering_extent;
+        if self.n_reordering.len() < other.n_reordering.len() {
+            self.n_reordering.resize(other.n_reordering.len(), 0);
+        }
+        for (count, other) in self.n_reordering.iter_mut().zip(&other.n_reordering) {
+            *count += other;
+        }
+    }
+
+    /// Share of the sent probes that were never answered.
+    pub fn loss_rate(&self) -> Option<f64> {
+        (self.sent > 0).then(|| self.lost as f64 / self.sent as f64)
+    }
+
+    /// Share of the sent probes that were answered more than once.
+    pub fn duplicate_rate(&self) -> Option<f64> {
+        (self.sent > 0).then(|| self.duplicated as f64 / self.sent as f64)
+    }
+
+    /// Share of the answered probes that were reordered.
+    pub fn reordered_ratio(&self) -> Option<f64> {
+        (self.delivered > 0).then(|| self.reordered as f64 / self.delivered as f64)
+    }
+
+    /// Mean reordering extent of the reordered probes.
+    pub fn mean_reordering_extent(&self) -> Option<f64> {
+        (self.reordered > 0).then(|| self.total_reordering_extent as f64 / self.reordered as f64)
+    }
+
+    /// Share of the answered probes that were n-reordered.
+    pub fn n_reordering_ratio(&self, n: usize) -> Option<f64> {
+        if self.delivered == 0 || n == 0 {
+            return None;
+        }
+        Some(self.n_reordering.get(n - 1).copied().unwrap_or(0) as f64 / self.delivered as f64)
+    }
+
+    /// Largest n for which a probe was n-reordered, 0 without reordering.
+    pub fn max_n_reordering(&self) -> usize {
+        self.n_reordering.len()
+    }
+}
+
+/// Reordering of the first answers of a running test, fed in the order they arrive.
+///
+/// Only the latest answers are kept, so the memory stays bounded however long the test runs.
+#[derive(Debug, Clone, Default)]
+pub struct ReorderingTracker {
+    /// RFC 4737 NextExp: the sequence number after the highest one so far.
+    next_expected: u64,
+    /// Answers so far.
+    arrivals: u64,
+    /// Arrival positions at which a new highest sequence number arrived, with increasing sequence numbers
+    records: VecDeque<(u64, u64)>,
+    /// Sequence numbers of the latest answers in the order they arrived.
+    recent: VecDeque<u64>,
+}
+
+impl ReorderingTracker {
+    /// Counts the first answer to the probe with `sequence` into the reordering metrics of `stats`.
+    pub fn arrived(&mut self, stats: &mut DeliveryStats, sequence: u64) {
+        let position = self.arrivals;
+        self.arrivals += 1;
+        if sequence >= self.next_expected {
+            self.next_expected = sequence + 1;
+            self.records.push_back((position, sequence));
+            while self.records.front().is_some_and(|(_, record)| record + REORDERING_WINDOW < self.next_expected) {
+                self.records.pop_front();
+            }
+        } else {
+            stats.reordered += 1;
+
+            let earliest = self.records[self.records.partition_point(|(_, record)| *record <= sequence)].0;
+            let extent = position - earliest;
+            stats.max_reordering_extent = stats.max_reordering_extent.max(extent);
+            stats.total_reordering_extent += extent;
+
+            let n = self.recent.iter().rev().take_while(|earlier| **earlier > sequence).count();
+            if stats.n_reordering.len() < n {
+                stats.n_reordering.resize(n, 0);
+            }
+            for count in &mut stats.n_reordering[..n] {
+                *count += 1;
+            }
+        }
+        self.recent.push_back(sequence);
+        if self.recent.len() as u64 > REORDERING_WINDOW {
+            self.recent.pop_front();
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use log::{debug, info, warn};
+use crate::client::RTTTimes;
+use crate::hooks::HookOutcome;
+use crate::transport::Clock;
+
+/// How often the listener threads check for new markers and for being stopped.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Something that happened during a test, e.g. a rule update triggered by the orchestrator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// Microseconds since the Unix epoch, on the same clock as the packet timestamps.
+    pub time: u128,
+    /// Where the event came from, e.g. `signal`, `socket`, `pipe` or `hook:before`.
+    pub source: String,
+    pub label: String,
+    /// How the command finished if the event is a hook.
+    pub hook: Option<HookOutcome>,
+}
+
+impl Event {
+    pub fn new(time: u128, source: impl Into<String>, label: impl Into<String>) -> Self {
+        Event { time, source: source.into(), label: label.into(), hook: None }
+    }
+}
+
+/// Events of a client, shared between the marker listeners and the test runs.
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    events: Arc<Mutex<Vec<Event>>>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        EventLog::default()
+    }
+
+    pub fn record(&self, event: Event) {
+        info!("Event from {} at {}: {}", event.source, event.time, event.label);
+        self.events.lock().unwrap().push(event);
+    }
+
+    /// Events from `start` up to and including `end`, ordered by time.
+    pub fn between(&self, start: u128, end: u128) -> Vec<Event> {
+        let mut events = self.events.lock().unwrap().iter().filter(|event| event.time >= start && event.time <= end).cloned().collect::<Vec<_>>();
+        events.sort_by_key(|event| event.time);
+        events
+    }
+}
+
+/// External source of event markers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkerSource {
+    /// Every `SIGUSR1` the process receives marks an event.
+    Signal,
+    /// Every datagram sent to the Unix socket at this path marks an event labelled with its content.
+    Socket(PathBuf),
+    /// Every line written to the named pipe at this path marks an event labelled with the line. The pipe is
+    /// created if it doesn't exist.
+    Pipe(PathBuf),
+}
+
+/// Listens for event markers until it is dropped.
+pub struct MarkerListener {
+    running: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+    signal: Option<signal_hook::SigId>,
+    socket_path: Option<PathBuf>,
+}
+
+impl MarkerListener {
+    /// Starts a listener thread per source, recording the markers into `events` with the time of `clock`.
+    pub fn start(sources: &[MarkerSource], events: &EventLog, clock: Arc<dyn Clock>) -> io::Result<Self> {
+        let mut listener = MarkerListener { running: Arc::new(AtomicBool::new(true)), threads: Vec::new(), signal: None, socket_path: None };
+        for source in sources {
+            let running = listener.running.clone();
+            let events = events.clone();
+            let clock = clock.clone();
+            match source {
+                MarkerSource::Signal => {
+                    // A counter rather than a flag, so that signals arriving within one poll each mark an event
+                    let count = Arc::new(AtomicUsize::new(0));
+                    let handler_count = count.clone();
+                    // The handler only increments an atomic, which is async-signal-safe
+                    listener.signal = Some(unsafe { signal_hook::low_level::register(signal_hook::consts::SIGUSR1, move || {
+                        handler_count.fetch_add(1, Ordering::SeqCst);
+                    }) }?);
+                    debug!("Listening for event markers on SIGUSR1");
+                    listener.threads.push(thread::spawn(move || {
+                        while running.load(Ordering::Relaxed) {
+                            let now = clock.now_micros();
+                            for _ in 0..count.swap(0, Ordering::SeqCst) {
+                                events.record(Event::new(now, "signal", "SIGUSR1"));
+                            }
+                            thread::sleep(POLL_INTERVAL);
+                        }
+                    }));
+                }
+                MarkerSource::Socket(path) => {
+                    if path.exists() {
+                        fs::remove_file(path)?;
+                    }
+                    let socket = UnixDatagram::bind(path)?;
+                    socket.set_read_timeout(Some(POLL_INTERVAL * 50))?;
+                    listener.socket_path = Some(path.clone());
+                    debug!("Listening for event markers on {}", path.display());
+                    listener.threads.push(thread::spawn(move || {
+                        let mut buf = [0; 4096];
+                        while running.load(Ordering::Relaxed) {
+                            match socket.recv(&mut buf) {
+                                Ok(len) => events.record(Event::new(clock.now_micros(), "socket", String::from_utf8_lossy(&buf[..len]).trim())),
+                                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {}
+                                Err(e) => warn!("Couldn't receive event marker: {}", e),
+                            }
+                        }
+                    }));
+                }
+                MarkerSource::Pipe(path) => {
+                    let mut pipe = open_pipe(path)?;
+                    debug!("Listening for event markers on {}", path.display());
+                    listener.threads.push(thread::spawn(move || {
+                        let mut buf = [0; 4096];
+                        let mut line = Vec::new();
+                        while running.load(Ordering::Relaxed) {
+                            match pipe.read(&mut buf) {
+                                // No writer has the pipe open
+                                Ok(0) => thread::sleep(POLL_INTERVAL),
+                                Ok(len) => {
+                                    let time = clock.now_micros();
+                                    for byte in &buf[..len] {
+                                        if *byte == b'\n' {
+                                            events.record(Event::new(time, "pipe", String::from_utf8_lossy(&line).trim()));
+                                            line.clear();
+                                        } else {
+                                            line.push(*byte);
+                                        }
+                                    }
+                                }
+                                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                                Err(e) => {
+                                    warn!("Couldn't read event marker: {}", e);
+                                    thread::sleep(POLL_INTERVAL);
+                                }
+                            }
+                        }
+                    }));
+                }
+            }
+        }
+        Ok(listener)
+    }
+}
+
+impl Drop for MarkerListener {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+        if let Some(signal) = self.signal.take() {
+            signal_hook::low_level::unregister(signal);
+        }
+        if let Some(path) = self.socket_path.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn open_pipe(path: &Path) -> io::Result<fs::File> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.file_type().is_fifo() => {}
+        Ok(_) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists, but isn't a named pipe", path.display()))),
+        Err(_) => {
+            let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+    OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(path)
+}
+
+/// Sends an event marker to a client listening on the Unix socket at `path`.
+pub fn send_marker(path: &Path, label: &str) -> io::Result<()> {
+    UnixDatagram::unbound()?.send_to(label.as_bytes(), path)?;
+    Ok(())
+}
+
+/// How the probes fared after an event, relative to the event time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventImpact {
+    /// Until the first probe sent after the event that was lost.
+    pub time_to_first_loss: Option<Duration>,
+    /// Until the first probe sent after that loss that was answered again.
+    pub time_to_recovery: Option<Duration>,
+}
+
+impl EventImpact {
+    pub fn of(event: &Event, rtt_times: &RTTTimes) -> Self {
+        let mut impact = EventImpact::default();
+        let since_event = |time: u128| Duration::from_micros((time - event.time) as u64);
+        for packet in rtt_times.rtt_times.iter().filter(|packet| packet.sent_time >= event.time) {
+            if impact.time_to_first_loss.is_none() {
+                if packet.received_time == 0 {
+                    impact.time_to_first_loss = Some(since_event(packet.sent_time));
+                }
+            } else if packet.received_time != 0 {
+                impact.time_to_recovery = Some(since_event(packet.sent_time));
+                break;
+            }
+        }
+        impact
+    }
+}
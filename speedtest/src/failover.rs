@@ -0,0 +1,504 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use log::{debug, error, info, warn};
+use serde_json::json;
+use threadpool::ThreadPool;
+use crate::client::{file_name_part, RESULTS_DIR};
+use crate::config::{ClientConfig, ExperimentTarget};
+use crate::transport::{Network, SystemNetwork, ThreadRole, Transport};
+use crate::udp_application::{UDPApplication, UDPApplicationEnum};
+
+/// Name of the file with one row per transition in the directory of a failover run.
+pub const TRANSITIONS_FILE: &str = "failover_transitions.csv";
+/// Name of the file with one row per probe in the directory of a failover run.
+pub const PROBES_FILE: &str = "failover_probes.csv";
+
+/// Ordered server list and health-check rules of the failover client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailoverConfig {
+    /// Servers in the order of preference, the first one is the primary.
+    pub servers: Vec<ExperimentTarget>,
+    /// Interval between two probes to the active server, the application traffic.
+    pub interval: Duration,
+    /// Interval between two health checks of each of the other servers.
+    pub health_interval: Duration,
+    /// Time after which an unanswered probe counts as lost.
+    pub timeout: Duration,
+    /// Consecutive failed probes after which a server counts as down.
+    pub max_losses: usize,
+    /// Probes answered slower than this count as failed too.
+    pub rtt_threshold: Option<Duration>,
+    /// Whether to switch back to a more preferred server once it recovered.
+    pub failback: bool,
+    /// Consecutive successful health checks after which a server counts as recovered.
+    pub recovery_probes: usize,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        FailoverConfig {
+            servers: Vec::new(),
+            interval: Duration::from_millis(100),
+            health_interval: Duration::from_secs(1),
+            timeout: Duration::from_millis(500),
+            max_losses: 3,
+            rtt_threshold: None,
+            failback: false,
+            recovery_probes: 3,
+        }
+    }
+}
+
+impl FailoverConfig {
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "servers": self.servers.iter().map(|server| json!({ "name": server.name, "address": server.address })).collect::<Vec<_>>(),
+            "interval": self.interval.as_secs_f64(),
+            "health_interval": self.health_interval.as_secs_f64(),
+            "timeout": self.timeout.as_secs_f64() * 1000.0,
+            "max_losses": self.max_losses,
+            "rtt_threshold": self.rtt_threshold.map(|threshold| threshold.as_secs_f64() * 1000.0),
+            "failback": self.failback,
+            "recovery_probes": self.recovery_probes,
+        })
+    }
+}
+
+/// A probe of the failover client, either application traffic to the active server or a health check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailoverProbe {
+    /// Index of the server in [`FailoverConfig::servers`].
+    pub server: usize,
+    pub health_check: bool,
+    pub session_id: u16,
+    pub sent_time: u128,
+    pub received_time: Option<u128>,
+    /// Whether the probe passed the health check, `None` while it is in flight.
+    pub passed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionKind {
+    /// Away from a server that went down.
+    Failover,
+    /// Back to a more preferred server that recovered.
+    Failback,
+}
+
+impl TransitionKind {
+    pub fn name(&self) -> &str {
+        match self {
+            TransitionKind::Failover => "failover",
+            TransitionKind::Failback => "failback",
+        }
+    }
+}
+
+/// A switch of the active server. All times are in microseconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub from: String,
+    pub to: String,
+    /// When the server went down or recovered as far as the probes tell: the sent time of the first probe of
+    /// the streak of failed (failover) or successful (failback) probes that triggered the switch.
+    pub changed_at: u128,
+    /// When the client decided to switch.
+    pub detected_at: u128,
+    /// When the first probe to the new server was answered, `None` if none was.
+    pub restored_at: Option<u128>,
+    /// Probes of the application traffic sent from `changed_at` until `restored_at` that were never
+    /// answered.
+    pub lost: usize,
+}
+
+impl Transition {
+    /// Time from the change of the server until the client noticed it.
+    pub fn detection_time(&self) -> Duration {
+        Duration::from_micros(self.detected_at.saturating_sub(self.changed_at) as u64)
+    }
+
+    /// Time from the decision to switch until the new server answered.
+    pub fn switchover_time(&self) -> Option<Duration> {
+        self.restored_at.map(|restored_at| Duration::from_micros(restored_at.saturating_sub(self.detected_at) as u64))
+    }
+}
+
+/// Consecutive failed or successful probes of a server.
+#[derive(Debug, Clone, Default)]
+struct Health {
+    failures: usize,
+    successes: usize,
+    /// Sent time of the first probe of the current streak.
+    since: u128,
+    /// Sent time of the latest probe with an outcome.
+    latest: u128,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    probes: Vec<FailoverProbe>,
+    /// Latest probe per session ID.
+    index: HashMap<u16, usize>,
+    /// Probes without an outcome, in the order they were sent.
+    in_flight: VecDeque<usize>,
+    health: Vec<Health>,
+    active: usize,
+    transitions: Vec<Transition>,
+    /// Whether a server is down without a healthy one to switch to, to only warn once.
+    stranded: bool,
+}
+
+impl State {
+    fn passed(&mut self, probe: usize, passed: bool) {
+        let (server, sent_time) = (self.probes[probe].server, self.probes[probe].sent_time);
+        self.probes[probe].passed = Some(passed);
+        let health = &mut self.health[server];
+        // A probe that timed out after a later one was answered doesn't break the streak of the later one
+        if sent_time < health.latest {
+            return;
+        }
+        health.latest = sent_time;
+        if passed {
+            if health.successes == 0 {
+                health.since = sent_time;
+            }
+            health.successes += 1;
+            health.failures = 0;
+        } else {
+            if health.failures == 0 {
+                health.since = sent_time;
+            }
+            health.failures += 1;
+            health.successes = 0;
+        }
+    }
+
+    /// Fails the probes in flight that weren't answered within `timeout`.
+    fn expire(&mut self, now: u128, timeout: Duration) {
+        while let Some(&probe) = self.in_flight.front() {
+            if self.probes[probe].passed.is_none() && self.probes[probe].sent_time + timeout.as_micros() > now {
+                break;
+            }
+            self.in_flight.pop_front();
+            if self.probes[probe].passed.is_none() {
+                self.passed(probe, false);
+            }
+        }
+    }
+
+    fn switch(&mut self, kind: TransitionKind, to: usize, changed_at: u128, now: u128, servers: &[ExperimentTarget]) {
+        let from = self.active;
+        match kind {
+            TransitionKind::Failover => warn!("{} is down, failing over to {}", servers[from].name, servers[to].name),
+            TransitionKind::Failback => info!("{} recovered, failing back from {}", servers[to].name, servers[from].name),
+        }
+        self.transitions.push(Transition { kind, from: servers[from].name.clone(), to: servers[to].name.clone(), changed_at, detected_at: now, restored_at: None, lost: 0 });
+        self.active = to;
+        self.stranded = false;
+    }
+
+    /// Fails over if the active server is down, or fails back to the most preferred server that recovered.
+    fn decide(&mut self, now: u128, config: &FailoverConfig) {
+        let active = self.active;
+        if self.health[active].failures >= config.max_losses {
+            // Servers that haven't been checked yet count as healthy
+            match (0..config.servers.len()).find(|&server| server != active && self.health[server].failures == 0) {
+                Some(server) => self.switch(TransitionKind::Failover, server, self.health[active].since, now, &config.servers),
+                None if !self.stranded => {
+                    warn!("{} is down and no other server is healthy", config.servers[active].name);
+                    self.stranded = true;
+                }
+                None => {}
+            }
+        } else if config.failback {
+            if let Some(server) = (0..active).find(|&server| self.health[server].successes >= config.recovery_probes) {
+                self.switch(TransitionKind::Failback, server, self.health[server].since, now, &config.servers);
+            }
+        }
+    }
+
+    fn send(&mut self, server: usize, health_check: bool, session_id: u16, now: u128) {
+        self.index.insert(session_id, self.probes.len());
+        self.in_flight.push_back(self.probes.len());
+        self.probes.push(FailoverProbe { server, health_check, session_id, sent_time: now, received_time: None, passed: None });
+    }
+
+    fn receive(&mut self, session_id: u16, from: SocketAddr, now: u128, addrs: &[SocketAddr], rtt_threshold: Option<Duration>) {
+        let Some(&probe) = self.index.get(&session_id) else {
+            return;
+        };
+        if addrs[self.probes[probe].server] != from || self.probes[probe].received_time.is_some() {
+            return;
+        }
+        self.probes[probe].received_time = Some(now);
+        // Responses after the timeout don't change the outcome
+        if self.probes[probe].passed.is_some() {
+            return;
+        }
+        let rtt = now - self.probes[probe].sent_time;
+        self.passed(probe, rtt_threshold.is_none_or(|threshold| rtt <= threshold.as_micros()));
+        let probe = &self.probes[probe];
+        if let Some(transition) = self.transitions.last_mut() {
+            if !probe.health_check && transition.restored_at.is_none() && probe.sent_time >= transition.detected_at {
+                transition.restored_at = Some(now);
+            }
+        }
+    }
+}
+
+/// Client that probes the first server of an ordered list like an application would, checks the health of
+/// the others, and switches to a backup when the active server goes down, optionally back once the preferred
+/// one recovered.
+///
+/// Application probes and health checks share one socket. A probe fails if it isn't answered within the
+/// timeout or, with an RTT threshold, answered too slowly.
+#[derive(Clone)]
+pub struct FailoverClient {
+    config: FailoverConfig,
+    client_addr: String,
+    payload_size: usize,
+    duration: Duration,
+    network: Arc<dyn Network>,
+}
+
+impl FailoverClient {
+    pub fn new(config: FailoverConfig) -> Self {
+        FailoverClient { config, client_addr: "0.0.0.0:0".to_string(), payload_size: 64, duration: Duration::from_secs(10), network: Arc::new(SystemNetwork) }
+    }
+
+    pub fn client_addr(mut self, client_addr: impl Into<String>) -> Self {
+        self.client_addr = client_addr.into();
+        self
+    }
+
+    pub fn payload_size(mut self, payload_size: usize) -> Self {
+        self.payload_size = payload_size;
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn network(mut self, network: Arc<dyn Network>) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Runs for the configured duration and blocks until the last probe was answered or timed out.
+    pub fn run(&self) -> io::Result<FailoverResult> {
+        let config = self.config.clone();
+        if config.servers.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no servers to fail over between"));
+        }
+        let addrs = config
+            .servers
+            .iter()
+            .map(|server| {
+                server.address.to_socket_addrs()?.next().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Couldn't resolve {}", server.address)))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let socket: Arc<dyn Transport> = Arc::from(self.network.bind(&self.client_addr)?);
+        socket.set_read_timeout(Some(config.timeout))?;
+        let state = Arc::new(Mutex::new(State { health: vec![Health::default(); config.servers.len()], ..State::default() }));
+        let clock = self.network.clock();
+        info!("Starting failover client with {} as primary", config.servers[0].name);
+
+        // Keep the clock from advancing until both threads are started
+        let setup = clock.register_thread(ThreadRole::Foreground).enter();
+        let pool = ThreadPool::new(2);
+        let start_time = clock.now_micros();
+        let duration = self.duration;
+
+        let receiver = (Arc::clone(&socket), Arc::clone(&state), Arc::clone(&clock), addrs.clone(), config.clone());
+        let token = clock.register_thread(ThreadRole::Foreground);
+        pool.execute(move || {
+            let _guard = token.enter();
+            let (socket, state, clock, addrs, config) = receiver;
+            let mut buf = [0; 131072];
+            while clock.elapsed_since(start_time) < duration + config.timeout {
+                match socket.recv_from(&mut buf) {
+                    Ok((len, from)) => {
+                        let now = clock.now_micros();
+                        let response = UDPApplication::new(&buf[..len]);
+                        state.lock().unwrap().receive(response.session_id, from, now, &addrs, config.rtt_threshold);
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {}
+                    Err(e) => error!("Error receiving packet: {:?}", e),
+                }
+            }
+        });
+
+        let payload = vec![0u8; self.payload_size];
+        let sender = (Arc::clone(&state), Arc::clone(&clock), config.clone());
+        let token = clock.register_thread(ThreadRole::Foreground);
+        pool.execute(move || {
+            let _guard = token.enter();
+            let (state, clock, config) = sender;
+            let mut session_id: u16 = 0;
+            let mut next_send_offset = Duration::ZERO;
+            let mut next_health_check = Duration::ZERO;
+            while clock.elapsed_since(start_time) < duration {
+                let now = clock.now_micros();
+                // Decide and record under the lock, but send without it, as a send may block in a simulation
+                let mut packets = Vec::new();
+                {
+                    let mut state = state.lock().unwrap();
+                    state.expire(now, config.timeout);
+                    state.decide(now, &config);
+                    let mut targets = vec![(state.active, false)];
+                    if clock.elapsed_since(start_time) >= next_health_check {
+                        targets.extend((0..config.servers.len()).filter(|&server| server != state.active).map(|server| (server, true)));
+                        next_health_check += config.health_interval;
+                    }
+                    for (server, health_check) in targets {
+                        state.send(server, health_check, session_id, now);
+                        packets.push((server, UDPApplication { type_field: UDPApplicationEnum::REQUEST as u16, session_id, test_payload: payload.clone() }));
+                        session_id = session_id.wrapping_add(1);
+                    }
+                }
+                for (server, packet) in packets {
+                    debug!("Sending request to {}: {}", config.servers[server].name, packet.summary());
+                    if let Err(e) = socket.send_to(&packet.to_bytes(), addrs[server]) {
+                        error!("Couldn't send to {}: {}", config.servers[server].name, e);
+                    }
+                }
+
+                next_send_offset += config.interval;
+                let elapsed = clock.elapsed_since(start_time);
+                if next_send_offset > elapsed {
+                    clock.sleep(next_send_offset - elapsed);
+                }
+            }
+        });
+
+        drop(setup);
+        pool.join();
+        let end_time = clock.now_micros();
+
+        let mut state = Arc::try_unwrap(state).map_err(|_| io::Error::other("failover threads still running"))?.into_inner().unwrap();
+        state.expire(u128::MAX, Duration::ZERO);
+        let mut transitions = state.transitions;
+        for transition in &mut transitions {
+            let until = transition.restored_at.unwrap_or(end_time);
+            transition.lost = state
+                .probes
+                .iter()
+                .filter(|probe| !probe.health_check && probe.received_time.is_none() && probe.sent_time >= transition.changed_at && probe.sent_time < until)
+                .count();
+        }
+        Ok(FailoverResult {
+            servers: config.servers,
+            started_at: SystemTime::UNIX_EPOCH + Duration::from_micros(start_time as u64),
+            elapsed: Duration::from_micros((end_time - start_time) as u64),
+            probes: state.probes,
+            transitions,
+        })
+    }
+}
+
+/// Outcome of a [`FailoverClient`] run.
+#[derive(Debug, Clone)]
+pub struct FailoverResult {
+    pub servers: Vec<ExperimentTarget>,
+    pub started_at: SystemTime,
+    pub elapsed: Duration,
+    /// All probes in the order they were sent.
+    pub probes: Vec<FailoverProbe>,
+    pub transitions: Vec<Transition>,
+}
+
+impl FailoverResult {
+    /// Number of sent and of never answered probes of the application traffic.
+    pub fn traffic_loss(&self) -> (usize, usize) {
+        let traffic = self.probes.iter().filter(|probe| !probe.health_check);
+        let sent = traffic.clone().count();
+        (sent, traffic.filter(|probe| probe.received_time.is_none()).count())
+    }
+
+    /// Logs every transition and the loss of the application traffic.
+    pub fn evaluate(&self) {
+        for transition in &self.transitions {
+            info!(
+                "{} from {} to {}: detection {:.3} ms, switchover {}, {} packets lost",
+                transition.kind.name(),
+                transition.from,
+                transition.to,
+                transition.detection_time().as_secs_f64() * 1000.0,
+                transition.switchover_time().map_or("never completed".to_string(), |time| format!("{:.3} ms", time.as_secs_f64() * 1000.0)),
+                transition.lost
+            );
+        }
+        let (sent, lost) = self.traffic_loss();
+        info!("{} transitions, {} of {} packets lost", self.transitions.len(), lost, sent);
+    }
+
+    /// Writes [`TRANSITIONS_FILE`] and [`PROBES_FILE`] to `dir`, times in microseconds.
+    pub fn write_to_dir(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let micros = |value: Option<u128>| value.map(|value| value.to_string()).unwrap_or_default();
+        let mut wtr = csv::Writer::from_path(dir.join(TRANSITIONS_FILE))?;
+        wtr.write_record(["Kind", "From", "To", "Changed At", "Detected At", "Restored At", "Detection Time", "Switchover Time", "Lost Packets"])?;
+        for transition in &self.transitions {
+            wtr.write_record([
+                transition.kind.name().to_string(),
+                transition.from.clone(),
+                transition.to.clone(),
+                transition.changed_at.to_string(),
+                transition.detected_at.to_string(),
+                micros(transition.restored_at),
+                transition.detection_time().as_micros().to_string(),
+                micros(transition.switchover_time().map(|time| time.as_micros())),
+                transition.lost.to_string(),
+            ])?;
+        }
+        wtr.flush()?;
+
+        let mut wtr = csv::Writer::from_path(dir.join(PROBES_FILE))?;
+        wtr.write_record(["Session ID", "Server Name", "Server Address", "Kind", "Sent Time", "Received Time", "RTT", "Passed"])?;
+        for probe in &self.probes {
+            let server = &self.servers[probe.server];
+            wtr.write_record([
+                probe.session_id.to_string(),
+                server.name.clone(),
+                server.address.clone(),
+                if probe.health_check { "health" } else { "traffic" }.to_string(),
+                probe.sent_time.to_string(),
+                micros(probe.received_time),
+                micros(probe.received_time.map(|received_time| received_time - probe.sent_time)),
+                probe.passed.unwrap_or_default().to_string(),
+            ])?;
+        }
+        wtr.flush()
+    }
+}
+
+/// Runs the failover client over `failover.servers` for `speedtest_duration` with the configured payload size
+/// and writes the results to `results/failover_<primary>_<start ms>/`.
+///
+/// Returns that directory.
+pub fn run(config: &ClientConfig) -> io::Result<PathBuf> {
+    let failover = &config.failover;
+    let started = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+    let primary = failover.servers.first().map_or(String::new(), |server| file_name_part(&server.name));
+    let dir = Path::new(RESULTS_DIR).join(format!("failover_{}_{}", primary, started));
+    let client = FailoverClient::new(failover.clone()).client_addr(config.client_addr.as_str()).payload_size(config.payload_size).duration(config.speedtest_duration);
+    let result = match client.run() {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Failover run failed: {}", e);
+            return Err(e);
+        }
+    };
+    result.evaluate();
+    result.write_to_dir(&dir)?;
+    info!("Wrote results to {}", dir.display());
+    Ok(dir)
+}
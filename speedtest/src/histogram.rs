@@ -0,0 +1,274 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use crate::stats;
+
+const LOG_HEADER: &str = "#udpbenchmark histogram v1";
+
+/// Latency histogram in the style of HdrHistogram, recording values in constant memory.
+///
+/// Values are kept in buckets that are exact up to `precision` significant decimal digits, i.e. with a
+/// precision of 3 percentiles are within 0.1% of the recorded values. Count, minimum, maximum, mean and
+/// standard deviation are tracked exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyHistogram {
+    precision: u8,
+    /// Number of linear sub-buckets per power of two, as a power of two itself.
+    sub_bucket_bits: u32,
+    counts: Vec<u64>,
+    total: u64,
+    min: u64,
+    max: u64,
+    sum: u128,
+    sum_squares: f64,
+}
+
+impl LatencyHistogram {
+    /// Creates a histogram with `precision` significant decimal digits, clamped to 1..=5.
+    pub fn new(precision: u8) -> Self {
+        let precision = precision.clamp(1, 5);
+        let largest_exact = 2 * 10u64.pow(precision as u32);
+        let sub_bucket_bits = 64 - (largest_exact - 1).leading_zeros();
+        LatencyHistogram {
+            precision,
+            sub_bucket_bits,
+            counts: Vec::new(),
+            total: 0,
+            min: u64::MAX,
+            max: 0,
+            sum: 0,
+            sum_squares: 0.0,
+        }
+    }
+
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    fn sub_bucket_count(&self) -> u64 {
+        1 << self.sub_bucket_bits
+    }
+
+    fn index_of(&self, value: u64) -> usize {
+        let sub_bucket_count = self.sub_bucket_count();
+        if value < sub_bucket_count {
+            return value as usize;
+        }
+        let half = sub_bucket_count / 2;
+        let bucket = (63 - value.leading_zeros()) - self.sub_bucket_bits + 1;
+        let sub_bucket = value >> bucket;
+        (sub_bucket_count + (bucket as u64 - 1) * half + (sub_bucket - half)) as usize
+    }
+
+    /// Lowest and highest value that are recorded into the bucket at `index`.
+    fn range_of(&self, index: usize) -> (u64, u64) {
+        let sub_bucket_count = self.sub_bucket_count();
+        let index = index as u64;
+        if index < sub_bucket_count {
+            return (index, index);
+        }
+        let half = sub_bucket_count / 2;
+        let bucket = (index - sub_bucket_count) / half + 1;
+        let sub_bucket = (index - sub_bucket_count) % half + half;
+        let lowest = sub_bucket << bucket;
+        (lowest, lowest + ((1 << bucket) - 1))
+    }
+
+    pub fn record(&mut self, value: u64) {
+        self.record_n(value, 1);
+    }
+
+    /// Records `value` `count` times.
+    pub fn record_n(&mut self, value: u64, count: u64) {
+        if count == 0 {
+            return;
+        }
+        let index = self.index_of(value);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += count;
+        self.total += count;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value as u128 * count as u128;
+        self.sum_squares += (value as f64).powi(2) * count as f64;
+    }
+
+    /// Adds all values recorded in `other`. Histograms of a different precision are re-bucketed, which
+    /// loses the precision of the coarser one.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        if other.total == 0 {
+            return;
+        }
+        if other.precision == self.precision {
+            if other.counts.len() > self.counts.len() {
+                self.counts.resize(other.counts.len(), 0);
+            }
+            for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+                *count += other_count;
+            }
+        } else {
+            for (index, count) in other.counts.iter().enumerate().filter(|(_, count)| **count > 0) {
+                let (lowest, highest) = other.range_of(index);
+                let index = self.index_of(lowest + (highest - lowest) / 2);
+                if index >= self.counts.len() {
+                    self.counts.resize(index + 1, 0);
+                }
+                self.counts[index] += count;
+            }
+        }
+        self.total += other.total;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.sum_squares += other.sum_squares;
+    }
+
+    pub fn len(&self) -> u64 {
+        self.total
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        (self.total > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        (self.total > 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.sum as f64 / self.total as f64)
+    }
+
+    /// Population variance of the recorded values.
+    pub fn variance(&self) -> Option<f64> {
+        let mean = self.mean()?;
+        Some((self.sum_squares / self.total as f64 - mean * mean).max(0.0))
+    }
+
+    pub fn stddev(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+
+    /// Smallest recorded value that `percentile` percent of all values are less than or equal to, within
+    /// the precision of the histogram.
+    pub fn value_at_percentile(&self, percentile: f64) -> Option<u64> {
+        if self.total == 0 {
+            return None;
+        }
+        let rank = stats::rank(percentile, self.total);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let (_, highest) = self.range_of(index);
+                return Some(highest.clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+
+    /// Non-empty buckets as (lowest value, count), in ascending order.
+    pub fn buckets(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.counts.iter().enumerate().filter(|(_, count)| **count > 0).map(move |(index, count)| (self.range_of(index).0, *count))
+    }
+
+    /// Writes the histogram as a text log with `tags` as extra `#key,value` header lines.
+    pub fn write_log(&self, path: &Path, tags: &[(&str, &str)]) -> io::Result<()> {
+        let mut out = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(out, "{}", LOG_HEADER)?;
+        for (key, value) in tags {
+            writeln!(out, "#{},{}", key, value)?;
+        }
+        writeln!(out, "#precision,{}", self.precision)?;
+        writeln!(out, "#min,{}", self.min)?;
+        writeln!(out, "#max,{}", self.max)?;
+        writeln!(out, "#sum,{}", self.sum)?;
+        writeln!(out, "#sum_squares,{}", self.sum_squares)?;
+        writeln!(out, "value,count")?;
+        for (value, count) in self.buckets() {
+            writeln!(out, "{},{}", value, count)?;
+        }
+        out.flush()
+    }
+
+    /// Reads a log written by [`LatencyHistogram::write_log`], returning the histogram and its tags.
+    pub fn read_log(path: &Path) -> Result<(Self, Vec<(String, String)>), HistogramLogError> {
+        let reader = BufReader::new(fs::File::open(path)?);
+        let mut lines = reader.lines();
+        if lines.next().transpose()?.as_deref() != Some(LOG_HEADER) {
+            return Err(HistogramLogError::Format("missing histogram log header".to_string()));
+        }
+
+        let mut histogram: Option<LatencyHistogram> = None;
+        let mut tags = Vec::new();
+        let (mut min, mut max, mut sum, mut sum_squares) = (u64::MAX, 0, 0, 0.0);
+        for line in lines {
+            let line = line?;
+            let (key, value) = line.split_once(',').ok_or_else(|| HistogramLogError::Format(format!("invalid line `{}`", line)))?;
+            let invalid = |_| HistogramLogError::Format(format!("invalid line `{}`", line));
+            match key {
+                "#precision" => histogram = Some(LatencyHistogram::new(value.parse().map_err(invalid)?)),
+                "#min" => min = value.parse().map_err(invalid)?,
+                "#max" => max = value.parse().map_err(invalid)?,
+                "#sum" => sum = value.parse().map_err(invalid)?,
+                "#sum_squares" => sum_squares = value.parse().map_err(|_| HistogramLogError::Format(format!("invalid line `{}`", line)))?,
+                "value" => {}
+                key if key.starts_with('#') => tags.push((key[1..].to_string(), value.to_string())),
+                _ => {
+                    let histogram = histogram.as_mut().ok_or_else(|| HistogramLogError::Format("missing precision".to_string()))?;
+                    let bucket_value: u64 = key.parse().map_err(invalid)?;
+                    let count: u64 = value.parse().map_err(invalid)?;
+                    let index = histogram.index_of(bucket_value);
+                    if index >= histogram.counts.len() {
+                        histogram.counts.resize(index + 1, 0);
+                    }
+                    histogram.counts[index] += count;
+                    histogram.total += count;
+                }
+            }
+        }
+
+        let mut histogram = histogram.ok_or_else(|| HistogramLogError::Format("missing precision".to_string()))?;
+        histogram.min = min;
+        histogram.max = max;
+        histogram.sum = sum;
+        histogram.sum_squares = sum_squares;
+        Ok((histogram, tags))
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram::new(3)
+    }
+}
+
+#[derive(Debug)]
+pub enum HistogramLogError {
+    Io(io::Error),
+    Format(String),
+}
+
+impl fmt::Display for HistogramLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistogramLogError::Io(e) => write!(f, "couldn't read histogram log: {}", e),
+            HistogramLogError::Format(e) => write!(f, "invalid histogram log: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HistogramLogError {}
+
+impl From<io::Error> for HistogramLogError {
+    fn from(e: io::Error) -> Self {
+        HistogramLogError::Io(e)
+    }
+}
@@ -0,0 +1,128 @@
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use log::{info, warn};
+use crate::events::{Event, EventLog};
+use crate::transport::{Clock, ThreadRole};
+
+/// Longest a scheduled hook sleeps at once, so it notices when the test is over.
+const WAIT_STEP: Duration = Duration::from_millis(10);
+
+/// When a hook runs, relative to a test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookStage {
+    /// Before the test starts, the test waits for it.
+    Before,
+    /// At the given offset into the test, while the test keeps running.
+    During(Duration),
+    /// After the test has finished.
+    After,
+}
+
+impl HookStage {
+    pub fn name(&self) -> &str {
+        match self {
+            HookStage::Before => "before",
+            HookStage::During(_) => "during",
+            HookStage::After => "after",
+        }
+    }
+}
+
+/// Shell command run around a test, e.g. to trigger a rule update or to clean up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hook {
+    pub stage: HookStage,
+    pub command: String,
+}
+
+impl Hook {
+    pub fn new(stage: HookStage, command: impl Into<String>) -> Self {
+        Hook { stage, command: command.into() }
+    }
+}
+
+/// How a hook command finished.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookOutcome {
+    /// Microseconds since the Unix epoch, on the same clock as the start time of the event.
+    pub finished: u128,
+    /// Exit code, `None` if the command couldn't be started or was killed by a signal.
+    pub status: Option<i32>,
+    /// Standard output followed by standard error, or why the command couldn't be started.
+    pub output: String,
+}
+
+/// Runs `hook` with `sh -c` and returns it as an event. `env` is passed to the command as environment
+/// variables.
+pub fn run_hook(hook: &Hook, clock: &dyn Clock, env: &[(String, String)]) -> Event {
+    let started = clock.now_micros();
+    let output = Command::new("sh").arg("-c").arg(&hook.command).envs(env.iter().cloned()).env("UDPBENCHMARK_HOOK_STAGE", hook.stage.name()).output();
+    let finished = clock.now_micros();
+    let outcome = match output {
+        Ok(output) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            HookOutcome { finished, status: output.status.code(), output: text.trim().to_string() }
+        }
+        Err(e) => HookOutcome { finished, status: None, output: e.to_string() },
+    };
+    match outcome.status {
+        Some(0) => info!("Hook `{}` ({}) finished after {:.3} s", hook.command, hook.stage.name(), (finished - started) as f64 / 1e6),
+        status => warn!("Hook `{}` ({}) failed with status {:?}: {}", hook.command, hook.stage.name(), status, outcome.output),
+    }
+    let mut event = Event::new(started, format!("hook:{}", hook.stage.name()), hook.command.clone());
+    event.hook = Some(outcome);
+    event
+}
+
+/// Runs the `During` hooks of a test at their offsets from the test start. Hooks that are not due when
+/// this is dropped are skipped, running ones are waited for.
+pub struct ScheduledHooks {
+    running: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl ScheduledHooks {
+    pub fn start(hooks: &[Hook], start_time: u128, events: &EventLog, clock: Arc<dyn Clock>, env: &[(String, String)]) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let mut threads = Vec::new();
+        for hook in hooks {
+            let offset = match hook.stage {
+                HookStage::During(offset) => offset,
+                _ => continue,
+            };
+            let (hook, running, events, clock) = (hook.clone(), running.clone(), events.clone(), clock.clone());
+            let env = env.to_vec();
+            let token = clock.register_thread(ThreadRole::Background);
+            threads.push(thread::spawn(move || {
+                let _guard = token.enter();
+                let due = start_time + offset.as_micros();
+                loop {
+                    let now = clock.now_micros();
+                    if now >= due {
+                        break;
+                    }
+                    if !running.load(Ordering::Relaxed) {
+                        warn!("Test ended before hook `{}` was due", hook.command);
+                        return;
+                    }
+                    clock.sleep(WAIT_STEP.min(Duration::from_micros((due - now) as u64)));
+                }
+                events.record(run_hook(&hook, clock.as_ref(), &env));
+            }));
+        }
+        ScheduledHooks { running, threads }
+    }
+}
+
+impl Drop for ScheduledHooks {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
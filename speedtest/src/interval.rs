@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use log::info;
+use crate::jitter::update_interarrival_jitter;
+use crate::stats::RttSummary;
+use crate::timeline::{PacketRecord, PacketStatus, ProbeTracker, TimelineEntry};
+
+/// Traffic and RTTs of one reporting interval of a test, like an iperf3 `-i` line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntervalReport {
+    /// Offset of the interval start from the test start.
+    pub start: Duration,
+    /// Offset of the interval end from the test start; the last interval may be shorter.
+    pub end: Duration,
+    pub packets_sent: u64,
+    pub bytes_sent: u64,
+    /// Responses received, including duplicates.
+    pub packets_received: u64,
+    pub bytes_received: u64,
+    /// Probes sent in the interval that were answered within the receive timeout.
+    pub answered: u64,
+    /// Probes sent in the interval that counted as lost, i.e. were unanswered for the receive timeout.
+    pub lost: u64,
+    /// RTTs of the answered probes with their 99th percentile, `None` if no probe was answered.
+    pub rtt: Option<RttSummary>,
+    /// RFC 3550 interarrival jitter of the RTTs after the last answer so far, like iperf3 reports it.
+    pub jitter: Option<f64>,
+}
+
+impl IntervalReport {
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+
+    /// Sent throughput in bits per second.
+    pub fn throughput_sent(&self) -> f64 {
+        self.bits_per_second(self.bytes_sent)
+    }
+
+    /// Received throughput in bits per second.
+    pub fn throughput_received(&self) -> f64 {
+        self.bits_per_second(self.bytes_received)
+    }
+
+    fn bits_per_second(&self, bytes: u64) -> f64 {
+        match self.duration().as_secs_f64() {
+            seconds if seconds > 0.0 => bytes as f64 * 8.0 / seconds,
+            _ => 0.0,
+        }
+    }
+
+    /// Share of the probes sent in the interval that were lost.
+    pub fn loss_rate(&self) -> Option<f64> {
+        match self.lost + self.answered {
+            0 => None,
+            total => Some(self.lost as f64 / total as f64),
+        }
+    }
+
+    /// 99th percentile of the RTTs in microseconds.
+    pub fn rtt_99th_percentile(&self) -> Option<u64> {
+        self.rtt.as_ref().and_then(|rtt| rtt.percentiles.first()).map(|(_, value)| *value)
+    }
+}
+
+/// Reports the traffic of a running test in fixed intervals from its start, to the log and as
+/// [`IntervalReport`]s.
+///
+/// Answers and losses count in the interval in which their probe was sent, so an interval is reported
+/// once a timestamp after its end comes in and each of its probes is answered or has timed out. The
+/// reports thus follow the test by up to the receive timeout. Responses count as received, and late
+/// answers only as received, in the interval in which they arrive.
+pub struct IntervalReporter {
+    sender: Sender<TimelineEntry>,
+    thread: JoinHandle<Intervals>,
+}
+
+impl IntervalReporter {
+    /// Starts reporting every `interval` from `start_time`, counting `packet_size` bytes per packet.
+    /// `label` prefixes every log line, e.g. the server address.
+    pub fn start(label: &str, interval: Duration, start_time: u128, packet_size: usize, loss_timeout: Duration) -> Self {
+        let intervals = Intervals {
+            label: label.to_string(),
+            interval: interval.as_micros().max(1),
+            start_time,
+            packet_size: packet_size as u64,
+            tracker: ProbeTracker::new(loss_timeout),
+            open: VecDeque::new(),
+            first_start: start_time,
+            jitter: None,
+            last_rtt: None,
+            reports: Vec::new(),
+        };
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || intervals.run(receiver));
+        IntervalReporter { sender, thread }
+    }
+
+    /// Sender for the packet timestamps.
+    pub fn sender(&self) -> Sender<TimelineEntry> {
+        self.sender.clone()
+    }
+
+    /// Reports the last interval up to `end_time` and returns all reports.
+    ///
+    /// Blocks until every other sender of the reporter is dropped.
+    pub fn finish(self, end_time: u128) -> Vec<IntervalReport> {
+        drop(self.sender);
+        let mut intervals = match self.thread.join() {
+            Ok(intervals) => intervals,
+            Err(_) => return Vec::new(),
+        };
+        // The probes still unanswered count as lost where they were sent
+        for record in intervals.tracker.finish() {
+            intervals.count(&record);
+        }
+        // The last interval ends with the test, even if that is exactly on an interval boundary, and takes
+        // the responses that arrived after it
+        while end_time > intervals.first_start + intervals.interval {
+            intervals.close(intervals.first_start + intervals.interval);
+        }
+        let last = intervals.open.drain(..).fold(Counters::default(), Counters::add);
+        if end_time > intervals.first_start || !last.is_empty() {
+            intervals.open.push_back(last);
+            intervals.close(end_time.max(intervals.first_start));
+        }
+        intervals.reports
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Counters {
+    packets_sent: u64,
+    packets_received: u64,
+    answered: u64,
+    lost: u64,
+    rtts: Vec<u128>,
+    /// Probes sent in the interval that are neither answered nor lost yet.
+    pending: u64,
+}
+
+impl Counters {
+    fn add(mut self, other: Counters) -> Self {
+        self.packets_sent += other.packets_sent;
+        self.packets_received += other.packets_received;
+        self.answered += other.answered;
+        self.lost += other.lost;
+        self.rtts.extend(other.rtts);
+        self.pending += other.pending;
+        self
+    }
+
+    fn is_empty(&self) -> bool {
+        self.packets_sent == 0 && self.packets_received == 0 && self.lost == 0
+    }
+}
+
+struct Intervals {
+    label: String,
+    interval: u128,
+    start_time: u128,
+    packet_size: u64,
+    tracker: ProbeTracker,
+    /// Intervals that aren't reported yet, the first one starting at `first_start`.
+    open: VecDeque<Counters>,
+    first_start: u128,
+    jitter: Option<f64>,
+    last_rtt: Option<u128>,
+    reports: Vec<IntervalReport>,
+}
+
+impl Intervals {
+    fn run(mut self, receiver: Receiver<TimelineEntry>) -> Self {
+        for entry in receiver {
+            let time = entry.time();
+            if let TimelineEntry::Sent { .. } = entry {
+                let counters = self.at(time);
+                counters.packets_sent += 1;
+                counters.pending += 1;
+            }
+            for record in self.tracker.track(&entry) {
+                self.count(&record);
+            }
+            self.advance(time);
+        }
+        self
+    }
+
+    /// Counters of the interval that contains `time`.
+    fn at(&mut self, time: u128) -> &mut Counters {
+        let index = (time.saturating_sub(self.first_start) / self.interval) as usize;
+        if index >= self.open.len() {
+            self.open.resize_with(index + 1, Counters::default);
+        }
+        &mut self.open[index]
+    }
+
+    fn count(&mut self, record: &PacketRecord) {
+        if let Some(rtt) = record.rtt() {
+            if let Some(last_rtt) = self.last_rtt {
+                self.jitter = Some(update_interarrival_jitter(self.jitter, last_rtt, rtt));
+            }
+            self.last_rtt = Some(rtt);
+        }
+        if let Some(received) = record.received {
+            self.at(received).packets_received += 1;
+        }
+        // A late answer's probe already counted as lost
+        let sent = match (record.status, record.sent) {
+            (PacketStatus::Ok | PacketStatus::Reordered | PacketStatus::Lost, Some(sent)) => sent,
+            _ => return,
+        };
+        let rtt = record.rtt();
+        let counters = self.at(sent);
+        counters.pending = counters.pending.saturating_sub(1);
+        match rtt {
+            Some(rtt) => {
+                counters.answered += 1;
+                counters.rtts.push(rtt);
+            }
+            None => counters.lost += 1,
+        }
+    }
+
+    /// Reports the intervals that ended before `time` and whose probes are all answered or lost.
+    fn advance(&mut self, time: u128) {
+        while time >= self.first_start + self.interval && self.open.front().is_none_or(|counters| counters.pending == 0) {
+            self.close(self.first_start + self.interval);
+        }
+    }
+
+    fn close(&mut self, end: u128) {
+        let counters = self.open.pop_front().unwrap_or_default();
+        let report = IntervalReport {
+            start: Duration::from_micros((self.first_start - self.start_time) as u64),
+            end: Duration::from_micros((end - self.start_time) as u64),
+            packets_sent: counters.packets_sent,
+            bytes_sent: counters.packets_sent * self.packet_size,
+            packets_received: counters.packets_received,
+            bytes_received: counters.packets_received * self.packet_size,
+            answered: counters.answered,
+            lost: counters.lost,
+            rtt: RttSummary::from_samples(&counters.rtts, &[99.0]),
+            jitter: self.jitter,
+        };
+        self.first_start = end;
+        let ms = |value: Option<f64>| value.map_or("-".to_string(), |value| format!("{:.3}", value / 1000.0));
+        info!(
+            "[{}] {:6.2}-{:<6.2} sec  sent {} packets {:.2} Mbits/sec  received {} packets {:.2} Mbits/sec  lost {}/{} ({:.2}%)  jitter {} ms  RTT min/avg/max/p99 {}/{}/{}/{} ms",
+            self.label,
+            report.start.as_secs_f64(),
+            report.end.as_secs_f64(),
+            report.packets_sent,
+            report.throughput_sent() / 1e6,
+            report.packets_received,
+            report.throughput_received() / 1e6,
+            report.lost,
+            report.lost + report.answered,
+            report.loss_rate().unwrap_or_default() * 100.0,
+            ms(report.jitter),
+            ms(report.rtt.as_ref().map(|rtt| rtt.min as f64)),
+            ms(report.rtt.as_ref().map(|rtt| rtt.mean)),
+            ms(report.rtt.as_ref().map(|rtt| rtt.max as f64)),
+            ms(report.rtt_99th_percentile().map(|value| value as f64))
+        );
+        self.reports.push(report);
+    }
+}
@@ -0,0 +1,208 @@
+use std::time::{Duration, SystemTime};
+use serde_json::{json, Map, Value};
+use crate::client::{SpeedtestEnum, TestResult};
+use crate::interval::IntervalReport;
+use crate::stats::RttSummary;
+
+/// Socket number of the single stream, iperf3 uses the file descriptor.
+const SOCKET: u64 = 1;
+
+/// Converts a test result into the structure of `iperf3 -u -J`: `start`, `intervals` and `end`.
+///
+/// The iperf3 fields describe the sent stream like an iperf3 client does, jitter and loss are taken from
+/// the responses. Fields prefixed with `rtt_`, the `rtt` object in `end` and the server name in
+/// `connecting_to` have no iperf3 counterpart. Without a report interval, `intervals` has a single interval
+/// covering the whole test, like iperf3 with `-i 0`.
+pub fn to_json(result: &TestResult) -> Value {
+    let intervals = match result.intervals.is_empty() {
+        true => vec![interval(&whole_test(result))],
+        false => result.intervals.iter().map(interval).collect(),
+    };
+    json!({
+        "start": start(result),
+        "intervals": intervals,
+        "end": end(result),
+    })
+}
+
+/// The whole test as one interval.
+fn whole_test(result: &TestResult) -> IntervalReport {
+    let delivery = &result.delivery;
+    IntervalReport {
+        start: Duration::ZERO,
+        end: result.send_duration(),
+        packets_sent: delivery.sent,
+        bytes_sent: result.bytes_sent as u64,
+        packets_received: delivery.delivered + delivery.duplicates + delivery.unsolicited,
+        bytes_received: result.bytes_received as u64,
+        answered: delivery.delivered,
+        lost: delivery.lost,
+        rtt: RttSummary::from_histogram(&result.histogram, &[99.0]),
+        jitter: result.jitter.interarrival_jitter,
+    }
+}
+
+fn start(result: &TestResult) -> Value {
+    let spec = &result.spec;
+    let (remote_host, remote_port) = split_addr(&spec.server_addr);
+    let (local_host, local_port) = split_addr(&spec.client_addr);
+    let timesecs = result.started_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs();
+    json!({
+        "connected": [{
+            "socket": SOCKET,
+            "local_host": local_host,
+            "local_port": local_port,
+            "remote_host": remote_host,
+            "remote_port": remote_port,
+        }],
+        "version": format!("udpbenchmark {}", env!("CARGO_PKG_VERSION")),
+        "system_info": format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+        "timestamp": {
+            "time": http_date(timesecs),
+            "timesecs": timesecs,
+        },
+        "connecting_to": {
+            "host": remote_host,
+            "port": remote_port,
+            "name": spec.server_name(),
+        },
+        "cookie": "",
+        "test_start": {
+            "protocol": "UDP",
+            "num_streams": 1,
+            "blksize": spec.payload_size + 4,
+            "omit": 0,
+            "duration": if spec.mode == SpeedtestEnum::ByPacketCount { 0.0 } else { spec.duration.as_secs_f64() },
+            "bytes": 0,
+            "blocks": if spec.mode == SpeedtestEnum::ByPacketCount { spec.packet_count } else { 0 },
+            "reverse": 0,
+            "tos": 0,
+            "target_bitrate": if spec.mode == SpeedtestEnum::ByDurationCustomBitrate { spec.bitrate * spec.bitrate_scale as i64 } else { 0 },
+            "mode": spec.mode.to_string(),
+        },
+    })
+}
+
+fn interval(report: &IntervalReport) -> Value {
+    let mut stream = Map::new();
+    stream.insert("socket".to_string(), json!(SOCKET));
+    let sum = interval_sum(report);
+    if let Value::Object(sum) = &sum {
+        stream.extend(sum.clone());
+    }
+    json!({ "streams": [stream], "sum": sum })
+}
+
+fn interval_sum(report: &IntervalReport) -> Value {
+    let rtt = report.rtt.as_ref();
+    json!({
+        "start": report.start.as_secs_f64(),
+        "end": report.end.as_secs_f64(),
+        "seconds": report.duration().as_secs_f64(),
+        "bytes": report.bytes_sent,
+        "bits_per_second": report.throughput_sent(),
+        "jitter_ms": ms(report.jitter.unwrap_or_default()),
+        "lost_packets": report.lost,
+        "packets": report.lost + report.answered,
+        "lost_percent": report.loss_rate().unwrap_or_default() * 100.0,
+        "omitted": false,
+        "sender": true,
+        "rtt_bytes_received": report.bytes_received,
+        "rtt_bits_per_second_received": report.throughput_received(),
+        "rtt_min_ms": rtt.map(|rtt| ms(rtt.min as f64)),
+        "rtt_mean_ms": rtt.map(|rtt| ms(rtt.mean)),
+        "rtt_max_ms": rtt.map(|rtt| ms(rtt.max as f64)),
+        "rtt_p99_ms": report.rtt_99th_percentile().map(|value| ms(value as f64)),
+    })
+}
+
+fn end(result: &TestResult) -> Value {
+    let seconds = result.send_duration().as_secs_f64();
+    let delivery = &result.delivery;
+    let jitter_ms = ms(result.jitter.interarrival_jitter.unwrap_or_default());
+    let lost_percent = delivery.loss_rate().unwrap_or_default() * 100.0;
+    let sum = |bytes: usize, bits_per_second: f64, sender: bool| {
+        json!({
+            "start": 0.0,
+            "end": seconds,
+            "seconds": seconds,
+            "bytes": bytes,
+            "bits_per_second": bits_per_second,
+            "jitter_ms": jitter_ms,
+            "lost_packets": delivery.lost,
+            "packets": delivery.sent,
+            "lost_percent": lost_percent,
+            "sender": sender,
+        })
+    };
+    let mut udp = Map::new();
+    udp.insert("socket".to_string(), json!(SOCKET));
+    if let Value::Object(sent) = sum(result.bytes_sent, result.throughput_sent(), true) {
+        udp.extend(sent);
+    }
+    udp.insert("out_of_order".to_string(), json!(delivery.reordered));
+    let summary = result.summary();
+    let percentiles = summary
+        .as_ref()
+        .map(|summary| summary.percentiles.iter().map(|(percentile, value)| (format!("p{}", percentile), json!(ms(*value as f64)))).collect::<Map<_, _>>())
+        .unwrap_or_default();
+    json!({
+        "streams": [{ "udp": udp }],
+        "sum": sum(result.bytes_sent, result.throughput_sent(), true),
+        "sum_sent": sum(result.bytes_sent, result.throughput_sent(), true),
+        "sum_received": sum(result.bytes_received, result.throughput_received(), false),
+        "rtt": {
+            "responses": summary.as_ref().map_or(0, |summary| summary.count),
+            "min_ms": summary.as_ref().map(|summary| ms(summary.min as f64)),
+            "mean_ms": summary.as_ref().map(|summary| ms(summary.mean)),
+            "median_ms": summary.as_ref().map(|summary| ms(summary.median as f64)),
+            "max_ms": summary.as_ref().map(|summary| ms(summary.max as f64)),
+            "stddev_ms": summary.as_ref().map(|summary| ms(summary.stddev)),
+            "percentiles_ms": percentiles,
+            "duplicate_percent": delivery.duplicate_rate().unwrap_or_default() * 100.0,
+            "reordered_percent": delivery.reordered_ratio().unwrap_or_default() * 100.0,
+            "mean_absolute_ipdv_ms": result.jitter.mean_absolute_ipdv.map(ms),
+            "pdv_99th_percentile_ms": result.jitter.pdv_99th_percentile.map(|value| ms(value as f64)),
+        },
+    })
+}
+
+fn ms(micros: f64) -> f64 {
+    micros / 1000.0
+}
+
+/// Host and port of `addr`, the port is 0 if there is none.
+fn split_addr(addr: &str) -> (String, u16) {
+    match addr.rsplit_once(':') {
+        Some((host, port)) => (host.trim_start_matches('[').trim_end_matches(']').to_string(), port.parse().unwrap_or(0)),
+        None => (addr.to_string(), 0),
+    }
+}
+
+/// Formats seconds since the Unix epoch like iperf3's `timestamp.time`, e.g. `Thu, 01 Jan 1970 00:00:00 GMT`.
+fn http_date(secs: u64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let days = secs / 86400;
+    let time = secs % 86400;
+    // Civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
@@ -0,0 +1,102 @@
+use crate::client::RTTTimes;
+use crate::histogram::LatencyHistogram;
+
+/// Delay variation of a test in microseconds.
+///
+/// The client only knows round-trip times, so the one-way transit times of RFC 3550 and RFC 3393 are
+/// replaced by the RTTs of the answered requests. The clock offset between client and server doesn't
+/// matter for any of the metrics anyway.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JitterStats {
+    /// RFC 3550 interarrival jitter after the last response, smoothed over the responses in arrival order.
+    pub interarrival_jitter: Option<f64>,
+    /// Mean of the RFC 3393 IPDV between consecutively sent requests that were both answered.
+    pub mean_ipdv: Option<f64>,
+    pub mean_absolute_ipdv: Option<f64>,
+    pub max_absolute_ipdv: Option<u128>,
+    /// 99th percentile of the PDV, i.e. the delay above the minimum delay (RFC 5481).
+    pub pdv_99th_percentile: Option<u128>,
+    pub max_pdv: Option<u128>,
+}
+
+/// Next RFC 3550 interarrival jitter estimate after a response with `rtt` that arrived after one with
+/// `previous_rtt`, starting from 0 without a previous estimate.
+pub fn update_interarrival_jitter(jitter: Option<f64>, previous_rtt: u128, rtt: u128) -> f64 {
+    let difference = (rtt as f64 - previous_rtt as f64).abs();
+    let jitter = jitter.unwrap_or(0.0);
+    jitter + (difference - jitter) / 16.0
+}
+
+impl JitterStats {
+    pub fn from_rtt_times(rtt_times: &RTTTimes) -> Self {
+        let mut tracker = JitterTracker::default();
+        let mut histogram = LatencyHistogram::new(EXACT_PRECISION);
+        let mut arrivals = Vec::new();
+        for packet in rtt_times.rtt_times.iter().filter(|packet| packet.sent_time != 0) {
+            let rtt = (packet.received_time != 0).then(|| packet.received_time - packet.sent_time);
+            tracker.probe(rtt);
+            if let Some(rtt) = rtt {
+                arrivals.push((packet.received_time, rtt));
+                histogram.record(rtt as u64);
+            }
+        }
+        arrivals.sort_unstable();
+        for (_, rtt) in arrivals {
+            tracker.answered(rtt);
+        }
+        tracker.finish(&histogram)
+    }
+}
+
+/// Histogram precision at which RTTs up to 200 ms are recorded exactly.
+const EXACT_PRECISION: u8 = 5;
+
+/// Delay variation of a running test, fed with every answer and every probe as their fate becomes known.
+/// Only the latest RTTs are kept, the PDV is taken from the histogram of all RTTs of the test.
+#[derive(Debug, Clone, Default)]
+pub struct JitterTracker {
+    interarrival_jitter: Option<f64>,
+    /// RTT of the latest answer in arrival order.
+    last_answer: Option<u128>,
+    /// RTT of the previous probe in send order, `None` inside if it was lost.
+    previous_probe: Option<Option<u128>>,
+    ipdv_count: u64,
+    ipdv_sum: i128,
+    absolute_ipdv_sum: u128,
+    max_absolute_ipdv: Option<u128>,
+}
+
+impl JitterTracker {
+    /// Counts an answer with `rtt`, in the order the answers arrived.
+    pub fn answered(&mut self, rtt: u128) {
+        if let Some(last_answer) = self.last_answer {
+            self.interarrival_jitter = Some(update_interarrival_jitter(self.interarrival_jitter, last_answer, rtt));
+        }
+        self.last_answer = Some(rtt);
+    }
+
+    /// Counts the next probe in the order they were sent, with its RTT or `None` if it was lost.
+    pub fn probe(&mut self, rtt: Option<u128>) {
+        if let (Some(Some(previous)), Some(rtt)) = (self.previous_probe, rtt) {
+            let ipdv = rtt as i128 - previous as i128;
+            self.ipdv_count += 1;
+            self.ipdv_sum += ipdv;
+            self.absolute_ipdv_sum += ipdv.unsigned_abs();
+            self.max_absolute_ipdv = self.max_absolute_ipdv.max(Some(ipdv.unsigned_abs()));
+        }
+        self.previous_probe = Some(rtt);
+    }
+
+    /// The delay variation so far, with the PDV of the RTTs in `histogram`.
+    pub fn finish(&self, histogram: &LatencyHistogram) -> JitterStats {
+        let pdv = |value: Option<u64>| value.zip(histogram.min()).map(|(value, min)| (value - min) as u128);
+        JitterStats {
+            interarrival_jitter: self.interarrival_jitter,
+            mean_ipdv: (self.ipdv_count > 0).then(|| self.ipdv_sum as f64 / self.ipdv_count as f64),
+            mean_absolute_ipdv: (self.ipdv_count > 0).then(|| self.absolute_ipdv_sum as f64 / self.ipdv_count as f64),
+            max_absolute_ipdv: self.max_absolute_ipdv,
+            pdv_99th_percentile: pdv(histogram.value_at_percentile(99.0)),
+            max_pdv: pdv(histogram.max()),
+        }
+    }
+}
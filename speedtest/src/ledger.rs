@@ -0,0 +1,211 @@
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use log::{info, warn};
+use crate::plan::{ExperimentPlan, PlanPoint};
+
+/// Name of the ledger in the results directory.
+pub const LEDGER_FILE: &str = "ledger.csv";
+
+/// Name of the file with the latest status of every point, next to the ledger and in the analysis output.
+pub const STATUS_FILE: &str = "campaign_status.csv";
+
+const COLUMNS: [&str; 8] = ["Time", "Iteration", "Point", "Status", "Attempt", "Run Directory", "Seed", "Error"];
+
+/// State of a point of an experiment plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointStatus {
+    /// Part of the plan, but not run yet.
+    Planned,
+    /// The last attempt failed.
+    Failed,
+    Completed,
+}
+
+impl PointStatus {
+    pub fn name(&self) -> &str {
+        match self {
+            PointStatus::Planned => "planned",
+            PointStatus::Failed => "failed",
+            PointStatus::Completed => "completed",
+        }
+    }
+
+    pub fn from_string(status: &str) -> Option<PointStatus> {
+        match status {
+            "planned" => Some(PointStatus::Planned),
+            "failed" => Some(PointStatus::Failed),
+            "completed" => Some(PointStatus::Completed),
+            _ => None,
+        }
+    }
+}
+
+/// A row of the ledger: a change of the status of a point, identified by its iteration and
+/// [`PlanPoint::id`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerEntry {
+    /// Milliseconds since the Unix epoch.
+    pub time: u128,
+    pub iteration: usize,
+    pub point: String,
+    pub status: PointStatus,
+    /// Number of the attempt, 0 for planned points.
+    pub attempt: usize,
+    pub run_dir: String,
+    /// Seed of the random schedule of the campaign, if it has one.
+    pub seed: Option<u64>,
+    pub error: String,
+}
+
+/// Completion ledger of an experiment campaign, an append-only CSV file, so that an interrupted campaign can
+/// be resumed where it stopped.
+#[derive(Debug, Clone)]
+pub struct Ledger {
+    path: PathBuf,
+    pub entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+    /// Opens the ledger at `path`, empty if there is none yet.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut entries = Vec::new();
+        if path.exists() {
+            let mut rdr = csv::Reader::from_path(path)?;
+            for record in rdr.records() {
+                let record = record?;
+                let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid ledger entry in {}: {:?}", path.display(), record));
+                entries.push(LedgerEntry {
+                    time: record[0].parse().map_err(|_| invalid())?,
+                    iteration: record[1].parse().map_err(|_| invalid())?,
+                    point: record[2].to_string(),
+                    status: PointStatus::from_string(&record[3]).ok_or_else(invalid)?,
+                    attempt: record[4].parse().map_err(|_| invalid())?,
+                    run_dir: record[5].to_string(),
+                    seed: record[6].parse().ok(),
+                    error: record[7].to_string(),
+                });
+            }
+        }
+        Ok(Ledger { path: path.to_path_buf(), entries })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `entry` to the ledger file right away.
+    pub fn record(&mut self, entry: LedgerEntry) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file_exists = self.path.exists();
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let mut wtr = csv::Writer::from_writer(file);
+        if !file_exists {
+            wtr.write_record(COLUMNS)?;
+        }
+        wtr.write_record([
+            entry.time.to_string(),
+            entry.iteration.to_string(),
+            entry.point.clone(),
+            entry.status.name().to_string(),
+            entry.attempt.to_string(),
+            entry.run_dir.clone(),
+            entry.seed.map(|seed| seed.to_string()).unwrap_or_default(),
+            entry.error.clone(),
+        ])?;
+        wtr.flush()?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Latest status of a point, `None` if it isn't in the ledger.
+    pub fn status(&self, iteration: usize, point: &str) -> Option<PointStatus> {
+        self.entries.iter().rev().find(|entry| entry.iteration == iteration && entry.point == point).map(|entry| entry.status)
+    }
+
+    /// Number of attempts to run a point so far.
+    pub fn attempts(&self, iteration: usize, point: &str) -> usize {
+        self.entries.iter().filter(|entry| entry.iteration == iteration && entry.point == point && entry.status != PointStatus::Planned).count()
+    }
+
+    /// Seed of the random schedule the campaign was planned with.
+    pub fn seed(&self) -> Option<u64> {
+        self.entries.iter().find_map(|entry| entry.seed)
+    }
+
+    /// Latest status of every point in the ledger, in the order they were planned.
+    pub fn points(&self) -> Vec<(usize, String, PointStatus)> {
+        let mut points: Vec<(usize, String, PointStatus)> = Vec::new();
+        for entry in &self.entries {
+            match points.iter_mut().find(|(iteration, point, _)| *iteration == entry.iteration && *point == entry.point) {
+                Some(point) => point.2 = entry.status,
+                None => points.push((entry.iteration, entry.point.clone(), entry.status)),
+            }
+        }
+        points
+    }
+
+    /// Points that were planned but haven't completed.
+    pub fn incomplete(&self) -> Vec<(usize, String, PointStatus)> {
+        self.points().into_iter().filter(|(_, _, status)| *status != PointStatus::Completed).collect()
+    }
+
+    /// Writes the latest status and the number of attempts of every point to a CSV file.
+    pub fn write_status_csv(&self, path: &Path) -> io::Result<()> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(["Iteration", "Point", "Status", "Attempts", "Complete"])?;
+        for (iteration, point, status) in self.points() {
+            let attempts = self.attempts(iteration, &point).to_string();
+            wtr.write_record([iteration.to_string(), point, status.name().to_string(), attempts, (status == PointStatus::Completed).to_string()])?;
+        }
+        wtr.flush()
+    }
+}
+
+fn now_millis() -> u128 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis()
+}
+
+/// Runs the points of `plan` that haven't completed according to `ledger`, each up to `1 + retries` times,
+/// and records every attempt. `run` runs a point and returns its run directory.
+///
+/// Returns the points that are still incomplete.
+pub fn run_campaign(plan: &ExperimentPlan, ledger: &mut Ledger, retries: usize, mut run: impl FnMut(&PlanPoint) -> io::Result<PathBuf>) -> io::Result<Vec<(usize, String, PointStatus)>> {
+    for point in &plan.points {
+        if ledger.status(point.iteration, &point.id()).is_none() {
+            ledger.record(LedgerEntry { time: now_millis(), iteration: point.iteration, point: point.id(), status: PointStatus::Planned, attempt: 0, run_dir: String::new(), seed: plan.seed, error: String::new() })?;
+        }
+    }
+    for point in &plan.points {
+        let id = point.id();
+        if ledger.status(point.iteration, &id) == Some(PointStatus::Completed) {
+            info!("Skipping completed experiment {} of iteration {}", id, point.iteration);
+            continue;
+        }
+        for _ in 0..=retries {
+            let attempt = ledger.attempts(point.iteration, &id) + 1;
+            let (status, run_dir, error) = match run(point) {
+                Ok(run_dir) => (PointStatus::Completed, run_dir.display().to_string(), String::new()),
+                Err(e) => {
+                    warn!("Experiment {} of iteration {} failed in attempt {}: {}", id, point.iteration, attempt, e);
+                    (PointStatus::Failed, String::new(), e.to_string())
+                }
+            };
+            ledger.record(LedgerEntry { time: now_millis(), iteration: point.iteration, point: id.clone(), status, attempt, run_dir, seed: plan.seed, error })?;
+            if status == PointStatus::Completed {
+                break;
+            }
+        }
+    }
+    let incomplete = ledger.incomplete();
+    if incomplete.is_empty() {
+        info!("All {} experiments completed", ledger.points().len());
+    }
+    for (iteration, point, status) in &incomplete {
+        warn!("Experiment {} of iteration {} is incomplete ({})", point, iteration, status.name());
+    }
+    Ok(incomplete)
+}
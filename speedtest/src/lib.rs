@@ -1 +1,27 @@
-pub mod udp_application;
\ No newline at end of file
+pub mod udp_application;
+pub mod analyze;
+pub mod client;
+pub mod collector;
+pub mod concurrent;
+pub mod config;
+pub mod delivery;
+pub mod events;
+pub mod failover;
+pub mod histogram;
+pub mod hooks;
+pub mod interval;
+pub mod iperf;
+pub mod jitter;
+pub mod ledger;
+pub mod manifest;
+pub mod outage;
+pub mod placement;
+pub mod plan;
+pub mod server;
+pub mod sim;
+pub mod stats;
+pub mod timeline;
+pub mod transport;
+
+pub use client::{Client, ClientBuilder, TestResult, TestSpec};
+pub use server::{QosProfile, Server, ServerBuilder, ServerHandle};
@@ -1,3 +1,275 @@
-mod udp_application;
-mod UDPServer;
-mod UDPClient;
\ No newline at end of file
+use std::path::{Path, PathBuf};
+use std::process;
+use std::fs;
+use std::time::{Duration, SystemTime};
+use clap::{Parser, Subcommand};
+use log::{error, info, LevelFilter};
+use udpbenchmark::{analyze, client, concurrent, events, failover, placement, server};
+use udpbenchmark::config::{ClientConfig, ConfigError, ExperimentTarget, ServerConfig};
+use udpbenchmark::events::MarkerSource;
+use udpbenchmark::plan::ExperimentPlan;
+
+#[derive(Parser)]
+#[command(name = "udpbenchmark", version, about = "UDP latency and throughput benchmark")]
+struct Cli {
+    /// Path to the YAML config file (defaults to the config built into the binary)
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Increase log verbosity (-v info, -vv debug, -vvv trace); RUST_LOG is used otherwise
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the echo server with the configured QoS profile
+    Server {
+        /// Address to bind to, overrides `server.address`
+        #[arg(long)]
+        address: Option<String>,
+        /// QoS profile, overrides `server.qos_profile`
+        #[arg(long)]
+        qos_profile: Option<String>,
+    },
+    /// Run the client as configured (single test or experiment mode)
+    Client {
+        #[command(flatten)]
+        overrides: ClientOverrides,
+        /// Speedtest mode, overrides `speedtest_mode`
+        #[arg(long)]
+        mode: Option<String>,
+    },
+    /// Run a single ping test
+    Ping {
+        #[command(flatten)]
+        overrides: ClientOverrides,
+        /// Interval between pings in seconds, overrides `ping_interval`
+        #[arg(long)]
+        interval: Option<f64>,
+    },
+    /// Ping all experiment servers at the same time and compare their latencies pairwise
+    Probe {
+        /// Test duration in seconds, overrides `speedtest_duration`
+        #[arg(long)]
+        duration: Option<f64>,
+        /// Interval between pings in seconds, overrides `ping_interval`
+        #[arg(long)]
+        interval: Option<f64>,
+    },
+    /// Rank the experiment servers for service placement by the configured composite score and SLA thresholds
+    Advise {
+        /// Number of rounds of probing and ranking
+        #[arg(long, default_value_t = 1)]
+        rounds: usize,
+        /// Re-evaluate until interrupted, pausing `placement.interval` between rounds
+        #[arg(long)]
+        continuous: bool,
+    },
+    /// Probe the failover servers like an application, switching to a backup when the active one goes down
+    Failover {
+        /// Test duration in seconds, overrides `speedtest_duration`
+        #[arg(long)]
+        duration: Option<f64>,
+        /// Fail back to a preferred server once it recovered, like `failover.failback`
+        #[arg(long)]
+        failback: bool,
+    },
+    /// Run the experiment plan over all experiment servers and swept parameters
+    Sweep {
+        /// Number of iterations, overrides `experiment_count`
+        #[arg(long)]
+        count: Option<usize>,
+        /// Print the expanded plan and its estimated total time instead of running it
+        #[arg(long)]
+        dry_run: bool,
+        /// Start a new campaign instead of resuming the one in `experiment_ledger`, keeping the old ledger
+        #[arg(long)]
+        fresh: bool,
+    },
+    /// Merge the client's result files and aggregate them across iterations, naming servers after `server_labels`
+    Analyze {
+        #[arg(long, default_value = "results")]
+        results_dir: PathBuf,
+        #[arg(long, default_value = ".")]
+        output_dir: PathBuf,
+    },
+    /// Print a summary table of the client's result files
+    Report {
+        #[arg(long, default_value = "results")]
+        results_dir: PathBuf,
+    },
+    /// Mark an event in a running client listening on a control socket
+    Mark {
+        /// Label of the event
+        label: String,
+        /// Control socket of the client, defaults to `event_markers.socket`
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Args)]
+struct ClientOverrides {
+    /// Server address, overrides `server_addr` and `experiment_servers`
+    #[arg(long)]
+    server: Option<String>,
+    /// Test duration in seconds, overrides `speedtest_duration`
+    #[arg(long)]
+    duration: Option<f64>,
+    /// Payload size in bytes, overrides `payload_size` and `experiment_payload_sizes`
+    #[arg(long)]
+    payload_size: Option<usize>,
+    /// Also write the results as iperf3-compatible JSON, like `json_output`
+    #[arg(short = 'J', long)]
+    json: bool,
+}
+
+impl ClientOverrides {
+    fn apply(&self, config: &mut ClientConfig) {
+        // The overrides also replace the swept values, so that they apply in experiment mode too
+        if let Some(server) = &self.server {
+            config.server_addr = server.clone();
+            config.experiment_servers = vec![ExperimentTarget::new(config.server_name(server), server.clone())];
+        }
+        if let Some(duration) = self.duration {
+            config.speedtest_duration = Duration::from_secs_f64(duration);
+            config.experiment_durations = vec![config.speedtest_duration];
+        }
+        if let Some(payload_size) = self.payload_size {
+            config.payload_size = payload_size;
+            config.experiment_payload_sizes = vec![payload_size];
+            for target in &mut config.experiment_servers {
+                target.payload_sizes = None;
+            }
+        }
+        if self.json {
+            config.json_output = true;
+        }
+    }
+}
+
+fn init_logging(verbose: u8) {
+    let mut builder = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+    match verbose {
+        0 => {}
+        1 => { builder.filter_level(LevelFilter::Info); }
+        2 => { builder.filter_level(LevelFilter::Debug); }
+        _ => { builder.filter_level(LevelFilter::Trace); }
+    }
+    builder.init();
+}
+
+fn load_client_config(path: Option<&Path>) -> Result<ClientConfig, ConfigError> {
+    let config = ClientConfig::load(path)?;
+    info!("Loaded client config from {}", path.map_or("built-in defaults".to_string(), |path| path.display().to_string()));
+    Ok(config)
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = cli.config.as_deref();
+    match cli.command {
+        Command::Server { address, qos_profile } => {
+            let mut config = ServerConfig::load(config_path)?;
+            if let Some(address) = address {
+                config.address = address;
+            }
+            if let Some(qos_profile) = qos_profile {
+                config.qos_profile = qos_profile;
+            }
+            server::run(&config)?;
+        }
+        Command::Client { overrides, mode } => {
+            let mut config = load_client_config(config_path)?;
+            overrides.apply(&mut config);
+            if let Some(mode) = mode {
+                config.speedtest_mode = mode.to_lowercase();
+                config.experiment_modes = vec![config.speedtest_mode.clone()];
+            }
+            client::run(&config);
+        }
+        Command::Ping { overrides, interval } => {
+            let mut config = load_client_config(config_path)?;
+            overrides.apply(&mut config);
+            if let Some(interval) = interval {
+                config.ping_interval = Duration::from_secs_f64(interval);
+            }
+            config.speedtest_mode = "ping".to_string();
+            client::run_single(&config);
+        }
+        Command::Probe { duration, interval } => {
+            let mut config = load_client_config(config_path)?;
+            if let Some(duration) = duration {
+                config.speedtest_duration = Duration::from_secs_f64(duration);
+            }
+            if let Some(interval) = interval {
+                config.ping_interval = Duration::from_secs_f64(interval);
+            }
+            concurrent::run(&config)?;
+        }
+        Command::Advise { rounds, continuous } => {
+            let config = load_client_config(config_path)?;
+            placement::run(&config, if continuous { None } else { Some(rounds) })?;
+        }
+        Command::Failover { duration, failback } => {
+            let mut config = load_client_config(config_path)?;
+            if let Some(duration) = duration {
+                config.speedtest_duration = Duration::from_secs_f64(duration);
+            }
+            if failback {
+                config.failover.failback = true;
+            }
+            failover::run(&config)?;
+        }
+        Command::Sweep { count, dry_run, fresh } => {
+            let mut config = load_client_config(config_path)?;
+            if let Some(count) = count {
+                config.experiment_count = count;
+            }
+            if dry_run {
+                println!("{}", ExperimentPlan::expand(&config));
+            } else {
+                if fresh && config.experiment_ledger.exists() {
+                    let started = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+                    let old_ledger = config.experiment_ledger.with_extension(format!("{}.csv", started));
+                    fs::rename(&config.experiment_ledger, &old_ledger)?;
+                    info!("Moved the previous campaign's ledger to {}", old_ledger.display());
+                }
+                client::run_experiments(&config);
+            }
+        }
+        Command::Analyze { results_dir, output_dir } => {
+            let config = load_client_config(config_path)?;
+            analyze::analyze(&results_dir, &output_dir, &config.server_labels)?;
+        }
+        Command::Report { results_dir } => analyze::report(&results_dir)?,
+        Command::Mark { label, socket } => {
+            let socket = match socket {
+                Some(socket) => socket,
+                None => load_client_config(config_path)?
+                    .event_markers
+                    .into_iter()
+                    .find_map(|marker| match marker {
+                        MarkerSource::Socket(path) => Some(path),
+                        _ => None,
+                    })
+                    .ok_or("no control socket configured in `event_markers.socket`")?,
+            };
+            events::send_marker(&socket, &label)?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let cli = Cli::parse();
+    init_logging(cli.verbose);
+
+    if let Err(e) = run(cli) {
+        error!("{}", e);
+        process::exit(1);
+    }
+}
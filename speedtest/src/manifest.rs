@@ -0,0 +1,122 @@
+use std::ffi::CStr;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+use serde_json::{json, Value};
+use crate::client::TestResult;
+use crate::config::ClientConfig;
+use crate::plan::PlanPoint;
+
+/// Name of the manifest in every run directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Context of a single run, written as `manifest.json` next to its result files so that runs can be
+/// told apart afterwards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunManifest {
+    /// Index of the experiment iteration, 0 for a single test.
+    pub iteration: usize,
+    /// Start and end of the test in microseconds since the Unix epoch.
+    pub started_at: u128,
+    pub ended_at: u128,
+    pub hostname: String,
+    pub kernel: String,
+    pub version: String,
+    /// Seed of the server's impairment RNG, if the server reported it.
+    pub server_seed: Option<u64>,
+    /// Resolved client config, see [`ClientConfig::to_json`].
+    pub config: Value,
+    /// Parameters of the test itself, see [`TestSpec::to_json`](crate::client::TestSpec::to_json).
+    pub test: Value,
+    /// Parameter tuple of the experiment plan the run belongs to, see [`PlanPoint::to_json`].
+    pub point: Option<Value>,
+    /// Result files in the run directory.
+    pub files: Vec<String>,
+}
+
+impl RunManifest {
+    pub fn new(result: &TestResult, config: &ClientConfig, iteration: usize) -> Self {
+        let started_at = result.started_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_micros();
+        let (hostname, kernel) = uname();
+        RunManifest {
+            iteration,
+            started_at,
+            ended_at: started_at + result.elapsed.as_micros(),
+            hostname,
+            kernel,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            server_seed: result.server_seed,
+            config: config.to_json(),
+            test: result.spec.to_json(),
+            point: None,
+            files: Vec::new(),
+        }
+    }
+
+    /// Tags the run with the plan point it measured.
+    pub fn point(mut self, point: &PlanPoint) -> Self {
+        self.point = Some(point.to_json());
+        self
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "tool": { "name": env!("CARGO_PKG_NAME"), "version": self.version },
+            "iteration": self.iteration,
+            "started_at": self.started_at,
+            "ended_at": self.ended_at,
+            "host": { "hostname": self.hostname, "kernel": self.kernel },
+            "seeds": { "server": self.server_seed },
+            "config": self.config,
+            "test": self.test,
+            "point": self.point,
+            "files": self.files,
+        })
+    }
+
+    pub fn from_json(doc: &Value) -> Option<Self> {
+        Some(RunManifest {
+            iteration: doc["iteration"].as_u64()? as usize,
+            started_at: doc["started_at"].as_u64()? as u128,
+            ended_at: doc["ended_at"].as_u64()? as u128,
+            hostname: doc["host"]["hostname"].as_str().unwrap_or_default().to_string(),
+            kernel: doc["host"]["kernel"].as_str().unwrap_or_default().to_string(),
+            version: doc["tool"]["version"].as_str().unwrap_or_default().to_string(),
+            server_seed: doc["seeds"]["server"].as_u64(),
+            config: doc["config"].clone(),
+            test: doc["test"].clone(),
+            point: Some(doc["point"].clone()).filter(|point| !point.is_null()),
+            files: doc["files"].as_array().map(|files| files.iter().filter_map(|file| file.as_str().map(str::to_string)).collect()).unwrap_or_default(),
+        })
+    }
+
+    /// Writes the manifest into `run_dir`, listing the files already in there.
+    pub fn write(&mut self, run_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(run_dir)?;
+        let mut files = fs::read_dir(run_dir)?
+            .filter_map(|entry| entry.ok().and_then(|entry| entry.file_name().into_string().ok()))
+            .filter(|name| name != MANIFEST_FILE)
+            .collect::<Vec<_>>();
+        files.sort();
+        self.files = files;
+        let file = fs::File::create(run_dir.join(MANIFEST_FILE))?;
+        serde_json::to_writer_pretty(file, &self.to_json()).map_err(io::Error::from)
+    }
+
+    /// Reads the manifest of the run in `run_dir`.
+    pub fn read(run_dir: &Path) -> io::Result<Self> {
+        let doc: Value = serde_json::from_reader(fs::File::open(run_dir.join(MANIFEST_FILE))?)?;
+        RunManifest::from_json(&doc).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("invalid manifest in {}", run_dir.display())))
+    }
+}
+
+/// Host name and kernel release of this machine, empty if they are unknown.
+fn uname() -> (String, String) {
+    let mut name: libc::utsname = unsafe { std::mem::zeroed() };
+    if unsafe { libc::uname(&mut name) } != 0 {
+        return (String::new(), String::new());
+    }
+    let field = |field: &[libc::c_char]| unsafe { CStr::from_ptr(field.as_ptr()) }.to_string_lossy().into_owned();
+    (field(&name.nodename), field(&name.release))
+}
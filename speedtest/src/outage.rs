@@ -0,0 +1,118 @@
+use std::time::Duration;
+use crate::client::RTTTimes;
+
+/// When a run of probes counts as an outage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutageConfig {
+    /// Minimum number of consecutive failed probes that make up an outage.
+    pub min_probes: usize,
+    /// Probes answered slower than this count as failed, too. Only lost probes fail if not set.
+    pub rtt_threshold: Option<Duration>,
+}
+
+impl Default for OutageConfig {
+    fn default() -> Self {
+        OutageConfig {
+            min_probes: 3,
+            rtt_threshold: None,
+        }
+    }
+}
+
+/// A window of consecutive failed probes. Times are microseconds since the Unix epoch like in
+/// [`crate::client::PacketStats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outage {
+    /// Send time of the first failed probe.
+    pub start: u128,
+    /// Send time of the first successful probe after the outage, or of the last probe if the test ended
+    /// during the outage.
+    pub end: u128,
+    /// Whether a probe succeeded again before the end of the test.
+    pub recovered: bool,
+    pub lost: u64,
+    /// Probes answered, but above the RTT threshold.
+    pub slow: u64,
+}
+
+impl Outage {
+    pub fn duration(&self) -> Duration {
+        Duration::from_micros((self.end - self.start) as u64)
+    }
+}
+
+/// Outages of a test, in the order they started.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutageReport {
+    pub outages: Vec<Outage>,
+}
+
+impl OutageReport {
+    /// Scans the probes in the order they were sent for runs of failed probes.
+    pub fn detect(rtt_times: &RTTTimes, config: &OutageConfig) -> Self {
+        let mut detector = OutageDetector::new(config);
+        for packet in rtt_times.rtt_times.iter().filter(|packet| packet.sent_time != 0) {
+            detector.probe(packet.sent_time, (packet.received_time != 0).then(|| packet.received_time - packet.sent_time));
+        }
+        detector.finish()
+    }
+
+    pub fn total_downtime(&self) -> Duration {
+        self.outages.iter().map(Outage::duration).sum()
+    }
+
+    pub fn longest(&self) -> Option<&Outage> {
+        // The first of several equally long outages
+        self.outages.iter().rev().max_by_key(|outage| outage.duration())
+    }
+}
+
+/// Detects the outages of a running test, fed with its probes in the order they were sent.
+#[derive(Debug, Clone)]
+pub struct OutageDetector {
+    min_probes: usize,
+    rtt_threshold: Option<u128>,
+    /// Current run of failed probes: (start, probes, lost, slow)
+    run: Option<(u128, usize, u64, u64)>,
+    last_sent: u128,
+    outages: Vec<Outage>,
+}
+
+impl OutageDetector {
+    pub fn new(config: &OutageConfig) -> Self {
+        OutageDetector {
+            min_probes: config.min_probes.max(1),
+            rtt_threshold: config.rtt_threshold.map(|threshold| threshold.as_micros()),
+            run: None,
+            last_sent: 0,
+            outages: Vec::new(),
+        }
+    }
+
+    /// Counts the next probe, sent at `sent_time`, with its RTT or `None` if it was lost.
+    pub fn probe(&mut self, sent_time: u128, rtt: Option<u128>) {
+        self.last_sent = sent_time;
+        let lost = rtt.is_none();
+        let slow = rtt.is_some_and(|rtt| self.rtt_threshold.is_some_and(|threshold| rtt > threshold));
+        if lost || slow {
+            let (_, probes, run_lost, run_slow) = self.run.get_or_insert((sent_time, 0, 0, 0));
+            *probes += 1;
+            *run_lost += lost as u64;
+            *run_slow += slow as u64;
+        } else if let Some((start, probes, lost, slow)) = self.run.take() {
+            if probes >= self.min_probes {
+                self.outages.push(Outage { start, end: sent_time, recovered: true, lost, slow });
+            }
+        }
+    }
+
+    /// The outages, including one that is still going on at the last probe.
+    pub fn finish(mut self) -> OutageReport {
+        if let Some((start, probes, lost, slow)) = self.run {
+            if probes >= self.min_probes {
+                self.outages.push(Outage { start, end: self.last_sent, recovered: false, lost, slow });
+            }
+        }
+        OutageReport { outages: self.outages }
+    }
+}
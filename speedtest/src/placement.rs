@@ -0,0 +1,277 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use log::{info, warn};
+use serde_json::json;
+use crate::client::{TestResult, RESULTS_DIR};
+use crate::concurrent::{self, ConcurrentProbe};
+use crate::config::ClientConfig;
+
+/// Name of the file with the recommendations of every round in the directory of a placement run.
+pub const PLACEMENT_FILE: &str = "placement.csv";
+
+/// Metric of a target that the placement score is computed from, in milliseconds or percent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlacementMetric {
+    MeanRtt,
+    MedianRtt,
+    P99Rtt,
+    /// RFC 3550 interarrival jitter of the RTTs.
+    Jitter,
+    /// Share of the probes that were never answered.
+    Loss,
+}
+
+impl PlacementMetric {
+    pub fn from_string(metric: &str) -> Option<PlacementMetric> {
+        match metric {
+            "mean_rtt" => Some(PlacementMetric::MeanRtt),
+            "median_rtt" => Some(PlacementMetric::MedianRtt),
+            "p99_rtt" => Some(PlacementMetric::P99Rtt),
+            "jitter" => Some(PlacementMetric::Jitter),
+            "loss" => Some(PlacementMetric::Loss),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            PlacementMetric::MeanRtt => "mean_rtt",
+            PlacementMetric::MedianRtt => "median_rtt",
+            PlacementMetric::P99Rtt => "p99_rtt",
+            PlacementMetric::Jitter => "jitter",
+            PlacementMetric::Loss => "loss",
+        }
+    }
+
+    /// Column name with the unit, e.g. `P99 RTT (ms)`.
+    pub fn column(&self) -> &str {
+        match self {
+            PlacementMetric::MeanRtt => "Mean RTT (ms)",
+            PlacementMetric::MedianRtt => "Median RTT (ms)",
+            PlacementMetric::P99Rtt => "P99 RTT (ms)",
+            PlacementMetric::Jitter => "Jitter (ms)",
+            PlacementMetric::Loss => "Loss (%)",
+        }
+    }
+
+    /// Value of the metric in `result`, `None` if no probe was answered.
+    pub fn value(&self, result: &TestResult) -> Option<f64> {
+        let millis = |micros: f64| micros / 1000.0;
+        match self {
+            PlacementMetric::MeanRtt => result.histogram.mean().map(millis),
+            PlacementMetric::MedianRtt => result.histogram.value_at_percentile(50.0).map(|value| millis(value as f64)),
+            PlacementMetric::P99Rtt => result.histogram.value_at_percentile(99.0).map(|value| millis(value as f64)),
+            PlacementMetric::Jitter => result.jitter.interarrival_jitter.map(millis),
+            PlacementMetric::Loss => result.delivery.loss_rate().map(|rate| rate * 100.0),
+        }
+    }
+}
+
+/// A metric with its weight in the composite score and its SLA threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacementCriterion {
+    pub metric: PlacementMetric,
+    pub weight: f64,
+    /// Highest acceptable value, in the unit of the metric. The metric enters the score relative to it.
+    pub sla: f64,
+}
+
+/// How targets are scored and ranked for service placement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacementPolicy {
+    pub criteria: Vec<PlacementCriterion>,
+    /// Pause between two rounds of a continuous re-evaluation.
+    pub interval: Duration,
+}
+
+impl Default for PlacementPolicy {
+    fn default() -> Self {
+        PlacementPolicy {
+            criteria: vec![
+                PlacementCriterion { metric: PlacementMetric::P99Rtt, weight: 1.0, sla: 20.0 },
+                PlacementCriterion { metric: PlacementMetric::Jitter, weight: 1.0, sla: 5.0 },
+                PlacementCriterion { metric: PlacementMetric::Loss, weight: 1.0, sla: 1.0 },
+            ],
+            interval: Duration::ZERO,
+        }
+    }
+}
+
+impl PlacementPolicy {
+    /// Scores every target and ranks them: targets that meet all SLA thresholds first, each group by
+    /// ascending score.
+    ///
+    /// The score is the weighted mean of every metric divided by its SLA threshold, so a target exactly at
+    /// all thresholds scores 1 and lower is better. A target without answered probes scores infinity.
+    pub fn rank(&self, results: &[TestResult]) -> Recommendation {
+        let total_weight = self.criteria.iter().map(|criterion| criterion.weight).sum::<f64>();
+        let mut candidates = results
+            .iter()
+            .map(|result| {
+                let values = self.criteria.iter().map(|criterion| criterion.metric.value(result)).collect::<Vec<_>>();
+                let violations = self
+                    .criteria
+                    .iter()
+                    .zip(&values)
+                    .filter(|(criterion, value)| value.is_none_or(|value| value > criterion.sla))
+                    .map(|(criterion, _)| criterion.metric)
+                    .collect();
+                let score = self
+                    .criteria
+                    .iter()
+                    .zip(&values)
+                    .map(|(criterion, value)| value.map_or(f64::INFINITY, |value| criterion.weight * value / criterion.sla))
+                    .sum::<f64>()
+                    / total_weight;
+                Candidate { name: result.spec.server_name().to_string(), address: result.spec.server_addr.clone(), values, score, violations }
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| b.meets_sla().cmp(&a.meets_sla()).then(a.score.total_cmp(&b.score)));
+        Recommendation { metrics: self.criteria.iter().map(|criterion| criterion.metric).collect(), candidates }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "criteria": self.criteria.iter().map(|criterion| json!({ "metric": criterion.metric.name(), "weight": criterion.weight, "sla": criterion.sla })).collect::<Vec<_>>(),
+            "interval": self.interval.as_secs_f64(),
+        })
+    }
+}
+
+/// A scored target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub name: String,
+    pub address: String,
+    /// Values of the metrics in the order of the criteria.
+    pub values: Vec<Option<f64>>,
+    pub score: f64,
+    /// Metrics above their SLA threshold.
+    pub violations: Vec<PlacementMetric>,
+}
+
+impl Candidate {
+    pub fn meets_sla(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Targets ranked by a [`PlacementPolicy`], the recommended one first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recommendation {
+    /// Metrics of the criteria, in the order of the candidates' values.
+    pub metrics: Vec<PlacementMetric>,
+    pub candidates: Vec<Candidate>,
+}
+
+impl Recommendation {
+    /// The recommended target, also if it doesn't meet the SLA.
+    pub fn best(&self) -> Option<&Candidate> {
+        self.candidates.first()
+    }
+
+    /// Score difference between the runner-up and the recommended target, `None` with less than two targets.
+    pub fn margin(&self) -> Option<f64> {
+        match self.candidates.as_slice() {
+            [best, runner_up, ..] => Some(runner_up.score - best.score),
+            _ => None,
+        }
+    }
+
+    /// Appends one row per candidate to a CSV file: the round and its start in microseconds since the Unix
+    /// epoch, the rank, score and metrics, and on the recommended target's row the margin and whether the
+    /// recommendation changed since the previous round.
+    pub fn append_csv(&self, path: &Path, round: usize, started_at: SystemTime, flipped: bool) -> io::Result<()> {
+        let file_exists = path.exists();
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut wtr = csv::Writer::from_writer(file);
+        if !file_exists {
+            let mut header = ["Round", "Time", "Rank", "Server Name", "Server Address", "Score", "SLA Met", "Violations"].iter().map(|column| column.to_string()).collect::<Vec<_>>();
+            header.extend(self.metrics.iter().map(|metric| metric.column().to_string()));
+            header.extend(["Margin".to_string(), "Flipped".to_string()]);
+            wtr.write_record(&header)?;
+        }
+        let time = started_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_micros();
+        for (rank, candidate) in self.candidates.iter().enumerate() {
+            let mut record = vec![
+                round.to_string(),
+                time.to_string(),
+                (rank + 1).to_string(),
+                candidate.name.clone(),
+                candidate.address.clone(),
+                candidate.score.to_string(),
+                candidate.meets_sla().to_string(),
+                candidate.violations.iter().map(PlacementMetric::name).collect::<Vec<_>>().join(" "),
+            ];
+            record.extend(candidate.values.iter().map(|value| value.map(|value| value.to_string()).unwrap_or_default()));
+            if rank == 0 {
+                record.extend([self.margin().map(|margin| margin.to_string()).unwrap_or_default(), flipped.to_string()]);
+            } else {
+                record.extend([String::new(), String::new()]);
+            }
+            wtr.write_record(&record)?;
+        }
+        wtr.flush()
+    }
+}
+
+impl fmt::Display for Recommendation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (rank, candidate) in self.candidates.iter().enumerate() {
+            let sla = if candidate.meets_sla() {
+                "SLA met".to_string()
+            } else {
+                format!("violates {}", candidate.violations.iter().map(PlacementMetric::name).collect::<Vec<_>>().join(", "))
+            };
+            writeln!(f, "{}. {} ({}): score {:.3}, {}", rank + 1, candidate.name, candidate.address, candidate.score, sla)?;
+        }
+        match (self.best(), self.margin()) {
+            (Some(best), Some(margin)) => write!(f, "Recommended: {} by a margin of {:.3} over {}", best.name, margin, self.candidates[1].name)?,
+            (Some(best), None) => write!(f, "Recommended: {}", best.name)?,
+            (None, _) => write!(f, "No targets")?,
+        }
+        if self.best().is_some_and(|best| !best.meets_sla()) {
+            write!(f, " (no target meets the SLA)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Probes all `experiment_servers` at the same time and ranks them with the `placement` policy, for `rounds`
+/// rounds or until interrupted if `rounds` is `None`. Logs every ranking and when the recommended target
+/// changes, and writes the probes of every round and [`PLACEMENT_FILE`] to `results/placement_<start ms>/`.
+///
+/// Returns that directory.
+pub fn run(config: &ClientConfig, rounds: Option<usize>) -> io::Result<PathBuf> {
+    let policy = &config.placement;
+    let started = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+    let dir = Path::new(RESULTS_DIR).join(format!("placement_{}", started));
+    fs::create_dir_all(&dir)?;
+    let mut preferred: Option<String> = None;
+    let mut round = 0;
+    while rounds.is_none_or(|rounds| round < rounds) {
+        let result = ConcurrentProbe::new(concurrent::specs(config)).run()?;
+        result.write_to_dir(&dir.join(format!("round_{}", round)))?;
+        let recommendation = policy.rank(&result.results);
+        for line in recommendation.to_string().lines() {
+            info!("Round {}: {}", round, line);
+        }
+        let best = recommendation.best().map(|best| best.name.clone());
+        let flipped = preferred.is_some() && best != preferred;
+        if flipped {
+            warn!("Round {}: preferred placement flipped from {} to {}", round, preferred.as_deref().unwrap_or_default(), best.as_deref().unwrap_or_default());
+        }
+        recommendation.append_csv(&dir.join(PLACEMENT_FILE), round, result.started_at, flipped)?;
+        preferred = best;
+        round += 1;
+        if rounds.is_none_or(|rounds| round < rounds) {
+            thread::sleep(policy.interval);
+        }
+    }
+    info!("Wrote placement recommendations to {}", dir.join(PLACEMENT_FILE).display());
+    Ok(dir)
+}
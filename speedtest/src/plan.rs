@@ -0,0 +1,265 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde_json::json;
+use crate::client::{file_name_part, get_bitrate_scale, BitrateScale, SpeedtestEnum, TestSpec};
+use crate::config::{ClientConfig, ExperimentTarget};
+
+/// Order of the tests within an iteration of an experiment plan.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    /// All tests of a target before those of the next, targets in the configured order.
+    Sequential,
+    /// One test of every target in turn, round-robin.
+    Interleaved,
+    /// A random permutation of all tests, drawn anew every iteration from a seeded RNG.
+    Random,
+}
+
+impl Schedule {
+    pub fn from_string(schedule: &str) -> Option<Schedule> {
+        match schedule {
+            "sequential" => Some(Schedule::Sequential),
+            "interleaved" | "round_robin" => Some(Schedule::Interleaved),
+            "random" => Some(Schedule::Random),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Schedule::Sequential => "sequential",
+            Schedule::Interleaved => "interleaved",
+            Schedule::Random => "random",
+        }
+    }
+}
+
+/// One test of an experiment campaign: a target and the values of all swept parameters.
+///
+/// Parameters the mode doesn't use are `None`, so that e.g. a bitrate sweep doesn't repeat ping tests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanPoint {
+    pub iteration: usize,
+    /// Position of the test within its iteration, in the order of the schedule.
+    pub position: usize,
+    pub target: ExperimentTarget,
+    pub mode: SpeedtestEnum,
+    pub payload_size: usize,
+    /// Target bitrate in bits per second, only in `duration_custom_bitrate` mode.
+    pub bitrate: Option<i64>,
+    /// Interval between two probes, only in ping mode.
+    pub interval: Option<Duration>,
+    /// Test duration, in all modes but `packet_count`.
+    pub duration: Option<Duration>,
+}
+
+impl PlanPoint {
+    /// Identifies the parameter tuple, the same in every iteration, e.g. `Public-Cloud_ping_500B_1s_10s`.
+    pub fn id(&self) -> String {
+        let mut id = format!("{}_{}_{}B", file_name_part(&self.target.name), self.mode.to_string(), self.payload_size);
+        if let Some(bitrate) = self.bitrate {
+            id.push_str(&format!("_{}bps", bitrate));
+        }
+        if let Some(interval) = self.interval {
+            id.push_str(&format!("_{}s", interval.as_secs_f64()));
+        }
+        if let Some(duration) = self.duration {
+            id.push_str(&format!("_{}s", duration.as_secs_f64()));
+        }
+        id
+    }
+
+    /// The spec of the test, from the client config with the target's address and the point's parameters.
+    pub fn spec(&self, config: &ClientConfig) -> TestSpec {
+        let mut spec = TestSpec::from_config(config, &self.target, self.payload_size);
+        spec.mode = self.mode;
+        if let Some(bitrate) = self.bitrate {
+            spec.bitrate = bitrate;
+            spec.bitrate_scale = BitrateScale::Bps;
+        }
+        if let Some(interval) = self.interval {
+            spec.interval = interval;
+        }
+        if let Some(duration) = self.duration {
+            spec.duration = duration;
+        }
+        spec
+    }
+
+    /// The parameter tuple with durations in seconds and the bitrate in bits per second, `null` where the
+    /// mode doesn't use a parameter.
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "id": self.id(),
+            "iteration": self.iteration,
+            "position": self.position,
+            "target": self.target.name,
+            "address": self.target.address,
+            "mode": self.mode.to_string(),
+            "payload_size": self.payload_size,
+            "bitrate": self.bitrate,
+            "interval": self.interval.map(|interval| interval.as_secs_f64()),
+            "duration": self.duration.map(|duration| duration.as_secs_f64()),
+        })
+    }
+}
+
+/// All tests of an experiment campaign, in the order they run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentPlan {
+    pub points: Vec<PlanPoint>,
+    pub schedule: Schedule,
+    /// Seed of the random schedule, `experiment_seed` or a random one if that isn't set.
+    pub seed: Option<u64>,
+    /// Pause after every test.
+    pub pause: Duration,
+}
+
+impl ExperimentPlan {
+    /// Expands `experiment_count` iterations of every target crossed with the configured modes, payload
+    /// sizes, bitrates, intervals and durations, ordered by `experiment_schedule`. A target's overrides
+    /// replace the respective list.
+    pub fn expand(config: &ClientConfig) -> Self {
+        let schedule = config.experiment_schedule;
+        let seed = match schedule {
+            Schedule::Random => Some(config.experiment_seed.unwrap_or_else(rand::random)),
+            _ => None,
+        };
+        let mut rng = StdRng::seed_from_u64(seed.unwrap_or_default());
+        let mut points = Vec::new();
+        for iteration in 0..config.experiment_count {
+            let mut per_target = Vec::new();
+            for target in &config.experiment_servers {
+                let mut target_points = Vec::new();
+                let modes = match &target.speedtest_mode {
+                    Some(mode) => vec![mode.clone()],
+                    None => config.experiment_modes.clone(),
+                };
+                let bitrates = match target.bitrate {
+                    Some(bitrate) => vec![bitrate * get_bitrate_scale(target.bitrate_scale.as_deref().unwrap_or(&config.bitrate_scale)) as i64],
+                    None => config.experiment_bitrates.iter().map(|bitrate| bitrate * get_bitrate_scale(&config.bitrate_scale) as i64).collect(),
+                };
+                let payload_sizes = target.payload_sizes.as_ref().unwrap_or(&config.experiment_payload_sizes);
+                for mode in modes.iter().map(|mode| SpeedtestEnum::from_string(mode)) {
+                    let bitrates = sweep(&bitrates, mode == SpeedtestEnum::ByDurationCustomBitrate);
+                    let intervals = sweep(&config.experiment_intervals, mode == SpeedtestEnum::Ping);
+                    let durations = sweep(&config.experiment_durations, mode != SpeedtestEnum::ByPacketCount);
+                    for &payload_size in payload_sizes {
+                        for &bitrate in &bitrates {
+                            for &interval in &intervals {
+                                for &duration in &durations {
+                                    target_points.push(PlanPoint { iteration, position: 0, target: target.clone(), mode, payload_size, bitrate, interval, duration });
+                                }
+                            }
+                        }
+                    }
+                }
+                per_target.push(target_points);
+            }
+            let mut iteration_points = match schedule {
+                Schedule::Sequential => per_target.into_iter().flatten().collect::<Vec<_>>(),
+                Schedule::Interleaved => {
+                    let rounds = per_target.iter().map(Vec::len).max().unwrap_or_default();
+                    let mut per_target = per_target.into_iter().map(Vec::into_iter).collect::<Vec<_>>();
+                    (0..rounds).flat_map(|_| per_target.iter_mut().filter_map(Iterator::next).collect::<Vec<_>>()).collect()
+                }
+                Schedule::Random => {
+                    let mut points = per_target.into_iter().flatten().collect::<Vec<_>>();
+                    points.shuffle(&mut rng);
+                    points
+                }
+            };
+            for (position, point) in iteration_points.iter_mut().enumerate() {
+                point.position = position;
+            }
+            points.extend(iteration_points);
+        }
+        ExperimentPlan { points, schedule, seed, pause: config.experiment_interval }
+    }
+
+    /// Writes the plan in the order of the schedule to a CSV file, one row per test, to record which order
+    /// the results were measured in.
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(["Schedule", "Seed", "Iteration", "Position", "Point", "Server Name", "Server Address", "Speedtest-Mode", "Payload Size", "Bitrate", "Interval", "Duration"])?;
+        let seconds = |value: Option<Duration>| value.map(|value| value.as_secs_f64().to_string()).unwrap_or_default();
+        for point in &self.points {
+            wtr.write_record([
+                self.schedule.name().to_string(),
+                self.seed.map(|seed| seed.to_string()).unwrap_or_default(),
+                point.iteration.to_string(),
+                point.position.to_string(),
+                point.id(),
+                point.target.name.clone(),
+                point.target.address.clone(),
+                point.mode.to_string().into(),
+                point.payload_size.to_string(),
+                point.bitrate.map(|bitrate| bitrate.to_string()).unwrap_or_default(),
+                seconds(point.interval),
+                seconds(point.duration),
+            ])?;
+        }
+        wtr.flush()
+    }
+
+    /// Estimated time to run the plan: the test durations and the pauses between the tests. Tests in
+    /// `packet_count` mode have no fixed duration and are not included, see
+    /// [`ExperimentPlan::unestimated_points`].
+    pub fn estimated_duration(&self) -> Duration {
+        self.points.iter().filter_map(|point| point.duration).sum::<Duration>() + self.pause * self.points.len() as u32
+    }
+
+    /// Number of tests without a fixed duration.
+    pub fn unestimated_points(&self) -> usize {
+        self.points.iter().filter(|point| point.duration.is_none()).count()
+    }
+}
+
+/// The values of a swept parameter, a single `None` if the mode doesn't use it.
+fn sweep<T: Copy>(values: &[T], used: bool) -> Vec<Option<T>> {
+    if used {
+        values.iter().copied().map(Some).collect()
+    } else {
+        vec![None]
+    }
+}
+
+impl fmt::Display for ExperimentPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = |value: Option<Duration>| value.map_or("-".to_string(), |value| format!("{} s", value.as_secs_f64()));
+        match self.seed {
+            Some(seed) => writeln!(f, "Schedule: {} (seed {})", self.schedule.name(), seed)?,
+            None => writeln!(f, "Schedule: {}", self.schedule.name())?,
+        }
+        writeln!(f, "Iteration | Target | Address | Mode | Payload Size | Bitrate | Interval | Duration")?;
+        for point in &self.points {
+            writeln!(
+                f,
+                "{} | {} | {} | {} | {} B | {} | {} | {}",
+                point.iteration,
+                point.target.name,
+                point.target.address,
+                point.mode.to_string(),
+                point.payload_size,
+                point.bitrate.map_or("-".to_string(), |bitrate| format!("{} bps", bitrate)),
+                seconds(point.interval),
+                seconds(point.duration)
+            )?;
+        }
+        let total = self.estimated_duration().as_secs();
+        write!(f, "{} tests, estimated total time {}:{:02}:{:02}", self.points.len(), total / 3600, total % 3600 / 60, total % 60)?;
+        if self.unestimated_points() > 0 {
+            write!(f, " plus {} packet count tests", self.unestimated_points())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,343 @@
+use log::{debug, error, info};
+use std::io;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use crate::config::ServerConfig;
+use crate::transport::{Clock, Network, SystemNetwork, ThreadRole, ThreadToken, Transport};
+use crate::udp_application::{UDPApplication, UDPApplicationEnum};
+use threadpool::ThreadPool;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Impairment the server applies to every response to simulate real-world network conditions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QosProfile {
+    Default,
+    /// Random delay of up to the given milliseconds.
+    Jitter(u64),
+    /// Fixed delay in milliseconds.
+    Delay(u64),
+    /// Drops the given percentage of responses.
+    Loss(u64),
+    /// Sends the given percentage of responses twice.
+    Duplicate(u64),
+    /// Delays the given percentage of responses by up to `reorder_delay` milliseconds.
+    Reorder { reorder: u64, reorder_delay: u64 },
+}
+
+impl QosProfile {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        match config.qos_profile.as_str() {
+            "jitter" => QosProfile::Jitter(config.jitter),
+            "delay" => QosProfile::Delay(config.delay),
+            "loss" => QosProfile::Loss(config.loss),
+            "duplicate" => QosProfile::Duplicate(config.duplicate),
+            "reorder" => QosProfile::Reorder { reorder: config.reorder, reorder_delay: config.reorder_delay },
+            _ => QosProfile::Default,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            QosProfile::Default => "Default",
+            QosProfile::Jitter(_) => "Jitter",
+            QosProfile::Delay(_) => "Delay",
+            QosProfile::Loss(_) => "Loss",
+            QosProfile::Duplicate(_) => "Duplicate",
+            QosProfile::Reorder { .. } => "Reorder",
+        }
+    }
+}
+
+/// Builder for a [`Server`].
+#[derive(Clone)]
+pub struct ServerBuilder {
+    address: String,
+    qos_profile: QosProfile,
+    threads: usize,
+    seed: Option<u64>,
+    network: Arc<dyn Network>,
+}
+
+impl fmt::Debug for ServerBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerBuilder")
+            .field("address", &self.address)
+            .field("qos_profile", &self.qos_profile)
+            .field("threads", &self.threads)
+            .field("seed", &self.seed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        ServerBuilder {
+            address: "0.0.0.0:8080".to_string(),
+            qos_profile: QosProfile::Default,
+            threads: 4, // Adjust the number of threads as needed
+            seed: None,
+            network: Arc::new(SystemNetwork),
+        }
+    }
+}
+
+impl ServerBuilder {
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.address = address.into();
+        self
+    }
+
+    pub fn qos_profile(mut self, qos_profile: QosProfile) -> Self {
+        self.qos_profile = qos_profile;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Seed of the impairment RNG. All workers share one stream and draw from it in the order the requests
+    /// are received, so a seed replays the same impairments with any number of threads.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Network the server listens on, the host's UDP stack by default.
+    ///
+    /// A [`SimNetwork`](crate::sim::SimNetwork) requires a single worker thread.
+    pub fn network(mut self, network: Arc<dyn Network>) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Binds the server socket.
+    pub fn build(self) -> io::Result<Server> {
+        let socket = self.network.bind(&self.address)?;
+        // Wake up regularly so the worker threads notice a shutdown request.
+        socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+        info!("Server bound to {}", socket.local_addr()?);
+        let seed = self.seed.unwrap_or_else(rand::random);
+        info!("Server RNG seed: {}", seed);
+        Ok(Server {
+            socket: Arc::new(Mutex::new(socket)),
+            clock: self.network.clock(),
+            pool: ThreadPool::new(self.threads),
+            qos_profile: self.qos_profile,
+            seed,
+            running: Arc::new(AtomicBool::new(true)),
+        })
+    }
+}
+
+/// UDP echo server answering every request with a response carrying the same session ID and payload.
+pub struct Server {
+    socket: Arc<Mutex<Box<dyn Transport>>>,
+    clock: Arc<dyn Clock>,
+    pool: ThreadPool,
+    qos_profile: QosProfile,
+    seed: u64,
+    running: Arc<AtomicBool>,
+}
+
+impl Server {
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder::default()
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.lock().unwrap().local_addr()
+    }
+
+    pub fn qos_profile(&self) -> QosProfile {
+        self.qos_profile
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Serves requests until the server is stopped through a [`ServerHandle`].
+    pub fn run(&self) {
+        self.serve(self.register_workers());
+    }
+
+    /// Runs the server on a background thread.
+    pub fn spawn(self) -> io::Result<ServerHandle> {
+        let local_addr = self.local_addr()?;
+        let seed = self.seed;
+        let running = Arc::clone(&self.running);
+        // Register the workers right away, a simulated client must not run ahead of them
+        let workers = self.register_workers();
+        let thread = thread::spawn(move || self.serve(workers));
+        Ok(ServerHandle { local_addr, seed, running, thread: Some(thread) })
+    }
+
+    fn register_workers(&self) -> Vec<(core_affinity::CoreId, ThreadToken)> {
+        let cores = core_affinity::get_core_ids().expect("Couldn't get core IDs");
+        cores
+            .into_iter()
+            .take(self.pool.max_count())
+            .map(|core_id| (core_id, self.clock.register_thread(ThreadRole::Background)))
+            .collect()
+    }
+
+    fn serve(&self, workers: Vec<(core_affinity::CoreId, ThreadToken)>) {
+        info!("Server type: {}", self.qos_profile.name());
+
+        let rng = Arc::new(Mutex::new(StdRng::seed_from_u64(self.seed)));
+        for (core_id, token) in workers {
+            let socket = Arc::clone(&self.socket);
+            let running = Arc::clone(&self.running);
+            let qos_profile = self.qos_profile;
+            let clock = Arc::clone(&self.clock);
+            let seed = self.seed;
+            let rng = Arc::clone(&rng);
+
+            self.pool.execute(move || {
+                let _guard = token.enter();
+                core_affinity::set_for_current(core_id);
+                handle_client(&socket, clock.as_ref(), &running, qos_profile, seed, &rng);
+            });
+        }
+
+        // Wait for all threads to finish, i.e. until the server is stopped
+        self.pool.join();
+    }
+}
+
+/// Handle to a server started with [`Server::spawn`]; stops the server when dropped.
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    seed: u64,
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Stops the server and waits for its worker threads to finish.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn send_response(socket: &dyn Transport, response_bytes: &[u8], addr: SocketAddr) {
+    if let Err(e) = socket.send_to(response_bytes, addr) {
+        error!("Failed to send response: {}", e);
+    } else {
+        debug!("Sent response to {}", addr);
+    }
+}
+
+fn handle_client(socket: &Mutex<Box<dyn Transport>>, clock: &dyn Clock, running: &AtomicBool, qos_profile: QosProfile, seed: u64, rng: &Mutex<StdRng>) {
+    let mut buf = [0; 131072];
+    while running.load(Ordering::Relaxed) {
+        let socket = socket.lock().unwrap();
+        match socket.recv_from(&mut buf) {
+            Ok((len, addr)) => {
+                let received_time = clock.now_micros();
+                debug!("Received {} bytes from {}", len, addr);
+                // Process the packet (this is where you can add your custom logic)
+                // For example, you can parse the packet and send a response
+                let request_packet = UDPApplication::from_bytes(&buf[..len]);
+                if request_packet.type_field == UDPApplicationEnum::INFO as u16 {
+                    let info_packet = UDPApplication {
+                        type_field: UDPApplicationEnum::SEED as u16,
+                        session_id: request_packet.session_id,
+                        test_payload: seed.to_be_bytes().to_vec(),
+                    };
+                    send_response(socket.as_ref(), &info_packet.to_bytes(), addr);
+                    continue;
+                }
+                let mut response_packet = UDPApplication {
+                    type_field: UDPApplicationEnum::RESPONSE as u16,
+                    session_id: request_packet.session_id, // Session ID can be set as needed
+                    test_payload: request_packet.test_payload,
+                };
+                let mut duplicate = false;
+                // Draw while holding the socket, so the draws follow the order the requests were received in
+                let mut rng = rng.lock().unwrap();
+                match qos_profile {
+                    QosProfile::Default => {}
+                    QosProfile::Jitter(jitter) => {
+                        if jitter > 0 {
+                            let jitter = rng.gen_range(0..jitter);
+                            clock.sleep(Duration::from_millis(jitter));
+                        }
+                    }
+                    QosProfile::Delay(delay) => {
+                        clock.sleep(Duration::from_millis(delay));
+                    }
+                    QosProfile::Loss(loss) => {
+                        let loss_ran = rng.gen_range(0..100);
+                        if loss_ran < loss {
+                            debug!("Dropping packet");
+                            continue;
+                        }
+                    }
+                    QosProfile::Duplicate(percentage) => {
+                        let duplicate_ran = rng.gen_range(0..100);
+                        duplicate = duplicate_ran < percentage;
+                    }
+                    QosProfile::Reorder { reorder, reorder_delay } => {
+                        let reorder_ran = rng.gen_range(0..100);
+                        if reorder_ran < reorder && reorder_delay > 0 {
+                            clock.sleep(Duration::from_millis(rng.gen_range(0..reorder_delay)));
+                        }
+                    }
+                }
+                drop(rng);
+                if request_packet.type_field == UDPApplicationEnum::TIMED_REQUEST as u16 {
+                    response_packet.set_server_timestamps(received_time, clock.now_micros());
+                }
+                let response_bytes = response_packet.to_bytes();
+                if duplicate {
+                    send_response(socket.as_ref(), &response_bytes, addr);
+                }
+                send_response(socket.as_ref(), &response_bytes, addr);
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {}
+            Err(e) => {
+                error!("Failed to receive data: {}", e);
+            }
+        }
+    }
+}
+
+pub fn run(config: &ServerConfig) -> io::Result<()> {
+    let mut builder = Server::builder()
+        .address(config.address.as_str())
+        .qos_profile(QosProfile::from_config(config));
+    if let Some(seed) = config.seed {
+        builder = builder.seed(seed);
+    }
+    builder.build()?.run();
+    Ok(())
+}
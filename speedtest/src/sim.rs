@@ -0,0 +1,384 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::transport::{Clock, Network, ThreadGuard, ThreadRole, ThreadToken, Transport};
+
+/// Virtual time a simulation starts at; timestamps must not be zero as the client uses zero for missing ones.
+const SIM_EPOCH: Duration = Duration::from_secs(1_600_000_000);
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+
+/// Properties of the simulated path between any two sockets, applied per direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkConfig {
+    /// One-way propagation delay.
+    pub delay: Duration,
+    /// Random extra one-way delay of up to the given duration.
+    pub jitter: Duration,
+    /// Percentage of packets dropped.
+    pub loss: f64,
+    /// Bandwidth in bits per second the sender is blocked for, 0 for an unlimited link.
+    pub bandwidth: u64,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        LinkConfig {
+            delay: Duration::ZERO,
+            jitter: Duration::ZERO,
+            loss: 0.0,
+            bandwidth: 1_000_000_000,
+        }
+    }
+}
+
+enum Waiter {
+    Sleep,
+    Recv(u16),
+}
+
+struct Datagram {
+    from: SocketAddr,
+    to_port: u16,
+    bytes: Vec<u8>,
+}
+
+#[derive(Default)]
+struct SimSocket {
+    queue: VecDeque<(SocketAddr, Vec<u8>)>,
+    waiters: BTreeSet<u64>,
+}
+
+struct SimState {
+    /// Virtual time in nanoseconds since the UNIX epoch.
+    now: u128,
+    link: LinkConfig,
+    /// Ports that drop all packets from and to them between two times in nanoseconds since the UNIX epoch.
+    outages: Vec<(u16, u128, u128)>,
+    rng: StdRng,
+    next_seq: u64,
+    next_port: u16,
+    foreground: usize,
+    /// Registered threads that are currently not waiting on the simulation.
+    running: usize,
+    waiters: HashMap<u64, (Option<u128>, Waiter)>,
+    timers: BTreeSet<(u128, u64)>,
+    deliveries: BTreeMap<(u128, u64), Datagram>,
+    sockets: HashMap<u16, SimSocket>,
+    woken: HashSet<u64>,
+}
+
+impl SimState {
+    fn next_seq(&mut self) -> u64 {
+        self.next_seq += 1;
+        self.next_seq
+    }
+
+    fn add_waiter(&mut self, deadline: Option<u128>, waiter: Waiter) -> u64 {
+        let seq = self.next_seq();
+        if let Some(deadline) = deadline {
+            self.timers.insert((deadline, seq));
+        }
+        if let Waiter::Recv(port) = waiter {
+            self.sockets.entry(port).or_default().waiters.insert(seq);
+        }
+        self.waiters.insert(seq, (deadline, waiter));
+        seq
+    }
+
+    /// Removes a waiter and counts its thread as running again.
+    fn remove_waiter(&mut self, seq: u64) -> bool {
+        match self.waiters.remove(&seq) {
+            Some((deadline, waiter)) => {
+                if let Some(deadline) = deadline {
+                    self.timers.remove(&(deadline, seq));
+                }
+                if let Waiter::Recv(port) = waiter {
+                    if let Some(socket) = self.sockets.get_mut(&port) {
+                        socket.waiters.remove(&seq);
+                    }
+                }
+                self.running += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn wake(&mut self, seq: u64) {
+        if self.remove_waiter(seq) {
+            self.woken.insert(seq);
+        }
+    }
+
+    /// Processes events in time order until a thread is woken up. Only one registered thread runs at a time,
+    /// which keeps a simulation deterministic.
+    fn advance(&mut self) {
+        while self.running == 0 && self.foreground > 0 {
+            let next_delivery = self.deliveries.keys().next().copied();
+            let next_timer = self.timers.iter().next().copied();
+            // Packets arriving at the same time as a timeout are delivered first
+            let deliver_first = match (next_delivery, next_timer) {
+                (Some(delivery), Some(timer)) => delivery.0 <= timer.0,
+                (delivery, _) => delivery.is_some(),
+            };
+            if let (true, Some(key)) = (deliver_first, next_delivery) {
+                let datagram = self.deliveries.remove(&key).unwrap();
+                self.now = self.now.max(key.0);
+                if let Some(socket) = self.sockets.get_mut(&datagram.to_port) {
+                    socket.queue.push_back((datagram.from, datagram.bytes));
+                    if let Some(&waiter) = socket.waiters.iter().next() {
+                        self.wake(waiter);
+                    }
+                }
+            } else if let Some((deadline, seq)) = next_timer {
+                self.now = self.now.max(deadline);
+                self.wake(seq);
+            } else {
+                panic!("Simulation deadlock: all threads wait without a timeout");
+            }
+        }
+    }
+}
+
+struct Shared {
+    state: Mutex<SimState>,
+    wakeup: Condvar,
+}
+
+impl Shared {
+    /// Marks the calling thread as waiting and lets the simulation proceed.
+    fn suspend(&self, state: &mut SimState) {
+        state.running -= 1;
+        state.advance();
+        self.wakeup.notify_all();
+    }
+
+    /// Waits until `seq` is woken up. Without foreground threads virtual time stands still, so background
+    /// threads give up after `real_timeout` to stay responsive, e.g. to a server shutdown.
+    fn wait_for<'a>(&'a self, mut state: MutexGuard<'a, SimState>, seq: u64, real_timeout: Option<Duration>) -> (MutexGuard<'a, SimState>, bool) {
+        let real_deadline = real_timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            if state.woken.remove(&seq) {
+                return (state, true);
+            }
+            match real_deadline {
+                Some(deadline) if state.foreground == 0 => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        state.remove_waiter(seq);
+                        return (state, false);
+                    }
+                    state = self.wakeup.wait_timeout(state, deadline - now).unwrap().0;
+                }
+                _ => state = self.wakeup.wait(state).unwrap(),
+            }
+        }
+    }
+
+    fn sleep_nanos<'a>(&'a self, mut state: MutexGuard<'a, SimState>, nanos: u128) -> MutexGuard<'a, SimState> {
+        let deadline = state.now + nanos;
+        let seq = state.add_waiter(Some(deadline), Waiter::Sleep);
+        self.suspend(&mut state);
+        self.wait_for(state, seq, Some(Duration::from_nanos(nanos as u64))).0
+    }
+}
+
+/// In-memory network with a virtual clock, seeded randomness and configurable delay and loss.
+///
+/// All sockets live on one virtual host and are identified by their port. Every thread that uses the
+/// simulation must be registered through [`Clock::register_thread`], which the client and server do for
+/// their worker threads. Virtual time only advances while all registered threads wait, so a test runs as
+/// fast as the host allows and its results only depend on the seed. Servers must run with a single worker
+/// thread, as their workers serialize on the socket outside of the simulation's control.
+#[derive(Clone)]
+pub struct SimNetwork {
+    shared: Arc<Shared>,
+}
+
+impl SimNetwork {
+    pub fn new(seed: u64) -> Self {
+        SimNetwork {
+            shared: Arc::new(Shared {
+                state: Mutex::new(SimState {
+                    now: SIM_EPOCH.as_nanos(),
+                    link: LinkConfig::default(),
+                    outages: Vec::new(),
+                    rng: StdRng::seed_from_u64(seed),
+                    next_seq: 0,
+                    next_port: FIRST_EPHEMERAL_PORT,
+                    foreground: 0,
+                    running: 0,
+                    waiters: HashMap::new(),
+                    timers: BTreeSet::new(),
+                    deliveries: BTreeMap::new(),
+                    sockets: HashMap::new(),
+                    woken: HashSet::new(),
+                }),
+                wakeup: Condvar::new(),
+            }),
+        }
+    }
+
+    pub fn with_link(self, link: LinkConfig) -> Self {
+        self.set_link(link);
+        self
+    }
+
+    /// Changes the path properties for all packets sent from now on.
+    pub fn set_link(&self, link: LinkConfig) {
+        self.shared.state.lock().unwrap().link = link;
+    }
+
+    /// Makes the socket at `port` unreachable from `start` until `end` after the start of the simulation, e.g.
+    /// to take down a server: packets sent from or to it in that time are dropped.
+    pub fn with_outage(self, port: u16, start: Duration, end: Duration) -> Self {
+        let epoch = SIM_EPOCH.as_nanos();
+        self.shared.state.lock().unwrap().outages.push((port, epoch + start.as_nanos(), epoch.saturating_add(end.as_nanos())));
+        self
+    }
+
+    /// Virtual time elapsed since the start of the simulation.
+    pub fn elapsed(&self) -> Duration {
+        let now = self.shared.state.lock().unwrap().now;
+        Duration::from_nanos((now - SIM_EPOCH.as_nanos()) as u64)
+    }
+}
+
+impl Network for SimNetwork {
+    fn bind(&self, addr: &str) -> io::Result<Box<dyn Transport>> {
+        let addr = addr.to_socket_addrs()?.next().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to bind to"))?;
+        let mut state = self.shared.state.lock().unwrap();
+        let port = if addr.port() == 0 {
+            while state.sockets.contains_key(&state.next_port) {
+                state.next_port = state.next_port.checked_add(1).unwrap_or(FIRST_EPHEMERAL_PORT);
+            }
+            state.next_port
+        } else if state.sockets.contains_key(&addr.port()) {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, "address already in use"));
+        } else {
+            addr.port()
+        };
+        state.sockets.insert(port, SimSocket::default());
+        let ip = if addr.ip().is_unspecified() { IpAddr::V4(Ipv4Addr::LOCALHOST) } else { addr.ip() };
+        Ok(Box::new(SimTransport {
+            shared: Arc::clone(&self.shared),
+            addr: SocketAddr::new(ip, port),
+            read_timeout: Mutex::new(None),
+        }))
+    }
+
+    fn clock(&self) -> Arc<dyn Clock> {
+        Arc::new(SimClock { shared: Arc::clone(&self.shared) })
+    }
+}
+
+struct SimClock {
+    shared: Arc<Shared>,
+}
+
+impl Clock for SimClock {
+    fn now_micros(&self) -> u128 {
+        self.shared.state.lock().unwrap().now / 1000
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let state = self.shared.state.lock().unwrap();
+        drop(self.shared.sleep_nanos(state, duration.as_nanos()));
+    }
+
+    fn register_thread(&self, role: ThreadRole) -> ThreadToken {
+        let mut state = self.shared.state.lock().unwrap();
+        // The new thread waits for its turn like a sleeper that is due immediately.
+        let now = state.now;
+        let seq = state.add_waiter(Some(now), Waiter::Sleep);
+        if role == ThreadRole::Foreground {
+            state.foreground += 1;
+        }
+        state.advance();
+        self.shared.wakeup.notify_all();
+        drop(state);
+
+        let shared = Arc::clone(&self.shared);
+        ThreadToken::new(move || {
+            let state = shared.state.lock().unwrap();
+            drop(shared.wait_for(state, seq, Some(Duration::ZERO)));
+            ThreadGuard::new(move || {
+                let mut state = shared.state.lock().unwrap();
+                if role == ThreadRole::Foreground {
+                    state.foreground -= 1;
+                }
+                shared.suspend(&mut state);
+            })
+        })
+    }
+}
+
+struct SimTransport {
+    shared: Arc<Shared>,
+    addr: SocketAddr,
+    read_timeout: Mutex<Option<Duration>>,
+}
+
+impl Transport for SimTransport {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        let mut state = self.shared.state.lock().unwrap();
+        let link = state.link;
+        let transmission = if link.bandwidth > 0 {
+            (buf.len() as u128 * 8 * 1_000_000_000 / link.bandwidth as u128).max(1)
+        } else {
+            0
+        };
+        let now = state.now;
+        let unreachable = state.outages.iter().any(|&(port, start, end)| (port == addr.port() || port == self.addr.port()) && (start..end).contains(&now));
+        let lost = link.loss > 0.0 && state.rng.gen::<f64>() * 100.0 < link.loss || unreachable;
+        let jitter = if link.jitter > Duration::ZERO { state.rng.gen_range(0..link.jitter.as_nanos()) } else { 0 };
+        if !lost {
+            let arrival = state.now + transmission + link.delay.as_nanos() + jitter;
+            let seq = state.next_seq();
+            state.deliveries.insert((arrival, seq), Datagram { from: self.addr, to_port: addr.port(), bytes: buf.to_vec() });
+        }
+        if transmission > 0 {
+            drop(self.shared.sleep_nanos(state, transmission));
+        }
+        Ok(buf.len())
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let read_timeout = *self.read_timeout.lock().unwrap();
+        let port = self.addr.port();
+        let mut state = self.shared.state.lock().unwrap();
+        if state.sockets.get(&port).is_none_or(|socket| socket.queue.is_empty()) {
+            let deadline = read_timeout.map(|timeout| state.now + timeout.as_nanos());
+            let seq = state.add_waiter(deadline, Waiter::Recv(port));
+            self.shared.suspend(&mut state);
+            state = self.shared.wait_for(state, seq, read_timeout).0;
+        }
+        match state.sockets.get_mut(&port).and_then(|socket| socket.queue.pop_front()) {
+            Some((from, bytes)) => {
+                let len = bytes.len().min(buf.len());
+                buf[..len].copy_from_slice(&bytes[..len]);
+                Ok((len, from))
+            }
+            None => Err(io::Error::new(io::ErrorKind::WouldBlock, "receive timed out")),
+        }
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        *self.read_timeout.lock().unwrap() = timeout;
+        Ok(())
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.addr)
+    }
+}
+
+impl Drop for SimTransport {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().sockets.remove(&self.addr.port());
+    }
+}
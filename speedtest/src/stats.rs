@@ -0,0 +1,136 @@
+use crate::histogram::LatencyHistogram;
+
+/// Percentiles reported when the configuration doesn't list any.
+pub const DEFAULT_PERCENTILES: [f64; 5] = [50.0, 90.0, 99.0, 99.9, 99.99];
+
+/// Arithmetic mean, `None` for an empty sample set.
+pub fn mean(samples: &[u128]) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+    Some(samples.iter().sum::<u128>() as f64 / samples.len() as f64)
+}
+
+/// Population variance, `None` for an empty sample set.
+pub fn variance(samples: &[u128]) -> Option<f64> {
+    let mean = mean(samples)?;
+    Some(samples.iter().map(|sample| (*sample as f64 - mean).powi(2)).sum::<f64>() / samples.len() as f64)
+}
+
+pub fn stddev(samples: &[u128]) -> Option<f64> {
+    variance(samples).map(f64::sqrt)
+}
+
+/// 1-based nearest rank of `percentile` (in percent) among `count` values, ignoring the rounding error of
+/// decimal percentiles like 99.9.
+pub fn rank(percentile: f64, count: u64) -> u64 {
+    let exact = percentile.clamp(0.0, 100.0) / 100.0 * count as f64;
+    ((exact - 1e-9 * exact.max(1.0)).ceil() as u64).clamp(1, count.max(1))
+}
+
+/// Nearest-rank `percentile` (in percent, e.g. 99.9) of samples sorted in ascending order.
+pub fn percentile(sorted: &[u128], percentile: f64) -> Option<u128> {
+    if sorted.is_empty() {
+        return None;
+    }
+    Some(sorted[rank(percentile, sorted.len() as u64) as usize - 1])
+}
+
+/// Column name of a percentile in the result files, e.g. `99.9th Percentile RTT`.
+pub fn percentile_column(percentile: f64) -> String {
+    format!("{}th Percentile RTT", percentile)
+}
+
+/// Summary of the RTTs of a test in microseconds, computed over the answered packets only.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RttSummary {
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub median: u64,
+    pub variance: f64,
+    pub stddev: f64,
+    /// Requested percentiles in percent with their values.
+    pub percentiles: Vec<(f64, u64)>,
+}
+
+impl RttSummary {
+    /// Summarizes `histogram`, `None` if nothing was recorded.
+    pub fn from_histogram(histogram: &LatencyHistogram, percentiles: &[f64]) -> Option<Self> {
+        Some(RttSummary {
+            count: histogram.len(),
+            min: histogram.min()?,
+            max: histogram.max()?,
+            mean: histogram.mean()?,
+            median: histogram.value_at_percentile(50.0)?,
+            variance: histogram.variance()?,
+            stddev: histogram.stddev()?,
+            percentiles: percentiles.iter().map(|p| histogram.value_at_percentile(*p).map(|value| (*p, value))).collect::<Option<_>>()?,
+        })
+    }
+
+    /// Summarizes the exact `samples`, `None` for an empty sample set.
+    pub fn from_samples(samples: &[u128], percentiles: &[f64]) -> Option<Self> {
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        Some(RttSummary {
+            count: sorted.len() as u64,
+            min: *sorted.first()? as u64,
+            max: *sorted.last()? as u64,
+            mean: mean(&sorted)?,
+            median: percentile(&sorted, 50.0)? as u64,
+            variance: variance(&sorted)?,
+            stddev: stddev(&sorted)?,
+            percentiles: percentiles.iter().map(|p| percentile(&sorted, *p).map(|value| (*p, value as u64))).collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// Two-sided 97.5% quantiles of Student's t-distribution for 1 to 30 degrees of freedom.
+const T_975: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069,
+    2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+];
+
+/// 97.5% quantile of Student's t-distribution, approximated as `1.96 + 2.4 / df` beyond 30 degrees of freedom
+/// (off by less than 0.005).
+pub fn t_quantile_975(degrees_of_freedom: usize) -> f64 {
+    match degrees_of_freedom {
+        0 => f64::NAN,
+        df if df <= T_975.len() => T_975[df - 1],
+        df => 1.96 + 2.4 / df as f64,
+    }
+}
+
+/// Summary of one metric across the runs of an experiment, e.g. the average RTT of every iteration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleSummary {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    /// Sample standard deviation, `None` for a single value.
+    pub stddev: Option<f64>,
+    /// 95% confidence interval of the mean from Student's t-distribution, `None` for a single value.
+    pub ci95: Option<(f64, f64)>,
+}
+
+impl SampleSummary {
+    /// Summarizes `values`, `None` if there are none.
+    pub fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let count = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / count as f64;
+        let median = if count % 2 == 1 { sorted[count / 2] } else { (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0 };
+        let stddev = (count > 1).then(|| (sorted.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt());
+        let ci95 = stddev.map(|stddev| {
+            let half_width = t_quantile_975(count - 1) * stddev / (count as f64).sqrt();
+            (mean - half_width, mean + half_width)
+        });
+        Some(SampleSummary { count, mean, median, stddev, ci95 })
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use log::debug;
+use crate::client::ServerTimestamps;
+use crate::events::Event;
+
+/// Timestamp of a packet, streamed from the sender and receiver threads to a [`TimelineWriter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimelineEntry {
+    Sent { session_id: u16, time: u128 },
+    Received { session_id: u16, time: u128, server_timestamps: Option<ServerTimestamps> },
+}
+
+impl TimelineEntry {
+    pub fn time(&self) -> u128 {
+        match self {
+            TimelineEntry::Sent { time, .. } | TimelineEntry::Received { time, .. } => *time,
+        }
+    }
+}
+
+/// Fate of a probe or a response, as far as it is known while the test is running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketStatus {
+    /// First response to the probe, in order.
+    Ok,
+    /// First response to the probe, after a response to a later probe.
+    Reordered,
+    /// Another response to an answered probe.
+    Duplicate,
+    /// No response within the loss timeout.
+    Lost,
+    /// Response to a probe that already counted as lost.
+    Late,
+    /// Response without a matching probe.
+    Unsolicited,
+}
+
+impl PacketStatus {
+    pub fn name(&self) -> &str {
+        match self {
+            PacketStatus::Ok => "ok",
+            PacketStatus::Reordered => "reordered",
+            PacketStatus::Duplicate => "dup",
+            PacketStatus::Lost => "lost",
+            PacketStatus::Late => "late",
+            PacketStatus::Unsolicited => "unsolicited",
+        }
+    }
+
+    /// Whether this is the first response to the probe.
+    pub fn is_answer(&self) -> bool {
+        matches!(self, PacketStatus::Ok | PacketStatus::Reordered | PacketStatus::Late)
+    }
+}
+
+/// A probe whose fate became known, or a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketRecord {
+    /// Position of the probe in the order of sending, `None` for unsolicited responses.
+    pub sequence: Option<u64>,
+    pub session_id: u16,
+    pub sent: Option<u128>,
+    pub server_timestamps: Option<ServerTimestamps>,
+    pub received: Option<u128>,
+    pub status: PacketStatus,
+}
+
+impl PacketRecord {
+    /// Round-trip time in microseconds of an answer.
+    pub fn rtt(&self) -> Option<u128> {
+        match (self.sent, self.received) {
+            (Some(sent), Some(received)) if self.status.is_answer() => Some(received.saturating_sub(sent)),
+            _ => None,
+        }
+    }
+}
+
+/// A probe that left the [`ProbeTracker`] after the loss timeout or at the end of the test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetiredProbe {
+    pub sequence: u64,
+    pub sent: u128,
+    /// Round-trip time of its first answer within the loss timeout, `None` if it counted as lost.
+    pub rtt: Option<u128>,
+}
+
+/// Matches responses to probes while a test is running, in the order the timestamps were taken.
+///
+/// Session IDs wrap around in long tests, so a response belongs to the latest probe sent with its ID.
+/// A probe counts as lost once it has been unanswered for the loss timeout.
+#[derive(Debug, Clone)]
+pub struct ProbeTracker {
+    loss_timeout: u128,
+    next_sequence: u64,
+    /// RFC 4737 NextExp: the sequence number after the latest in-order response.
+    next_expected: u64,
+    /// Latest probe sent with every session ID.
+    sequences: HashMap<u16, u64>,
+    /// Probes within the loss timeout, by sequence number.
+    pending: BTreeMap<u64, Probe>,
+    /// Send time of the probes that counted as lost, as long as no later probe reused their session ID.
+    lost: HashMap<u64, u128>,
+    /// Probes that left the tracker in the order they were sent, if they are kept, see [`Self::retiring`].
+    retired: Option<Vec<RetiredProbe>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Probe {
+    session_id: u16,
+    sent: u128,
+    /// Receive time of the first answer.
+    answered: Option<u128>,
+}
+
+impl ProbeTracker {
+    pub fn new(loss_timeout: Duration) -> Self {
+        ProbeTracker {
+            loss_timeout: loss_timeout.as_micros(),
+            next_sequence: 0,
+            next_expected: 0,
+            sequences: HashMap::new(),
+            pending: BTreeMap::new(),
+            lost: HashMap::new(),
+            retired: None,
+        }
+    }
+
+    /// Also keeps the probes leaving the tracker for [`Self::take_retired`].
+    pub fn retiring(mut self) -> Self {
+        self.retired = Some(Vec::new());
+        self
+    }
+
+    /// The probes that left the tracker since the last call, in the order they were sent.
+    pub fn take_retired(&mut self) -> Vec<RetiredProbe> {
+        self.retired.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Tracks `entry` and returns the probes that timed out before it, followed by the response if it is one.
+    pub fn track(&mut self, entry: &TimelineEntry) -> Vec<PacketRecord> {
+        let mut records = self.expire(entry.time());
+        match *entry {
+            TimelineEntry::Sent { session_id, time } => {
+                let sequence = self.next_sequence;
+                self.next_sequence += 1;
+                if let Some(previous) = self.sequences.insert(session_id, sequence) {
+                    // Responses with this ID belong to the new probe from now on
+                    self.lost.remove(&previous);
+                }
+                self.pending.insert(sequence, Probe { session_id, sent: time, answered: None });
+            }
+            TimelineEntry::Received { session_id, time, server_timestamps } => records.push(self.received(session_id, time, server_timestamps)),
+        }
+        records
+    }
+
+    /// The probes that are still unanswered, as lost.
+    pub fn finish(&mut self) -> Vec<PacketRecord> {
+        let pending = std::mem::take(&mut self.pending);
+        pending.into_iter().filter_map(|(sequence, probe)| self.retire(sequence, probe)).collect()
+    }
+
+    fn received(&mut self, session_id: u16, time: u128, server_timestamps: Option<ServerTimestamps>) -> PacketRecord {
+        let mut record = PacketRecord { sequence: None, session_id, sent: None, server_timestamps, received: Some(time), status: PacketStatus::Unsolicited };
+        let sequence = match self.sequences.get(&session_id) {
+            Some(&sequence) => sequence,
+            None => return record,
+        };
+        record.sequence = Some(sequence);
+        record.sent = self.pending.get(&sequence).map(|probe| probe.sent);
+        record.status = match self.pending.get_mut(&sequence) {
+            Some(probe) if probe.answered.is_none() => {
+                probe.answered = Some(time);
+                if sequence < self.next_expected {
+                    PacketStatus::Reordered
+                } else {
+                    self.next_expected = sequence + 1;
+                    PacketStatus::Ok
+                }
+            }
+            Some(_) => PacketStatus::Duplicate,
+            None => match self.lost.remove(&sequence) {
+                Some(sent) => {
+                    record.sent = Some(sent);
+                    PacketStatus::Late
+                }
+                None => PacketStatus::Duplicate,
+            },
+        };
+        record
+    }
+
+    /// Retires the probes sent more than the loss timeout before `now`, returning the unanswered ones.
+    fn expire(&mut self, now: u128) -> Vec<PacketRecord> {
+        let mut records = Vec::new();
+        while let Some((&sequence, &probe)) = self.pending.iter().next() {
+            if probe.sent + self.loss_timeout >= now {
+                break;
+            }
+            self.pending.remove(&sequence);
+            records.extend(self.retire(sequence, probe));
+        }
+        records
+    }
+
+    /// Removes a probe from the pending ones, returning it as lost if it is unanswered.
+    fn retire(&mut self, sequence: u64, probe: Probe) -> Option<PacketRecord> {
+        if let Some(retired) = &mut self.retired {
+            retired.push(RetiredProbe { sequence, sent: probe.sent, rtt: probe.answered.map(|received| received.saturating_sub(probe.sent)) });
+        }
+        if probe.answered.is_some() {
+            return None;
+        }
+        self.lost.insert(sequence, probe.sent);
+        Some(lost(sequence, &probe))
+    }
+}
+
+fn lost(sequence: u64, probe: &Probe) -> PacketRecord {
+    PacketRecord { sequence: Some(sequence), session_id: probe.session_id, sent: Some(probe.sent), server_timestamps: None, received: None, status: PacketStatus::Lost }
+}
+
+/// Writes one row per probe and per response of a test to a CSV file while the test is running.
+///
+/// Rows are written once a probe's fate is known, so they are ordered by that time rather than by
+/// sequence number. The status of a row is the [`PacketStatus`] name, or `event` for the events marked
+/// during the test, which are appended when the test is finished.
+pub struct TimelineWriter {
+    path: PathBuf,
+    sender: Sender<TimelineEntry>,
+    thread: JoinHandle<io::Result<Timeline>>,
+}
+
+impl TimelineWriter {
+    /// Creates the file at `path`. `stream` identifies the probe stream in every row, e.g. the server
+    /// address, and probes unanswered for `loss_timeout` are written as lost.
+    pub fn create(path: &Path, stream: &str, payload_size: usize, loss_timeout: Duration) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(["Stream", "Sequence", "Session ID", "Sent", "Server Received", "Server Sent", "Received", "Payload Size", "Status", "Label"])?;
+        debug!("Writing timeline to {}", path.display());
+        let timeline = Timeline { writer, stream: stream.to_string(), payload_size, tracker: ProbeTracker::new(loss_timeout) };
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || timeline.run(receiver));
+        Ok(TimelineWriter { path: path.to_path_buf(), sender, thread })
+    }
+
+    /// Sender for the packet timestamps; the timeline is complete once all senders are dropped.
+    pub fn sender(&self) -> Sender<TimelineEntry> {
+        self.sender.clone()
+    }
+
+    /// Writes the probes that are still unanswered as lost, appends `events` and closes the file.
+    ///
+    /// Blocks until every other sender of the timeline is dropped.
+    pub fn finish(self, events: &[Event]) -> io::Result<PathBuf> {
+        drop(self.sender);
+        let mut timeline = self.thread.join().map_err(|_| io::Error::other("timeline writer panicked"))??;
+        for record in timeline.tracker.finish() {
+            timeline.write(&record)?;
+        }
+        for event in events {
+            let stream = timeline.stream.clone();
+            timeline.writer.write_record([stream, String::new(), String::new(), String::new(), String::new(), String::new(), event.time.to_string(), String::new(), "event".to_string(), event.label.clone()])?;
+        }
+        timeline.writer.flush()?;
+        Ok(self.path)
+    }
+}
+
+struct Timeline {
+    writer: csv::Writer<File>,
+    stream: String,
+    payload_size: usize,
+    tracker: ProbeTracker,
+}
+
+impl Timeline {
+    fn run(mut self, receiver: Receiver<TimelineEntry>) -> io::Result<Self> {
+        loop {
+            let entry = match receiver.try_recv() {
+                Ok(entry) => entry,
+                Err(TryRecvError::Empty) => {
+                    // Caught up with the test, so make the rows so far visible
+                    self.writer.flush()?;
+                    match receiver.recv() {
+                        Ok(entry) => entry,
+                        Err(_) => return Ok(self),
+                    }
+                }
+                Err(TryRecvError::Disconnected) => return Ok(self),
+            };
+            for record in self.tracker.track(&entry) {
+                self.write(&record)?;
+            }
+        }
+    }
+
+    fn write(&mut self, record: &PacketRecord) -> io::Result<()> {
+        let cell = |value: Option<u128>| value.map(|value| value.to_string()).unwrap_or_default();
+        self.writer.write_record([
+            self.stream.clone(),
+            record.sequence.map(|sequence| sequence.to_string()).unwrap_or_default(),
+            record.session_id.to_string(),
+            cell(record.sent),
+            cell(record.server_timestamps.map(|(received, _)| received)),
+            cell(record.server_timestamps.map(|(_, sent)| sent)),
+            cell(record.received),
+            self.payload_size.to_string(),
+            record.status.name().to_string(),
+            String::new(),
+        ])?;
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Datagram socket the client and server send and receive their packets through.
+pub trait Transport: Send + Sync {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize>;
+    /// Receives one datagram, failing with `WouldBlock` or `TimedOut` once the read timeout expires.
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+impl Transport for UdpSocket {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        UdpSocket::send_to(self, buf, addr)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        UdpSocket::recv_from(self, buf)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UdpSocket::set_read_timeout(self, timeout)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        UdpSocket::local_addr(self)
+    }
+}
+
+/// Whether a thread keeps a simulation running (client threads) or only reacts to it (server threads).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThreadRole {
+    Foreground,
+    Background,
+}
+
+/// Registration of a thread with a [`Clock`], created before the thread is started.
+///
+/// The started thread must call [`ThreadToken::enter`] before using the clock or any transport.
+pub struct ThreadToken {
+    enter: Option<Box<dyn FnOnce() -> ThreadGuard + Send>>,
+}
+
+impl ThreadToken {
+    /// Token of a clock that doesn't need to track its threads.
+    pub fn detached() -> Self {
+        ThreadToken { enter: None }
+    }
+
+    pub fn new(enter: impl FnOnce() -> ThreadGuard + Send + 'static) -> Self {
+        ThreadToken { enter: Some(Box::new(enter)) }
+    }
+
+    /// Waits until the thread may run and returns a guard that unregisters it when dropped.
+    pub fn enter(self) -> ThreadGuard {
+        match self.enter {
+            Some(enter) => enter(),
+            None => ThreadGuard::detached(),
+        }
+    }
+}
+
+pub struct ThreadGuard {
+    exit: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl ThreadGuard {
+    pub fn detached() -> Self {
+        ThreadGuard { exit: None }
+    }
+
+    pub fn new(exit: impl FnOnce() + Send + 'static) -> Self {
+        ThreadGuard { exit: Some(Box::new(exit)) }
+    }
+}
+
+impl Drop for ThreadGuard {
+    fn drop(&mut self) {
+        if let Some(exit) = self.exit.take() {
+            exit();
+        }
+    }
+}
+
+/// Time source used for all timestamps, sleeps and test durations.
+pub trait Clock: Send + Sync {
+    /// Microseconds since the UNIX epoch.
+    fn now_micros(&self) -> u128;
+    fn sleep(&self, duration: Duration);
+
+    fn register_thread(&self, _role: ThreadRole) -> ThreadToken {
+        ThreadToken::detached()
+    }
+
+    fn elapsed_since(&self, start_micros: u128) -> Duration {
+        Duration::from_micros(self.now_micros().saturating_sub(start_micros) as u64)
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_micros(&self) -> u128 {
+        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).expect("get millis error").as_micros()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Provides the sockets and the clock a client or server runs on.
+pub trait Network: Send + Sync {
+    fn bind(&self, addr: &str) -> io::Result<Box<dyn Transport>>;
+    fn clock(&self) -> Arc<dyn Clock>;
+}
+
+/// The host's UDP stack and wall clock.
+pub struct SystemNetwork;
+
+impl Network for SystemNetwork {
+    fn bind(&self, addr: &str) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(UdpSocket::bind(addr)?))
+    }
+
+    fn clock(&self) -> Arc<dyn Clock> {
+        Arc::new(SystemClock)
+    }
+}
@@ -1,7 +1,19 @@
+use std::convert::TryInto;
 
 pub enum UDPApplicationEnum {
     REQUEST = 0,
     RESPONSE = 1,
+    /// Asks the server for its RNG seed.
+    INFO = 2,
+    /// Carries the server's RNG seed as a big-endian u64 payload.
+    SEED = 3,
+    /// Response to a `TIMED_REQUEST` whose first 16 payload bytes are replaced by the server's receive and
+    /// send time of the request, as big-endian u64 microseconds since the Unix epoch.
+    #[allow(non_camel_case_types)]
+    TIMED_RESPONSE = 4,
+    /// Request asking for a `TIMED_RESPONSE`. Servers without timestamp support answer it like a `REQUEST`.
+    #[allow(non_camel_case_types)]
+    TIMED_REQUEST = 5,
 }
 
 #[derive(Debug)]
@@ -11,7 +23,7 @@ pub struct UDPApplication {
     pub test_payload: Vec<u8>,
 }
 
-impl<'a> UDPApplication {
+impl UDPApplication {
 
     pub fn new(buf: &[u8]) -> Self {
         let type_field = u16::from_be_bytes([buf[0], buf[1]]);
@@ -46,12 +58,34 @@ impl<'a> UDPApplication {
         }
     }
 
+    /// Whether the packet is an answer to a request, with or without server timestamps.
+    pub fn is_response(&self) -> bool {
+        self.type_field == UDPApplicationEnum::RESPONSE as u16 || self.type_field == UDPApplicationEnum::TIMED_RESPONSE as u16
+    }
+
+    /// Server receive and send time of a `TIMED_RESPONSE`.
+    pub fn server_timestamps(&self) -> Option<(u128, u128)> {
+        if self.type_field != UDPApplicationEnum::TIMED_RESPONSE as u16 || self.test_payload.len() < 16 {
+            return None;
+        }
+        let received = u64::from_be_bytes(self.test_payload[..8].try_into().unwrap());
+        let sent = u64::from_be_bytes(self.test_payload[8..16].try_into().unwrap());
+        Some((received as u128, sent as u128))
+    }
+
+    /// Turns a response into a `TIMED_RESPONSE` carrying the given server times, if its payload is large enough.
+    pub fn set_server_timestamps(&mut self, received: u128, sent: u128) {
+        if self.test_payload.len() >= 16 {
+            self.type_field = UDPApplicationEnum::TIMED_RESPONSE as u16;
+            self.test_payload[..8].copy_from_slice(&(received as u64).to_be_bytes());
+            self.test_payload[8..16].copy_from_slice(&(sent as u64).to_be_bytes());
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.push((self.type_field >> 8) as u8);
-        bytes.push(self.type_field as u8);
-        bytes.push((self.session_id >> 8) as u8);
-        bytes.push(self.session_id as u8);
+        let mut bytes = Vec::with_capacity(4 + self.test_payload.len());
+        bytes.extend_from_slice(&self.type_field.to_be_bytes());
+        bytes.extend_from_slice(&self.session_id.to_be_bytes());
         bytes.extend_from_slice(&self.test_payload);
         bytes
     }
@@ -0,0 +1,88 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use udpbenchmark::{QosProfile, Server};
+use common::temp_dir;
+
+const CONFIG: &str = "
+server_addr: 10.0.0.1:8080
+client_addr: 127.0.0.1:0
+payload_size: 500
+speedtest_duration: 10
+ping_interval: 0.05
+speedtest_mode: ping
+experiment_mode: true
+experiment_count: 1
+experiment_interval: 0
+experiment_servers:
+  - name: Unreachable
+    address: 10.0.0.1:8080
+  - name: Sized
+    address: 10.0.0.2:8080
+    payload_sizes: [1000]
+experiment_payload_sizes: [500, 1000]
+";
+
+fn udpbenchmark(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_udpbenchmark")).current_dir(dir).args(args).output().expect("Couldn't run udpbenchmark");
+    assert!(output.status.success(), "udpbenchmark {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn client_overrides_apply_in_experiment_mode() {
+    let dir = temp_dir("cli-overrides");
+    fs::write(dir.join("client_config.yaml"), CONFIG).unwrap();
+    let server = Server::builder().address("127.0.0.1:0").qos_profile(QosProfile::Default).build().unwrap().spawn().unwrap();
+    let server_addr = server.local_addr().to_string();
+    udpbenchmark(&dir, &["-c", "client_config.yaml", "client", "--server", &server_addr, "--payload-size", "200", "--duration", "0.3"]);
+    server.stop();
+
+    // A single test against the given server with the given payload size instead of the configured sweep
+    let plan = fs::read_dir(dir.join("results")).unwrap().map(|entry| entry.unwrap().path()).find(|path| path.file_name().unwrap().to_string_lossy().starts_with("plan_")).unwrap();
+    let mut rdr = csv::Reader::from_path(plan).unwrap();
+    let headers = rdr.headers().unwrap().clone();
+    let rows = rdr.records().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(rows.len(), 1);
+    let cell = |column: &str| rows[0][headers.iter().position(|header| header == column).unwrap()].to_string();
+    assert_eq!((cell("Server Address"), cell("Payload Size")), (server_addr, "200".to_string()));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn report_prints_times_in_their_units() {
+    let dir = temp_dir("cli-report");
+    fs::write(
+        dir.join("rtt_times_edge_ping.csv"),
+        "Server Name,Server Address,Speedtest-Mode,Responses,Average RTT,Variance RTT,Interarrival Jitter (RTT),Longest Outage,Loss Rate\n\
+         Edge,127.0.0.1:8080,ping,10,1500,4000000,250,20000,0.5\n",
+    )
+    .unwrap();
+    let report = udpbenchmark(&dir, &["report", "--results-dir", "."]);
+    let row = report.lines().nth(1).unwrap();
+    assert_eq!(row, "Edge | 127.0.0.1:8080 | ping | 10 | 1.500 ms | 4.000 ms^2 | 0.250 ms | 20.000 ms | 0.5");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn unanswered_tests_stay_incomplete_in_the_ledger() {
+    let dir = temp_dir("cli-unanswered");
+    // A server that receives the requests, but never answers
+    let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let config = format!(
+        "server_addr: {0}\nclient_addr: 127.0.0.1:0\nspeedtest_duration: 0.2\nping_interval: 0.05\nspeedtest_mode: ping\nexperiment_mode: true\nexperiment_interval: 0\nexperiment_retries: 0\nexperiment_servers:\n  - name: Silent\n    address: {0}\n",
+        silent.local_addr().unwrap()
+    );
+    fs::write(dir.join("client_config.yaml"), config).unwrap();
+    udpbenchmark(&dir, &["-c", "client_config.yaml", "client"]);
+
+    let mut rdr = csv::Reader::from_path(dir.join("results").join("ledger.csv")).unwrap();
+    let statuses = rdr.records().map(|record| record.unwrap()[3].to_string()).collect::<Vec<_>>();
+    assert_eq!(statuses, ["planned", "failed"]);
+    // The sweep reports the incomplete test itself
+    let status = fs::read_to_string(dir.join("results").join("campaign_status.csv")).unwrap();
+    assert_eq!(status.lines().nth(1), Some("0,Silent_ping_500B_0.05s_0.2s,failed,1,false"));
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,56 @@
+//! Helpers shared by the integration tests. Not every test uses all of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use udpbenchmark::sim::SimNetwork;
+use udpbenchmark::{QosProfile, Server, ServerHandle, TestResult, TestSpec};
+
+/// Starts a single-threaded server on `address` of the simulated network.
+pub fn start_server(network: &SimNetwork, address: &str, qos_profile: QosProfile) -> ServerHandle {
+    Server::builder()
+        .network(Arc::new(network.clone()))
+        .address(address)
+        .qos_profile(qos_profile)
+        .threads(1)
+        .build()
+        .expect("Couldn't start server")
+        .spawn()
+        .expect("Couldn't spawn server")
+}
+
+/// A two-second ping target of 64-byte probes every 100 ms.
+pub fn target(name: &str, server: &ServerHandle) -> TestSpec {
+    TestSpec {
+        server_addr: server.local_addr().to_string(),
+        server_name: Some(name.to_string()),
+        client_addr: "127.0.0.1:0".to_string(),
+        payload_size: 64,
+        interval: Duration::from_millis(100),
+        duration: Duration::from_secs(2),
+        receive_timeout: Duration::from_secs(1),
+        ..TestSpec::default()
+    }
+}
+
+/// An empty directory of this process in the system's temporary directory.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("udpbenchmark-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn sent_packets(result: &TestResult) -> usize {
+    result.delivery.sent as usize
+}
+
+pub fn loss_rate(result: &TestResult) -> f64 {
+    result.delivery.loss_rate().unwrap()
+}
+
+pub fn assert_within(name: &str, value: f64, min: f64, max: f64) {
+    assert!(value >= min && value <= max, "{} = {} not within [{}, {}]", name, value, min, max);
+}
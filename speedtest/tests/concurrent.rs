@@ -0,0 +1,46 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+use udpbenchmark::concurrent::{ConcurrentProbe, DIFFERENCES_FILE};
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::QosProfile;
+use common::{start_server, target};
+
+#[test]
+fn targets_are_probed_over_the_same_window() {
+    let network = SimNetwork::new(7).with_link(LinkConfig { delay: Duration::from_millis(1), ..LinkConfig::default() });
+    let edge = start_server(&network, "127.0.0.1:8080", QosProfile::Delay(5));
+    let cloud = start_server(&network, "127.0.0.1:8081", QosProfile::Delay(20));
+    let result = ConcurrentProbe::new(vec![target("Edge", &edge), target("Cloud", &cloud)])
+        .network(Arc::new(network.clone()))
+        .run()
+        .expect("Probing failed");
+    edge.stop();
+    cloud.stop();
+
+    assert_eq!(result.results.len(), 2);
+    let sent = |i: usize| result.results[i].rtt_times.rtt_times.iter().map(|packet| packet.sent_time).collect::<Vec<_>>();
+    assert_eq!(sent(0).len(), 20);
+    assert_eq!(sent(0), sent(1));
+    assert!(result.results.iter().all(|target| target.started_at == result.started_at && target.delivery.lost == 0));
+    let medians = result.results.iter().map(|target| target.summary().unwrap().median).collect::<Vec<_>>();
+    assert!(medians[1] - medians[0] >= 14_900 && medians[1] - medians[0] <= 15_100, "{:?}", medians);
+
+    let differences = result.differences();
+    assert_eq!(differences.len(), 1);
+    let difference = &differences[0];
+    assert_eq!((difference.first.as_str(), difference.second.as_str()), ("Edge", "Cloud"));
+    let summary = difference.summary.as_ref().unwrap();
+    assert_eq!(summary.count, 20);
+    assert_eq!((summary.mean, summary.median), (-15_000.0, -15_000.0));
+
+    let dir = std::env::temp_dir().join(format!("udpbenchmark-concurrent-{}", std::process::id()));
+    result.write_to_dir(&dir).unwrap();
+    assert!(dir.join("rtt_times_Edge_ping.csv").exists() && dir.join("rtt_times_Cloud_ping.csv").exists());
+    let mut rdr = csv::Reader::from_path(dir.join(DIFFERENCES_FILE)).unwrap();
+    let rows = rdr.records().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(rows.len(), 1);
+    assert_eq!((&rows[0][2], &rows[0][3], &rows[0][4], &rows[0][5]), ("Edge", "Cloud", "20", "-15000"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use udpbenchmark::analyze::{self, ResultTable};
+use udpbenchmark::client::{SpeedtestEnum, TestSpec};
+use udpbenchmark::config::{ClientConfig, ExperimentTarget, ServerConfig};
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::{Client, Server};
+use yaml_rust::YamlLoader;
+
+const CONFIG: &str = "
+server_addr: 10.0.0.1:8080
+client_addr: 0.0.0.0:0
+speedtest_mode: duration
+experiment_payload_sizes: [100, 200]
+server_labels:
+  10.0.0.3:8080: Edge
+experiment_servers:
+  - name: Private Cloud
+    address: 00.00.00.00:8080
+  - name: Public Cloud
+    address: 00.00.00.00:8080
+    payload_sizes: [64]
+    bitrate: 5
+    bitrate_scale: K
+    speedtest_mode: Ping
+  - 10.0.0.3:8080
+";
+
+#[test]
+fn experiment_targets_have_names_and_overrides() {
+    let config = ClientConfig::from_yaml(&YamlLoader::load_from_str(CONFIG).unwrap()[0]).unwrap();
+    let names = config.experiment_servers.iter().map(|target| (target.name.as_str(), target.address.as_str())).collect::<Vec<_>>();
+    assert_eq!(names, vec![("Private Cloud", "00.00.00.00:8080"), ("Public Cloud", "00.00.00.00:8080"), ("Edge", "10.0.0.3:8080")]);
+    assert_eq!(config.experiment_servers[0], ExperimentTarget::new("Private Cloud", "00.00.00.00:8080"));
+
+    let public = &config.experiment_servers[1];
+    assert_eq!(public.payload_sizes, Some(vec![64]));
+    let spec = TestSpec::from_config(&config, public, 64);
+    assert_eq!((spec.server_name(), spec.mode, spec.bitrate * spec.bitrate_scale as i64), ("Public Cloud", SpeedtestEnum::Ping, 5 * 1024));
+    assert_eq!(spec.server_file_name(), "Public-Cloud");
+    let private = TestSpec::from_config(&config, &config.experiment_servers[0], 100);
+    assert_eq!((private.mode, private.bitrate), (SpeedtestEnum::ByDuration, 100));
+    assert_eq!(config.to_json()["experiment_servers"][1]["bitrate"], 5);
+}
+
+#[test]
+fn targets_sharing_an_address_stay_apart() {
+    let results_dir = std::env::temp_dir().join(format!("udpbenchmark-targets-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&results_dir);
+    let network = SimNetwork::new(3).with_link(LinkConfig { delay: Duration::from_millis(1), ..LinkConfig::default() });
+    let server = Server::builder().network(Arc::new(network.clone())).threads(1).build().unwrap().spawn().unwrap();
+    for name in ["Private Cloud", "Public Cloud"] {
+        let result = Client::builder()
+            .network(Arc::new(network.clone()))
+            .server_addr(server.local_addr().to_string())
+            .server_name(name)
+            .client_addr("127.0.0.1:0")
+            .mode(SpeedtestEnum::Ping)
+            .interval(Duration::from_millis(100))
+            .duration(Duration::from_millis(500))
+            .build()
+            .run()
+            .unwrap();
+        result.write_evaluated_data_to_csv(&results_dir).unwrap();
+        assert!(results_dir.join(format!("rtt_times_{}_ping.csv", result.spec.server_file_name())).exists());
+    }
+    server.stop();
+
+    let table = ResultTable::read_dir(&results_dir).unwrap();
+    assert_eq!(&table.columns[..3], ["Server Name", "Server Address", "Speedtest-Mode"]);
+    let grouped = table.group_mean();
+    assert_eq!(grouped.rows.iter().map(|row| row[0].as_str()).collect::<Vec<_>>(), ["Private Cloud", "Public Cloud"]);
+    let aggregates = analyze::aggregate(&results_dir, &Default::default()).unwrap();
+    let responses = aggregates.iter().filter(|aggregate| aggregate.metric == "Responses").map(|aggregate| aggregate.group[0].as_str()).collect::<Vec<_>>();
+    assert_eq!(responses, ["Private Cloud", "Public Cloud"]);
+    fs::remove_dir_all(results_dir).unwrap();
+}
+
+#[test]
+fn logged_server_seeds_load_from_the_config() {
+    let random = Server::builder().address("127.0.0.1:0").build().unwrap().spawn().unwrap();
+    for seed in [random.seed(), 17386264056990141951, u64::MAX, 0] {
+        let yaml = format!("server:\n  address: 127.0.0.1:8080\n  seed: {}\n", seed);
+        let config = ServerConfig::from_yaml(&YamlLoader::load_from_str(&yaml).unwrap()[0]).unwrap();
+        assert_eq!(config.seed, Some(seed));
+    }
+    random.stop();
+
+    let yaml = "server:\n  address: 127.0.0.1:8080\n  seed: 1.5\n";
+    assert!(ServerConfig::from_yaml(&YamlLoader::load_from_str(yaml).unwrap()[0]).is_err());
+}
+
+#[test]
+fn targets_need_distinct_names() {
+    let yaml = |second: &str| format!("server_addr: 10.0.0.1:8080\nclient_addr: 0.0.0.0:0\nexperiment_servers:\n  - name: Edge Site\n    address: 10.0.0.3:8080\n  - name: {}\n    address: 10.0.0.4:8080\n", second);
+    let load = |second: &str| ClientConfig::from_yaml(&YamlLoader::load_from_str(&yaml(second)).unwrap()[0]);
+
+    assert!(load("Cloud").is_ok());
+    for duplicate in ["Edge Site", "Edge-Site", "Edge/Site"] {
+        let error = load(duplicate).unwrap_err().to_string();
+        assert!(error.contains("10.0.0.4:8080") && error.contains("experiment_servers"), "{}", error);
+    }
+}
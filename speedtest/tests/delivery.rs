@@ -0,0 +1,113 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+use udpbenchmark::client::{PacketLog, RTTTimes, SpeedtestEnum};
+use udpbenchmark::delivery::DeliveryStats;
+use udpbenchmark::jitter::JitterStats;
+use udpbenchmark::outage::OutageReport;
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::{Client, QosProfile, Server, TestResult};
+use common::assert_within;
+
+fn ping(link: LinkConfig, qos_profile: QosProfile) -> TestResult {
+    ping_with_packet_log(link, qos_profile, false)
+}
+
+fn ping_with_packet_log(link: LinkConfig, qos_profile: QosProfile, packet_log: bool) -> TestResult {
+    let network = SimNetwork::new(5).with_link(link);
+    let server = Server::builder().network(Arc::new(network.clone())).qos_profile(qos_profile).threads(1).seed(5).build().unwrap().spawn().unwrap();
+    let result = Client::builder()
+        .network(Arc::new(network))
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .mode(SpeedtestEnum::Ping)
+        .interval(Duration::from_millis(1))
+        .duration(Duration::from_secs(5))
+        .packet_log(packet_log)
+        .build()
+        .run()
+        .unwrap();
+    server.stop();
+    result
+}
+
+#[test]
+fn probes_are_classified_like_rfc_4737() {
+    let mut sender = PacketLog::default();
+    let mut receiver = PacketLog::default();
+    for session_id in 0..10u16 {
+        sender.sent(session_id, 1000 + session_id as u128);
+    }
+    // Probe 3 arrives after 4, 5 and 6, probe 1 arrives twice and probe 9 is lost
+    for (time, session_id) in [0, 1, 2, 4, 5, 6, 3, 7, 8, 1].iter().enumerate() {
+        receiver.received(*session_id, 2000 + time as u128);
+    }
+    receiver.received(42, 3000);
+    let delivery = DeliveryStats::from_rtt_times(&RTTTimes::from_logs(vec![sender, receiver]));
+
+    assert_eq!((delivery.sent, delivery.delivered, delivery.lost, delivery.duplicates, delivery.unsolicited), (10, 9, 1, 1, 1));
+    assert_eq!((delivery.duplicated, delivery.reordered), (1, 1));
+    assert_eq!(delivery.loss_rate(), Some(0.1));
+    assert_eq!(delivery.duplicate_rate(), Some(0.1));
+    assert_eq!(delivery.reordered_ratio(), Some(1.0 / 9.0));
+    assert_eq!(delivery.max_reordering_extent, 3);
+    assert_eq!(delivery.n_reordering, vec![1, 1, 1]);
+    assert_eq!(delivery.n_reordering_ratio(4), Some(0.0));
+}
+
+#[test]
+fn no_probes_give_no_rates() {
+    let delivery = DeliveryStats::from_rtt_times(&RTTTimes::new());
+
+    assert_eq!(delivery.loss_rate(), None);
+    assert_eq!(delivery.reordered_ratio(), None);
+    assert_eq!(delivery.mean_reordering_extent(), None);
+    assert_eq!(delivery.max_n_reordering(), 0);
+}
+
+#[test]
+fn server_loss_profile_is_measured() {
+    let result = ping(LinkConfig { delay: Duration::from_millis(1), ..LinkConfig::default() }, QosProfile::Loss(20));
+
+    assert_eq!(result.delivery.sent, 5_000);
+    assert_within("loss rate", result.delivery.loss_rate().unwrap(), 0.18, 0.22);
+    assert_eq!((result.delivery.duplicated, result.delivery.reordered), (0, 0));
+}
+
+#[test]
+fn server_duplicate_profile_is_measured() {
+    let result = ping(LinkConfig { delay: Duration::from_millis(1), ..LinkConfig::default() }, QosProfile::Duplicate(30));
+
+    assert_eq!(result.delivery.lost, 0);
+    assert_within("duplicate rate", result.delivery.duplicate_rate().unwrap(), 0.28, 0.32);
+    assert_eq!(result.delivery.duplicates, result.delivery.duplicated);
+}
+
+#[test]
+fn link_jitter_reorders_probes() {
+    let result = ping(LinkConfig { delay: Duration::from_millis(10), jitter: Duration::from_millis(5), ..LinkConfig::default() }, QosProfile::Default);
+
+    let delivery = &result.delivery;
+    assert_eq!(delivery.lost, 0);
+    assert!(delivery.reordered > 0 && delivery.reordered < delivery.delivered);
+    assert!(delivery.max_reordering_extent >= delivery.max_n_reordering() as u64);
+    assert!(delivery.n_reordering.windows(2).all(|counts| counts[0] >= counts[1]));
+    assert!(delivery.n_reordering[0] <= delivery.reordered);
+}
+
+#[test]
+fn statistics_taken_while_running_match_the_packet_log() {
+    let link = LinkConfig { delay: Duration::from_millis(10), jitter: Duration::from_millis(5), loss: 10.0, ..LinkConfig::default() };
+    let result = ping_with_packet_log(link, QosProfile::Duplicate(10), true);
+
+    let (live, logged) = (&result.delivery, DeliveryStats::from_rtt_times(&result.rtt_times));
+    assert_eq!((live.sent, live.delivered, live.lost, live.duplicated, live.duplicates), (logged.sent, logged.delivered, logged.lost, logged.duplicated, logged.duplicates));
+    // Answers received in the same microsecond are taken in arrival order, the packet log sorts them by sequence
+    assert_within("reordered", live.reordered as f64, logged.reordered as f64 * 0.99, logged.reordered as f64 * 1.01);
+    assert_eq!(result.outages, OutageReport::detect(&result.rtt_times, &result.spec.outage));
+    let jitter = JitterStats::from_rtt_times(&result.rtt_times);
+    assert_eq!((result.jitter.mean_ipdv, result.jitter.max_absolute_ipdv), (jitter.mean_ipdv, jitter.max_absolute_ipdv));
+    assert_eq!(result.histogram.len(), result.rtt_times.get_rtts().len() as u64);
+    assert_eq!(result.histogram.max(), result.rtt_times.get_max_rtt().map(|rtt| rtt as u64));
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use udpbenchmark::client::{PacketLog, RTTTimes, SpeedtestEnum};
+use udpbenchmark::events::{self, Event, EventImpact, MarkerSource};
+use udpbenchmark::{Client, Server, TestResult};
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("udpbenchmark-{}-{}", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+/// Pings a loopback server for a second while `mark` runs after 300 ms.
+fn ping_with_marker(source: MarkerSource, mark: impl FnOnce() + Send + 'static) -> TestResult {
+    let server = Server::builder().address("127.0.0.1:0").build().unwrap().spawn().unwrap();
+    let client = Client::builder()
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .mode(SpeedtestEnum::Ping)
+        .interval(Duration::from_millis(10))
+        .duration(Duration::from_secs(1))
+        .receive_timeout(Duration::from_millis(300))
+        .marker(source)
+        .build();
+    let marker = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        mark();
+    });
+    let result = client.run().unwrap();
+    marker.join().unwrap();
+    server.stop();
+    result
+}
+
+fn assert_marked_mid_test(result: &TestResult, source: &str, label: &str) {
+    assert_eq!(result.events.len(), 1, "events: {:?}", result.events);
+    assert_eq!((result.events[0].source.as_str(), result.events[0].label.as_str()), (source, label));
+    let started_at = result.started_at.duration_since(std::time::UNIX_EPOCH).unwrap().as_micros();
+    let offset = (result.events[0].time - started_at) as f64 / 1e6;
+    assert!(offset > 0.2 && offset < 0.8, "event after {} s", offset);
+}
+
+#[test]
+fn impact_measures_time_to_loss_and_recovery() {
+    let mut log = PacketLog::default();
+    // Probes every 10 ms, 4 to 6 are lost
+    for session_id in 0..10u16 {
+        let sent_time = 1_000_000 + session_id as u128 * 10_000;
+        log.sent(session_id, sent_time);
+        if !(4..=6).contains(&session_id) {
+            log.received(session_id, sent_time + 500);
+        }
+    }
+    let rtt_times = RTTTimes::from_logs(vec![log]);
+
+    let impact = EventImpact::of(&Event::new(1_015_000, "socket", "rules update"), &rtt_times);
+    assert_eq!(impact.time_to_first_loss, Some(Duration::from_millis(25)));
+    assert_eq!(impact.time_to_recovery, Some(Duration::from_millis(55)));
+    assert_eq!(EventImpact::of(&Event::new(1_075_000, "socket", "after"), &rtt_times), EventImpact::default());
+}
+
+#[test]
+fn control_socket_marks_events() {
+    let path = temp_path("marker.sock");
+    let socket = path.clone();
+    let result = ping_with_marker(MarkerSource::Socket(path.clone()), move || events::send_marker(&socket, "rules update\n").unwrap());
+
+    assert_marked_mid_test(&result, "socket", "rules update");
+    assert!(!path.exists(), "control socket must be removed after the test");
+}
+
+#[test]
+fn named_pipe_marks_events() {
+    let path = temp_path("marker.fifo");
+    let pipe = path.clone();
+    let result = ping_with_marker(MarkerSource::Pipe(path.clone()), move || {
+        let mut writer = fs::OpenOptions::new().write(true).open(&pipe).unwrap();
+        writer.write_all(b"migration\n").unwrap();
+    });
+
+    assert_marked_mid_test(&result, "pipe", "migration");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn signal_marks_events() {
+    let result = ping_with_marker(MarkerSource::Signal, || {
+        signal_hook::low_level::raise(signal_hook::consts::SIGUSR1).unwrap();
+    });
+
+    assert_marked_mid_test(&result, "signal", "SIGUSR1");
+
+    // Signals in quick succession are not merged into one marker
+    let result = ping_with_marker(MarkerSource::Signal, || {
+        for _ in 0..3 {
+            signal_hook::low_level::raise(signal_hook::consts::SIGUSR1).unwrap();
+        }
+    });
+    assert_eq!(result.events.len(), 3, "events: {:?}", result.events);
+    assert!(result.events.iter().all(|event| event.source == "signal"));
+}
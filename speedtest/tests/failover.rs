@@ -0,0 +1,80 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+use udpbenchmark::config::{ClientConfig, ExperimentTarget};
+use udpbenchmark::failover::{FailoverClient, FailoverConfig, TransitionKind, PROBES_FILE, TRANSITIONS_FILE};
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::QosProfile;
+use yaml_rust::YamlLoader;
+use common::start_server;
+
+const CONFIG: &str = "
+server_addr: 127.0.0.1:8080
+client_addr: 127.0.0.1:0
+experiment_servers:
+  - name: Cloud
+    address: 127.0.0.1:8081
+failover:
+  servers:
+    - name: Edge
+      address: 127.0.0.1:8080
+    - name: Cloud
+      address: 127.0.0.1:8081
+  interval: 0.1
+  health_interval: 0.2
+  timeout: 300
+  max_losses: 3
+  failback: true
+  recovery_probes: 3
+";
+
+fn assert_millis(name: &str, value: Duration, millis: f64) {
+    assert!((value.as_secs_f64() * 1000.0 - millis).abs() < 1.0, "{} = {:?}, expected {} ms", name, value, millis);
+}
+
+#[test]
+fn client_fails_over_and_back() {
+    let config = ClientConfig::from_yaml(&YamlLoader::load_from_str(CONFIG).unwrap()[0]).unwrap();
+    let failover = config.failover.clone();
+    assert_eq!(failover.servers, [ExperimentTarget::new("Edge", "127.0.0.1:8080"), ExperimentTarget::new("Cloud", "127.0.0.1:8081")]);
+    assert_eq!((failover.timeout, failover.rtt_threshold, failover.failback), (Duration::from_millis(300), None, true));
+    let defaults = ClientConfig::from_yaml(&YamlLoader::load_from_str(&CONFIG[..CONFIG.find("failover:").unwrap()]).unwrap()[0]).unwrap();
+    assert_eq!(defaults.failover, FailoverConfig { servers: defaults.experiment_servers.clone(), ..FailoverConfig::default() });
+
+    // The primary is unreachable from 2 s to 5 s into the run
+    let network = SimNetwork::new(9).with_link(LinkConfig { delay: Duration::from_millis(1), ..LinkConfig::default() }).with_outage(8080, Duration::from_secs(2), Duration::from_secs(5));
+    let edge = start_server(&network, "127.0.0.1:8080", QosProfile::Default);
+    let cloud = start_server(&network, "127.0.0.1:8081", QosProfile::Default);
+    let result = FailoverClient::new(failover).network(Arc::new(network.clone())).client_addr("127.0.0.1:0").duration(Duration::from_secs(8)).run().unwrap();
+    edge.stop();
+    cloud.stop();
+
+    assert_eq!(result.transitions.len(), 2, "{:?}", result.transitions);
+    let start = result.started_at.duration_since(std::time::UNIX_EPOCH).unwrap().as_micros();
+    let failover = &result.transitions[0];
+    assert_eq!((failover.kind, failover.from.as_str(), failover.to.as_str()), (TransitionKind::Failover, "Edge", "Cloud"));
+    // The probe sent at 2 s is the first lost one, the third loss times out at 2.5 s
+    assert_eq!(failover.changed_at - start, 2_000_000);
+    assert_millis("detection time", failover.detection_time(), 500.0);
+    assert_millis("switchover time", failover.switchover_time().unwrap(), 2.0);
+    assert_eq!(failover.lost, 5);
+
+    // Health checks every 200 ms see the primary back at 5 s, the third one is answered before 5.5 s
+    let failback = &result.transitions[1];
+    assert_eq!((failback.kind, failback.from.as_str(), failback.to.as_str()), (TransitionKind::Failback, "Cloud", "Edge"));
+    assert_eq!(failback.changed_at - start, 5_000_000);
+    assert_millis("detection time", failback.detection_time(), 500.0);
+    assert_millis("switchover time", failback.switchover_time().unwrap(), 2.0);
+    assert_eq!(failback.lost, 0);
+    assert_eq!(result.traffic_loss(), (80, 5));
+
+    let dir = std::env::temp_dir().join(format!("udpbenchmark-failover-{}", std::process::id()));
+    result.write_to_dir(&dir).unwrap();
+    let mut rdr = csv::Reader::from_path(dir.join(TRANSITIONS_FILE)).unwrap();
+    let rows = rdr.records().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!((&rows[0][0], &rows[0][6], &rows[0][8]), ("failover", "500000", "5"));
+    assert_eq!(&rows[1][0], "failback");
+    assert_eq!(csv::Reader::from_path(dir.join(PROBES_FILE)).unwrap().records().count(), result.probes.len());
+    std::fs::remove_dir_all(dir).unwrap();
+}
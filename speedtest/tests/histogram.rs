@@ -0,0 +1,92 @@
+mod common;
+
+use std::fs;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use udpbenchmark::analyze::merge_histograms;
+use udpbenchmark::histogram::LatencyHistogram;
+use common::temp_dir;
+
+fn samples(seed: u64, count: usize) -> Vec<u64> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count).map(|_| rng.gen_range(50..2_000_000)).collect()
+}
+
+fn exact_percentile(sorted: &[u64], percentile: f64) -> u64 {
+    let rank = ((percentile / 100.0 * sorted.len() as f64).ceil() as usize).max(1);
+    sorted[rank - 1]
+}
+
+#[test]
+fn empty_histogram_has_no_statistics() {
+    let histogram = LatencyHistogram::new(3);
+
+    assert!(histogram.is_empty());
+    assert_eq!(histogram.min(), None);
+    assert_eq!(histogram.mean(), None);
+    assert_eq!(histogram.stddev(), None);
+    assert_eq!(histogram.value_at_percentile(99.0), None);
+}
+
+#[test]
+fn percentiles_are_within_precision() {
+    let mut values = samples(1, 100_000);
+    let mut histogram = LatencyHistogram::new(3);
+    for value in &values {
+        histogram.record(*value);
+    }
+    values.sort();
+
+    assert_eq!(histogram.len(), values.len() as u64);
+    assert_eq!(histogram.min(), values.first().copied());
+    assert_eq!(histogram.max(), values.last().copied());
+    let mean = values.iter().sum::<u64>() as f64 / values.len() as f64;
+    assert!((histogram.mean().unwrap() - mean).abs() < 1e-6);
+    for percentile in [50.0, 90.0, 99.0, 99.9, 99.99] {
+        let exact = exact_percentile(&values, percentile) as f64;
+        let value = histogram.value_at_percentile(percentile).unwrap() as f64;
+        assert!((value - exact).abs() <= exact * 0.001, "p{} = {} instead of {}", percentile, value, exact);
+    }
+}
+
+#[test]
+fn merged_histograms_equal_a_single_recording() {
+    let (first, second) = (samples(1, 10_000), samples(2, 10_000));
+    let mut merged = LatencyHistogram::new(3);
+    let mut single = LatencyHistogram::new(3);
+    for values in [&first, &second] {
+        let mut histogram = LatencyHistogram::new(3);
+        for value in values.iter() {
+            histogram.record(*value);
+            single.record(*value);
+        }
+        merged.merge(&histogram);
+    }
+
+    assert_eq!(merged.len(), single.len());
+    assert_eq!(merged.buckets().collect::<Vec<_>>(), single.buckets().collect::<Vec<_>>());
+    assert_eq!(merged.value_at_percentile(99.9), single.value_at_percentile(99.9));
+}
+
+#[test]
+fn histogram_logs_round_trip_and_merge_per_server() {
+    let dir = temp_dir("histogram-logs");
+    for (run, server) in ["10.0.0.1:8080", "10.0.0.1:8080", "10.0.0.2:8080"].iter().enumerate() {
+        let mut histogram = LatencyHistogram::new(2);
+        for value in samples(run as u64, 1_000) {
+            histogram.record(value);
+        }
+        let path = dir.join(format!("rtt_histogram_{}.hlog", run));
+        histogram.write_log(&path, &[("server", server), ("mode", "ping")]).unwrap();
+
+        let (read, tags) = LatencyHistogram::read_log(&path).unwrap();
+        assert_eq!(read, histogram);
+        assert!(tags.contains(&("server".to_string(), server.to_string())));
+    }
+
+    let merged = merge_histograms(&dir).unwrap();
+    assert_eq!(merged.len(), 2);
+    assert_eq!((merged[0].server_addr.as_str(), merged[0].runs, merged[0].histogram.len()), ("10.0.0.1:8080", 2, 2_000));
+    assert_eq!((merged[1].server_addr.as_str(), merged[1].runs, merged[1].histogram.len()), ("10.0.0.2:8080", 1, 1_000));
+    fs::remove_dir_all(&dir).unwrap();
+}
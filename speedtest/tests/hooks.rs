@@ -0,0 +1,38 @@
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use udpbenchmark::client::SpeedtestEnum;
+use udpbenchmark::hooks::{Hook, HookStage};
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::{Client, Server};
+
+#[test]
+fn hooks_run_around_the_test_and_are_recorded() {
+    let network = SimNetwork::new(1).with_link(LinkConfig { delay: Duration::from_millis(1), ..LinkConfig::default() });
+    let server = Server::builder().network(Arc::new(network.clone())).threads(1).build().unwrap().spawn().unwrap();
+    let result = Client::builder()
+        .network(Arc::new(network))
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .mode(SpeedtestEnum::Ping)
+        .interval(Duration::from_millis(100))
+        .duration(Duration::from_secs(5))
+        .hook(Hook::new(HookStage::Before, "echo before $UDPBENCHMARK_MODE"))
+        .hook(Hook::new(HookStage::During(Duration::from_secs(2)), "echo failing >&2; exit 3"))
+        .hook(Hook::new(HookStage::During(Duration::from_secs(60)), "echo too late"))
+        .hook(Hook::new(HookStage::After, "echo $UDPBENCHMARK_HOOK_STAGE"))
+        .build()
+        .run()
+        .unwrap();
+    server.stop();
+
+    let hooks = result.events.iter().map(|event| (event.source.as_str(), event.hook.clone().unwrap())).collect::<Vec<_>>();
+    assert_eq!(hooks.len(), 3, "events: {:?}", result.events);
+    assert_eq!((hooks[0].0, hooks[0].1.status, hooks[0].1.output.as_str()), ("hook:before", Some(0), "before ping"));
+    assert_eq!((hooks[1].0, hooks[1].1.status, hooks[1].1.output.as_str()), ("hook:during", Some(3), "failing"));
+    assert_eq!((hooks[2].0, hooks[2].1.status, hooks[2].1.output.as_str()), ("hook:after", Some(0), "after"));
+
+    // Virtual time stands still while a hook runs, so the offset is exact
+    let started_at = result.started_at.duration_since(UNIX_EPOCH).unwrap().as_micros();
+    assert_eq!(result.events[1].time - started_at, 2_000_000);
+    assert!(result.events[0].time <= started_at && result.events[2].time >= started_at + 5_000_000);
+}
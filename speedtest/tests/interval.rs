@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use std::time::Duration;
+use udpbenchmark::client::SpeedtestEnum;
+use udpbenchmark::interval::IntervalReporter;
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::timeline::TimelineEntry;
+use udpbenchmark::{Client, QosProfile, Server};
+
+#[test]
+fn reports_traffic_per_interval() {
+    let reporter = IntervalReporter::start("test", Duration::from_millis(100), 1_000_000, 100, Duration::from_millis(150));
+    let sender = reporter.sender();
+    // A probe every 20 ms for 250 ms, answered after 5 ms except for the one sent at 60 ms
+    for session_id in 0..13u16 {
+        let time = 1_000_000 + session_id as u128 * 20_000;
+        sender.send(TimelineEntry::Sent { session_id, time }).unwrap();
+        if session_id != 3 {
+            sender.send(TimelineEntry::Received { session_id, time: time + 5_000, server_timestamps: None }).unwrap();
+        }
+    }
+    drop(sender);
+    let reports = reporter.finish(1_250_000);
+
+    let counts = reports.iter().map(|report| (report.start.as_millis(), report.end.as_millis(), report.packets_sent, report.answered, report.lost)).collect::<Vec<_>>();
+    // The lost probe times out at 210 ms, but counts in the first interval, in which it was sent
+    assert_eq!(counts, vec![(0, 100, 5, 4, 1), (100, 200, 5, 5, 0), (200, 250, 3, 3, 0)]);
+    assert_eq!(reports[0].bytes_sent, 500);
+    assert_eq!(reports[0].throughput_sent(), 40_000.0);
+    assert_eq!(reports[0].loss_rate(), Some(0.2));
+    assert_eq!(reports[2].loss_rate(), Some(0.0));
+    let rtt = reports[1].rtt.as_ref().unwrap();
+    assert_eq!((rtt.min, rtt.max, reports[1].rtt_99th_percentile()), (5_000, 5_000, Some(5_000)));
+}
+
+#[test]
+fn test_reports_intervals_that_add_up() {
+    let network = SimNetwork::new(4).with_link(LinkConfig { delay: Duration::from_millis(1), ..LinkConfig::default() });
+    let server = Server::builder().network(Arc::new(network.clone())).qos_profile(QosProfile::Loss(10)).threads(1).seed(4).build().unwrap().spawn().unwrap();
+    let result = Client::builder()
+        .network(Arc::new(network))
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .mode(SpeedtestEnum::Ping)
+        .interval(Duration::from_millis(10))
+        .duration(Duration::from_secs(2))
+        .receive_timeout(Duration::from_millis(100))
+        .report_interval(Duration::from_millis(500))
+        .build()
+        .run()
+        .unwrap();
+    server.stop();
+
+    assert!(result.intervals.len() >= 4, "intervals: {:?}", result.intervals);
+    assert_eq!(result.intervals[1].start, Duration::from_millis(500));
+    let sum = |count: fn(&udpbenchmark::interval::IntervalReport) -> u64| result.intervals.iter().map(count).sum::<u64>();
+    assert_eq!(sum(|report| report.packets_sent), result.delivery.sent);
+    assert_eq!(sum(|report| report.answered), result.delivery.delivered);
+    assert_eq!(sum(|report| report.lost), result.delivery.lost);
+    assert_eq!(sum(|report| report.bytes_sent), result.bytes_sent as u64);
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::time::Duration;
+use udpbenchmark::client::SpeedtestEnum;
+use udpbenchmark::iperf;
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::{Client, QosProfile, Server};
+
+#[test]
+fn json_follows_iperf3_structure() {
+    let network = SimNetwork::new(6).with_link(LinkConfig { delay: Duration::from_millis(1), ..LinkConfig::default() });
+    let server = Server::builder().network(Arc::new(network.clone())).qos_profile(QosProfile::Loss(10)).threads(1).seed(6).build().unwrap().spawn().unwrap();
+    let result = Client::builder()
+        .network(Arc::new(network))
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .mode(SpeedtestEnum::Ping)
+        .payload_size(100)
+        .interval(Duration::from_millis(10))
+        .duration(Duration::from_secs(2))
+        .receive_timeout(Duration::from_millis(100))
+        .report_interval(Duration::from_millis(500))
+        .build()
+        .run()
+        .unwrap();
+    let server_port = server.local_addr().port();
+    server.stop();
+    let json = iperf::to_json(&result);
+
+    let start = &json["start"];
+    assert_eq!(start["connecting_to"]["port"], server_port);
+    assert_eq!(start["timestamp"]["time"], "Sun, 13 Sep 2020 12:26:40 GMT");
+    assert_eq!((start["test_start"]["protocol"].as_str(), start["test_start"]["blksize"].as_u64(), start["test_start"]["duration"].as_f64()), (Some("UDP"), Some(104), Some(2.0)));
+
+    let intervals = json["intervals"].as_array().unwrap();
+    assert_eq!(intervals.len(), result.intervals.len());
+    assert_eq!(intervals[1]["sum"]["start"], 0.5);
+    assert_eq!(intervals[1]["streams"][0]["socket"], 1);
+    assert_eq!(intervals[1]["streams"][0]["bytes"], intervals[1]["sum"]["bytes"]);
+    assert!(intervals[1]["sum"]["rtt_mean_ms"].as_f64().unwrap() >= 2.0);
+
+    let end = &json["end"];
+    assert_eq!(end["sum"]["packets"], result.delivery.sent);
+    assert_eq!(end["sum"]["lost_packets"], result.delivery.lost);
+    assert!(end["sum"]["lost_percent"].as_f64().unwrap() > 0.0);
+    assert_eq!(end["streams"][0]["udp"]["bytes"], result.bytes_sent);
+    assert_eq!(end["sum_received"]["bytes"], result.bytes_received);
+    assert_eq!(end["sum"]["jitter_ms"], result.jitter.interarrival_jitter.unwrap() / 1000.0);
+    assert_eq!(end["rtt"]["responses"], result.delivery.delivered);
+    assert!(end["rtt"]["percentiles_ms"]["p99"].as_f64().unwrap() >= end["rtt"]["min_ms"].as_f64().unwrap());
+}
+
+#[test]
+fn json_without_report_interval_has_one_interval() {
+    let network = SimNetwork::new(7).with_link(LinkConfig { delay: Duration::from_millis(1), ..LinkConfig::default() });
+    let server = Server::builder().network(Arc::new(network.clone())).qos_profile(QosProfile::Default).threads(1).build().unwrap().spawn().unwrap();
+    let result = Client::builder()
+        .network(Arc::new(network))
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .mode(SpeedtestEnum::Ping)
+        .interval(Duration::from_millis(10))
+        .duration(Duration::from_millis(500))
+        .receive_timeout(Duration::from_millis(100))
+        .build()
+        .run()
+        .unwrap();
+    server.stop();
+    let json = iperf::to_json(&result);
+
+    assert_eq!(json["start"]["test_start"]["duration"], 0.5);
+    let intervals = json["intervals"].as_array().unwrap();
+    assert_eq!(intervals.len(), 1);
+    let sum = &intervals[0]["sum"];
+    assert_eq!((sum["start"].as_f64(), sum["end"].as_f64()), (Some(0.0), Some(0.5)));
+    assert_eq!(sum["bytes"], json["end"]["sum"]["bytes"]);
+    assert_eq!(sum["packets"], result.delivery.sent);
+    assert_eq!(sum["lost_packets"], 0);
+}
@@ -0,0 +1,48 @@
+use std::sync::Arc;
+use std::time::Duration;
+use udpbenchmark::client::{PacketLog, RTTTimes, SpeedtestEnum};
+use udpbenchmark::jitter::JitterStats;
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::{Client, Server};
+
+#[test]
+fn jitter_follows_rfc_3550_and_rfc_3393() {
+    let mut log = PacketLog::default();
+    for (session_id, rtt) in [(0, Some(100)), (1, Some(300)), (2, None), (3, Some(200)), (4, Some(200))] {
+        let sent_time = 1000 + session_id as u128 * 10;
+        log.sent(session_id, sent_time);
+        if let Some(rtt) = rtt {
+            log.received(session_id, sent_time + rtt);
+        }
+    }
+    let jitter = JitterStats::from_rtt_times(&RTTTimes::from_logs(vec![log]));
+
+    // Arrival order is 100, 200, 200, 300 µs, so the transit differences are 100, 0 and 100 µs
+    let expected = [100.0, 0.0, 100.0].iter().fold(0.0, |jitter, difference| jitter + (difference - jitter) / 16.0);
+    assert!((jitter.interarrival_jitter.unwrap() - expected).abs() < 1e-9);
+    // Only the pairs (0, 1) and (3, 4) were both answered
+    assert_eq!(jitter.mean_ipdv, Some(100.0));
+    assert_eq!(jitter.max_absolute_ipdv, Some(200));
+    assert_eq!((jitter.pdv_99th_percentile, jitter.max_pdv), (Some(200), Some(200)));
+}
+
+#[test]
+fn constant_delay_has_no_jitter() {
+    let network = SimNetwork::new(1).with_link(LinkConfig { delay: Duration::from_millis(3), bandwidth: 0, ..LinkConfig::default() });
+    let server = Server::builder().network(Arc::new(network.clone())).threads(1).build().unwrap().spawn().unwrap();
+    let result = Client::builder()
+        .network(Arc::new(network))
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .mode(SpeedtestEnum::Ping)
+        .interval(Duration::from_millis(10))
+        .duration(Duration::from_secs(1))
+        .build()
+        .run()
+        .unwrap();
+    server.stop();
+
+    assert_eq!(result.jitter.interarrival_jitter, Some(0.0));
+    assert_eq!((result.jitter.max_absolute_ipdv, result.jitter.max_pdv), (Some(0), Some(0)));
+    assert_eq!(JitterStats::from_rtt_times(&RTTTimes::new()), JitterStats::default());
+}
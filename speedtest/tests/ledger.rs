@@ -0,0 +1,72 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use udpbenchmark::config::ClientConfig;
+use udpbenchmark::ledger::{run_campaign, Ledger, PointStatus};
+use udpbenchmark::plan::ExperimentPlan;
+use yaml_rust::YamlLoader;
+
+const CONFIG: &str = "
+server_addr: 10.0.0.1:8080
+client_addr: 0.0.0.0:0
+speedtest_mode: ping
+experiment_count: 2
+experiment_schedule: random
+experiment_servers:
+  - name: Edge
+    address: 10.0.0.3:8080
+  - name: Cloud
+    address: 10.0.0.4:8080
+";
+
+#[test]
+fn campaign_resumes_from_the_ledger() {
+    let dir = std::env::temp_dir().join(format!("udpbenchmark-ledger-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("ledger.csv");
+    let config = ClientConfig::from_yaml(&YamlLoader::load_from_str(CONFIG).unwrap()[0]).unwrap();
+    let plan = ExperimentPlan::expand(&config);
+    assert_eq!(plan.points.len(), 4);
+
+    // The first run fails Cloud in iteration 1 and the last point, each twice
+    let last = plan.points.last().unwrap().clone();
+    let failing = if last.iteration == 1 && last.target.name == "Cloud" { 1 } else { 2 };
+    let mut runs = Vec::new();
+    let mut ledger = Ledger::open(&path).unwrap();
+    let incomplete = run_campaign(&plan, &mut ledger, 1, |point| {
+        runs.push((point.iteration, point.target.name.clone()));
+        if point.iteration == 1 && point.target.name == "Cloud" || *point == last {
+            Err(io::Error::other("server unreachable"))
+        } else {
+            Ok(PathBuf::from(format!("run_{}", point.id())))
+        }
+    })
+    .unwrap();
+    assert_eq!(runs.len(), 4 + failing);
+    assert_eq!(incomplete.len(), failing);
+    assert!(incomplete.iter().all(|(_, _, status)| *status == PointStatus::Failed));
+
+    // The resumed campaign reuses the seed of the random schedule and only runs the incomplete points
+    let mut ledger = Ledger::open(&path).unwrap();
+    assert_eq!(ledger.seed(), plan.seed);
+    let mut resumed = config.clone();
+    resumed.experiment_seed = ledger.seed();
+    let resumed_plan = ExperimentPlan::expand(&resumed);
+    assert_eq!(resumed_plan, plan);
+    let mut reruns = Vec::new();
+    let incomplete = run_campaign(&resumed_plan, &mut ledger, 1, |point| {
+        reruns.push((point.iteration, point.target.name.clone()));
+        Ok(PathBuf::from(format!("run_{}", point.id())))
+    })
+    .unwrap();
+    assert_eq!(reruns.len(), failing);
+    assert!(reruns.contains(&(1, "Cloud".to_string())));
+    assert!(incomplete.is_empty());
+    assert_eq!(ledger.attempts(1, &plan.points.iter().find(|point| point.iteration == 1 && point.target.name == "Cloud").unwrap().id()), 3);
+
+    ledger.write_status_csv(&dir.join("status.csv")).unwrap();
+    let status = fs::read_to_string(dir.join("status.csv")).unwrap();
+    assert_eq!(status.lines().count(), 5);
+    assert!(status.lines().skip(1).all(|line| line.ends_with(",completed,1,true") || line.ends_with(",completed,3,true")), "{}", status);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,255 @@
+mod common;
+
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use udpbenchmark::client::{BitrateScale, SpeedtestEnum};
+use udpbenchmark::{Client, ClientBuilder, QosProfile, Server, ServerHandle, TestResult};
+use common::{assert_within, loss_rate, sent_packets};
+
+// The tests measure timing, so they must not compete with each other for the CPU.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn start_server(qos_profile: QosProfile) -> ServerHandle {
+    Server::builder()
+        .address("127.0.0.1:0")
+        .qos_profile(qos_profile)
+        .build()
+        .expect("Couldn't start server")
+        .spawn()
+        .expect("Couldn't spawn server")
+}
+
+fn client(server: &ServerHandle) -> ClientBuilder {
+    Client::builder()
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .payload_size(500)
+        .receive_timeout(Duration::from_millis(300))
+}
+
+fn ping(qos_profile: QosProfile, interval: Duration, duration: Duration) -> TestResult {
+    let server = start_server(qos_profile);
+    let result = client(&server)
+        .mode(SpeedtestEnum::Ping)
+        .interval(interval)
+        .duration(duration)
+        .build()
+        .run()
+        .expect("Test run failed");
+    server.stop();
+    result
+}
+
+#[test]
+fn ping_default_profile_answers_every_probe() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let result = ping(QosProfile::Default, Duration::from_millis(10), Duration::from_secs(1));
+
+    assert_within("sent packets", sent_packets(&result) as f64, 80.0, 101.0);
+    assert_eq!(loss_rate(&result), 0.0);
+    assert_eq!(result.bytes_sent, result.bytes_received);
+    assert!(result.histogram.max().unwrap() < 50_000, "loopback RTT above 50 ms");
+}
+
+#[test]
+fn client_records_server_seed() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let server = Server::builder().address("127.0.0.1:0").seed(1234).build().expect("Couldn't start server").spawn().expect("Couldn't spawn server");
+    let result = client(&server)
+        .mode(SpeedtestEnum::ByPacketCount)
+        .packet_count(10)
+        .build()
+        .run()
+        .expect("Test run failed");
+    server.stop();
+
+    assert_eq!(result.server_seed, Some(1234));
+}
+
+#[test]
+fn seeded_server_replays_impairments_with_all_workers() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let run = |seed| {
+        let server = Server::builder().address("127.0.0.1:0").qos_profile(QosProfile::Loss(50)).seed(seed).build().expect("Couldn't start server").spawn().expect("Couldn't spawn server");
+        let result = client(&server)
+            .mode(SpeedtestEnum::Ping)
+            .interval(Duration::from_millis(1))
+            .duration(Duration::from_millis(400))
+            .packet_log(true)
+            .build()
+            .run()
+            .expect("Test run failed");
+        server.stop();
+        // Whether each probe was answered, in the order they were sent
+        result.rtt_times.rtt_times.iter().filter(|packet| packet.sent_time != 0).map(|packet| packet.received_time != 0).collect::<Vec<_>>()
+    };
+
+    // The runs may send a few probes more or less, depending on the load of the host
+    let (first, second, other) = (run(7), run(7), run(8));
+    let common = first.len().min(second.len()).min(other.len());
+    assert!(common > 100, "only {} probes sent", common);
+    assert_eq!(first[..common], second[..common]);
+    assert_ne!(first[..common], other[..common]);
+}
+
+#[test]
+fn server_without_seed_support_is_asked_once() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    // An echo server that answers every packet, also the seed request, with a plain response
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+    let server_addr = socket.local_addr().unwrap();
+    let running = Arc::new(AtomicBool::new(true));
+    let echo = {
+        let running = Arc::clone(&running);
+        thread::spawn(move || {
+            let mut buf = [0; 2048];
+            while running.load(Ordering::Relaxed) {
+                if let Ok((len, addr)) = socket.recv_from(&mut buf) {
+                    buf[..2].copy_from_slice(&1u16.to_be_bytes());
+                    socket.send_to(&buf[..len], addr).unwrap();
+                }
+            }
+        })
+    };
+    let started = Instant::now();
+    let result = Client::builder()
+        .server_addr(server_addr.to_string())
+        .client_addr("127.0.0.1:0")
+        .payload_size(500)
+        .receive_timeout(Duration::from_secs(2))
+        .mode(SpeedtestEnum::ByPacketCount)
+        .packet_count(10)
+        .build()
+        .run()
+        .expect("Test run failed");
+    let elapsed = started.elapsed();
+    running.store(false, Ordering::Relaxed);
+    echo.join().unwrap();
+
+    assert_eq!(result.server_seed, None);
+    assert_eq!(sent_packets(&result), 10);
+    assert!(elapsed < Duration::from_millis(900), "test took {:?}", elapsed);
+}
+
+#[test]
+fn bitrate_mode_hits_target_bitrate() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let server = start_server(QosProfile::Default);
+    let result = client(&server)
+        .mode(SpeedtestEnum::ByDurationCustomBitrate)
+        .bitrate(1, BitrateScale::Mbps)
+        .duration(Duration::from_secs(2))
+        .build()
+        .run()
+        .expect("Test run failed");
+    server.stop();
+
+    // The 4 byte header is on top of the payload the bitrate is calculated for
+    let target = BitrateScale::Mbps as i64 as f64 * 504.0 / 500.0;
+    assert_within("sent throughput", result.throughput_sent(), target * 0.9, target * 1.05);
+    assert_within("received throughput", result.throughput_received(), target * 0.85, target * 1.05);
+}
+
+#[test]
+fn duration_mode_receives_responses() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let server = start_server(QosProfile::Default);
+    let result = client(&server)
+        .mode(SpeedtestEnum::ByDuration)
+        .duration(Duration::from_millis(500))
+        .build()
+        .run()
+        .expect("Test run failed");
+    server.stop();
+
+    assert!(result.bytes_sent > 0);
+    assert!(result.bytes_received > 0);
+    assert!(result.bytes_received <= result.bytes_sent);
+    assert!(result.has_rtts());
+}
+
+#[test]
+fn packet_count_mode_sends_exact_count() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let server = start_server(QosProfile::Default);
+    let result = client(&server)
+        .mode(SpeedtestEnum::ByPacketCount)
+        .packet_count(100)
+        .build()
+        .run()
+        .expect("Test run failed");
+    server.stop();
+
+    assert_eq!(sent_packets(&result), 100);
+    assert_eq!(result.bytes_sent, 100 * 504);
+    assert_within("loss rate", loss_rate(&result), 0.0, 0.05);
+}
+
+#[test]
+fn packet_count_mode_terminates_on_loss() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let server = start_server(QosProfile::Loss(100));
+    let result = client(&server)
+        .mode(SpeedtestEnum::ByPacketCount)
+        .packet_count(10)
+        .build()
+        .run()
+        .expect("Test run failed");
+    server.stop();
+
+    assert_eq!(result.bytes_received, 0);
+    assert!(!result.has_rtts());
+}
+
+#[test]
+fn loss_profile_drops_configured_share() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let result = ping(QosProfile::Loss(10), Duration::from_millis(2), Duration::from_secs(2));
+
+    assert!(sent_packets(&result) > 500);
+    assert_within("loss rate", loss_rate(&result), 0.05, 0.15);
+}
+
+#[test]
+fn duplicate_profile_duplicates_configured_share() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let result = ping(QosProfile::Duplicate(10), Duration::from_millis(2), Duration::from_secs(2));
+
+    let duplicate_rate = result.bytes_received as f64 / result.bytes_sent as f64 - 1.0;
+    assert_within("duplicate rate", duplicate_rate, 0.05, 0.15);
+}
+
+#[test]
+fn delay_profile_delays_every_response() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let result = ping(QosProfile::Delay(20), Duration::from_millis(50), Duration::from_secs(1));
+
+    assert_eq!(loss_rate(&result), 0.0);
+    assert!(result.histogram.min().unwrap() >= 20_000);
+    assert!(result.histogram.max().unwrap() < 60_000);
+}
+
+#[test]
+fn jitter_profile_spreads_rtts() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let result = ping(QosProfile::Jitter(20), Duration::from_millis(30), Duration::from_secs(2));
+
+    // Uniform delay in [0, 20) ms has a mean of 9.5 ms and a standard deviation of 5.8 ms
+    assert_within("average RTT", result.histogram.mean().unwrap(), 5_000.0, 15_000.0);
+    assert!(result.histogram.max().unwrap() - result.histogram.min().unwrap() > 10_000);
+}
+
+#[test]
+fn reorder_profile_delays_configured_share() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let result = ping(QosProfile::Reorder { reorder: 50, reorder_delay: 20 }, Duration::from_millis(30), Duration::from_secs(2));
+
+    // Half of the responses are delayed, three quarters of those by more than 5 ms
+    let delayed = result.histogram.buckets().filter(|(rtt, _)| *rtt > 5_000).map(|(_, count)| count).sum::<u64>() as f64 / result.histogram.len() as f64;
+    assert_eq!(loss_rate(&result), 0.0);
+    assert_within("delayed share", delayed, 0.2, 0.6);
+}
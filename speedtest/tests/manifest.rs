@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use udpbenchmark::analyze::{self, ResultTable};
+use udpbenchmark::client::SpeedtestEnum;
+use udpbenchmark::config::{self, ClientConfig, DEFAULT_CLIENT_CONFIG};
+use udpbenchmark::manifest::{RunManifest, MANIFEST_FILE};
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::{Client, Server};
+
+#[test]
+fn run_directory_holds_results_and_manifest() {
+    let results_dir = std::env::temp_dir().join(format!("udpbenchmark-manifest-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&results_dir);
+    let network = SimNetwork::new(2).with_link(LinkConfig { delay: Duration::from_millis(1), ..LinkConfig::default() });
+    let server = Server::builder().network(Arc::new(network.clone())).threads(1).seed(42).build().unwrap().spawn().unwrap();
+    let result = Client::builder()
+        .network(Arc::new(network))
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .mode(SpeedtestEnum::Ping)
+        .payload_size(64)
+        .interval(Duration::from_millis(100))
+        .duration(Duration::from_secs(1))
+        .build()
+        .run()
+        .unwrap();
+    server.stop();
+    let config = ClientConfig::from_yaml(&config::load_yaml(None, DEFAULT_CLIENT_CONFIG).unwrap()).unwrap();
+
+    let run_dir = results_dir.join("run_3");
+    result.write_evaluated_data_to_csv(&run_dir).unwrap();
+    RunManifest::new(&result, &config, 3).write(&run_dir).unwrap();
+
+    let manifest = RunManifest::read(&run_dir).unwrap();
+    let started_at = result.started_at.duration_since(UNIX_EPOCH).unwrap().as_micros();
+    assert_eq!((manifest.iteration, manifest.started_at, manifest.ended_at), (3, started_at, started_at + result.elapsed.as_micros()));
+    assert_eq!(manifest.server_seed, Some(42));
+    assert_eq!(manifest.version, env!("CARGO_PKG_VERSION"));
+    assert!(!manifest.hostname.is_empty() && !manifest.kernel.is_empty());
+    assert_eq!(manifest.config["server_addr"], config.server_addr.as_str());
+    assert_eq!(manifest.config["hooks"]["during"], serde_json::json!([]));
+    assert_eq!((manifest.test["mode"].as_str(), manifest.test["payload_size"].as_u64()), (Some("ping"), Some(64)));
+    let csv_name = format!("rtt_times_{}_ping.csv", result.spec.server_addr);
+    assert_eq!(manifest.files, vec![csv_name]);
+    assert!(run_dir.join(MANIFEST_FILE).exists());
+
+    // Results are found in the run directories
+    let table = ResultTable::read_dir(&results_dir).unwrap();
+    assert_eq!(table.rows.len(), 1);
+
+    // A second iteration of the same test is aggregated with the first, named after the given labels
+    RunManifest::new(&result, &config, 4).write(&{
+        let run_dir = results_dir.join("run_4");
+        result.write_evaluated_data_to_csv(&run_dir).unwrap();
+        run_dir
+    })
+    .unwrap();
+    let labels = BTreeMap::from([(result.spec.server_addr.clone(), "Simulated".to_string())]);
+    let aggregates = analyze::aggregate(&results_dir, &labels).unwrap();
+    let mean = aggregates.iter().find(|aggregate| aggregate.metric == "Average RTT").unwrap();
+    assert_eq!(mean.group, ["Simulated", &result.spec.server_addr, "ping", "64", "", "", ""]);
+    assert_eq!((mean.unit, mean.summary.count), ("ms", 2));
+    assert_eq!(mean.summary.ci95, Some((mean.summary.mean, mean.summary.mean)));
+    assert!(mean.summary.mean >= 2.0);
+    fs::remove_dir_all(results_dir).unwrap();
+}
@@ -0,0 +1,67 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use udpbenchmark::client::SpeedtestEnum;
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::{Client, QosProfile, Server, TestResult};
+
+/// Counts the heap memory in use and its peak since the last reset.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Runs a ping test at 10000 probes per second and returns it with the peak memory above the memory in
+/// use before it.
+fn ping(duration: Duration, packet_log: bool) -> (TestResult, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let network = SimNetwork::new(1).with_link(LinkConfig { delay: Duration::from_millis(1), jitter: Duration::from_millis(1), loss: 1.0, bandwidth: 0 });
+    let server = Server::builder().network(Arc::new(network.clone())).qos_profile(QosProfile::Default).threads(1).build().unwrap().spawn().unwrap();
+    let result = Client::builder()
+        .network(Arc::new(network))
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .mode(SpeedtestEnum::Ping)
+        .interval(Duration::from_micros(100))
+        .duration(duration)
+        .receive_timeout(Duration::from_millis(100))
+        .packet_log(packet_log)
+        .build()
+        .run()
+        .unwrap();
+    server.stop();
+    (result, PEAK.load(Ordering::Relaxed) - before)
+}
+
+#[test]
+fn long_tests_run_in_bounded_memory() {
+    // Both tests send more probes than session IDs, so that the reordering window is full in both
+    let (short, short_peak) = ping(Duration::from_secs(10), false);
+    let (long, long_peak) = ping(Duration::from_secs(30), false);
+
+    assert_eq!((short.delivery.sent, long.delivery.sent), (100_000, 300_000));
+    assert!(long.histogram.len() > 290_000 && long.delivery.reordered > 0 && long.rtt_times.rtt_times.is_empty());
+    assert!(long_peak < short_peak + (1 << 20), "peak memory grew from {} to {} bytes", short_peak, long_peak);
+
+    // Keeping every packet grows with the test
+    let (_, logged_peak) = ping(Duration::from_secs(10), true);
+    assert!(logged_peak > short_peak + 100_000 * 32, "packet log took {} bytes", logged_peak);
+}
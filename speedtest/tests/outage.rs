@@ -0,0 +1,44 @@
+use std::time::Duration;
+use udpbenchmark::client::{PacketLog, RTTTimes};
+use udpbenchmark::outage::{Outage, OutageConfig, OutageReport};
+
+/// Probes sent every 10 ms, with the RTT in ms or `None` if lost.
+fn probes(rtts: &[Option<u128>]) -> RTTTimes {
+    let mut log = PacketLog::default();
+    for (session_id, rtt) in rtts.iter().enumerate() {
+        let sent_time = 1_000_000 + session_id as u128 * 10_000;
+        log.sent(session_id as u16, sent_time);
+        if let Some(rtt) = rtt {
+            log.received(session_id as u16, sent_time + rtt * 1000);
+        }
+    }
+    RTTTimes::from_logs(vec![log])
+}
+
+#[test]
+fn consecutive_losses_make_up_an_outage() {
+    let rtt_times = probes(&[Some(1), None, Some(1), None, None, None, None, Some(1), Some(1), None, None]);
+    let report = OutageReport::detect(&rtt_times, &OutageConfig { min_probes: 2, rtt_threshold: None });
+
+    assert_eq!(
+        report.outages,
+        vec![
+            Outage { start: 1_030_000, end: 1_070_000, recovered: true, lost: 4, slow: 0 },
+            Outage { start: 1_090_000, end: 1_100_000, recovered: false, lost: 2, slow: 0 },
+        ]
+    );
+    assert_eq!(report.total_downtime(), Duration::from_millis(50));
+    assert_eq!(report.longest().unwrap().duration(), Duration::from_millis(40));
+}
+
+#[test]
+fn slow_probes_count_with_a_threshold() {
+    let rtt_times = probes(&[Some(1), Some(80), None, Some(120), Some(1)]);
+
+    let without = OutageReport::detect(&rtt_times, &OutageConfig { min_probes: 2, rtt_threshold: None });
+    assert!(without.outages.is_empty());
+    assert_eq!(without.longest(), None);
+
+    let with = OutageReport::detect(&rtt_times, &OutageConfig { min_probes: 2, rtt_threshold: Some(Duration::from_millis(50)) });
+    assert_eq!(with.outages, vec![Outage { start: 1_010_000, end: 1_040_000, recovered: true, lost: 1, slow: 2 }]);
+}
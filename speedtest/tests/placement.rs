@@ -0,0 +1,73 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use udpbenchmark::concurrent::ConcurrentProbe;
+use udpbenchmark::config::ClientConfig;
+use udpbenchmark::placement::{PlacementMetric, PlacementPolicy};
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::QosProfile;
+use yaml_rust::YamlLoader;
+use common::{start_server, target};
+
+const CONFIG: &str = "
+server_addr: 10.0.0.1:8080
+client_addr: 0.0.0.0:0
+placement:
+  criteria:
+    - metric: p99_rtt
+      weight: 2
+      sla: 15
+    - metric: loss
+      sla: 1
+  interval: 30
+";
+
+#[test]
+fn targets_are_ranked_by_the_composite_score() {
+    let config = ClientConfig::from_yaml(&YamlLoader::load_from_str(CONFIG).unwrap()[0]).unwrap();
+    let policy = &config.placement;
+    assert_eq!(policy.criteria.iter().map(|criterion| (criterion.metric, criterion.weight, criterion.sla)).collect::<Vec<_>>(), [(PlacementMetric::P99Rtt, 2.0, 15.0), (PlacementMetric::Loss, 1.0, 1.0)]);
+    assert_eq!(policy.interval, Duration::from_secs(30));
+    assert_eq!(config.to_json()["placement"]["criteria"][0]["metric"], "p99_rtt");
+    assert!(ClientConfig::from_yaml(&YamlLoader::load_from_str(&CONFIG.replace("sla: 1", "sla: 0")).unwrap()[0]).is_err());
+
+    let network = SimNetwork::new(11).with_link(LinkConfig { delay: Duration::from_millis(1), ..LinkConfig::default() });
+    let servers = [
+        start_server(&network, "127.0.0.1:8080", QosProfile::Delay(5)),
+        start_server(&network, "127.0.0.1:8081", QosProfile::Delay(20)),
+        start_server(&network, "127.0.0.1:8082", QosProfile::Loss(50)),
+    ];
+    let specs = ["Edge", "Cloud", "Lossy"].iter().zip(&servers).map(|(name, server)| target(name, server)).collect();
+    let result = ConcurrentProbe::new(specs).network(Arc::new(network.clone())).run().unwrap();
+    for server in servers {
+        server.stop();
+    }
+
+    // Edge meets both thresholds; Cloud is too slow and ranks before Lossy, whose loss weighs far more
+    let recommendation = policy.rank(&result.results);
+    let ranking = recommendation.candidates.iter().map(|candidate| (candidate.name.as_str(), candidate.meets_sla())).collect::<Vec<_>>();
+    assert_eq!(ranking, [("Edge", true), ("Cloud", false), ("Lossy", false)]);
+    assert_eq!(recommendation.candidates[1].violations, [PlacementMetric::P99Rtt]);
+    let edge = &recommendation.candidates[0];
+    assert!((edge.score - 2.0 * edge.values[0].unwrap() / 15.0 / 3.0).abs() < 1e-9);
+    let margin = recommendation.margin().unwrap();
+    assert!((margin - 2.0 * 15.0 / 15.0 / 3.0).abs() < 0.01, "{}", margin);
+    assert!(recommendation.to_string().ends_with(&format!("Recommended: Edge by a margin of {:.3} over Cloud", margin)));
+
+    // Without the loss criterion the fastest target wins, although it loses half of the probes
+    let latency_only = PlacementPolicy { criteria: policy.criteria[..1].to_vec(), ..PlacementPolicy::default() };
+    assert_eq!(latency_only.rank(&result.results).best().unwrap().name, "Lossy");
+
+    let path = std::env::temp_dir().join(format!("udpbenchmark-placement-{}.csv", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    recommendation.append_csv(&path, 0, SystemTime::UNIX_EPOCH, false).unwrap();
+    recommendation.append_csv(&path, 1, SystemTime::UNIX_EPOCH, true).unwrap();
+    let mut rdr = csv::Reader::from_path(&path).unwrap();
+    let rows = rdr.records().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(rows.len(), 6);
+    assert_eq!((&rows[0][3], &rows[0][6], &rows[0][11]), ("Edge", "true", "false"));
+    assert_eq!((&rows[3][0], &rows[3][2], &rows[3][11]), ("1", "1", "true"));
+    assert_eq!((&rows[5][3], &rows[5][7], &rows[5][10]), ("Lossy", "loss", ""));
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+use udpbenchmark::client::{BitrateScale, SpeedtestEnum};
+use udpbenchmark::config::ClientConfig;
+use udpbenchmark::plan::ExperimentPlan;
+use yaml_rust::YamlLoader;
+
+const CONFIG: &str = "
+server_addr: 10.0.0.1:8080
+client_addr: 0.0.0.0:0
+bitrate_scale: K
+experiment_count: 2
+experiment_interval: 1
+experiment_modes: [duration_custom_bitrate, ping, packet_count]
+experiment_payload_sizes: [100, 200]
+experiment_bitrates: [10, 20]
+experiment_intervals: [0.5]
+experiment_durations: [5, 10]
+experiment_servers:
+  - name: Edge
+    address: 10.0.0.3:8080
+  - name: Cloud
+    address: 10.0.0.4:8080
+    payload_sizes: [64]
+    speedtest_mode: ping
+";
+
+#[test]
+fn plan_crosses_the_parameters_each_mode_uses() {
+    let config = ClientConfig::from_yaml(&YamlLoader::load_from_str(CONFIG).unwrap()[0]).unwrap();
+    let plan = ExperimentPlan::expand(&config);
+
+    // Edge: 2 payload sizes × (2 bitrates × 2 durations + 2 durations + 1), Cloud: 2 durations, per iteration
+    assert_eq!(plan.points.len(), 2 * (2 * (4 + 2 + 1) + 2));
+    let edge = plan.points.iter().filter(|point| point.iteration == 0 && point.target.name == "Edge").collect::<Vec<_>>();
+    let bitrate = edge.iter().find(|point| point.mode == SpeedtestEnum::ByDurationCustomBitrate).unwrap();
+    assert_eq!((bitrate.bitrate, bitrate.interval, bitrate.duration), (Some(10 * 1024), None, Some(Duration::from_secs(5))));
+    assert_eq!(bitrate.id(), "Edge_duration_custom_bitrate_100B_10240bps_5s");
+    let ping = edge.iter().find(|point| point.mode == SpeedtestEnum::Ping).unwrap();
+    assert_eq!((ping.bitrate, ping.interval), (None, Some(Duration::from_millis(500))));
+    let packet_count = edge.iter().filter(|point| point.mode == SpeedtestEnum::ByPacketCount).collect::<Vec<_>>();
+    assert_eq!(packet_count.len(), 2);
+    assert_eq!(packet_count[0].duration, None);
+    assert!(plan.points.iter().filter(|point| point.target.name == "Cloud").all(|point| point.mode == SpeedtestEnum::Ping && point.payload_size == 64));
+
+    let spec = bitrate.spec(&config);
+    assert_eq!((spec.server_name(), spec.bitrate, spec.bitrate_scale, spec.duration), ("Edge", 10 * 1024, BitrateScale::Bps, Duration::from_secs(5)));
+    assert_eq!(bitrate.to_json()["bitrate"], 10 * 1024);
+    assert!(bitrate.to_json()["interval"].is_null());
+
+    // Per iteration: Edge 2 × (2 × 15 s + 15 s) + Cloud 15 s, plus a second per test
+    assert_eq!(plan.estimated_duration(), Duration::from_secs(2 * (2 * 45 + 15) + plan.points.len() as u64));
+    assert_eq!(plan.unestimated_points(), 4);
+    let printed = plan.to_string();
+    assert_eq!(printed.lines().count(), plan.points.len() + 3);
+    assert!(printed.starts_with("Schedule: sequential\n"));
+    assert!(printed.ends_with(&format!("{} tests, estimated total time 0:04:02 plus 4 packet count tests", plan.points.len())), "{}", printed);
+}
+
+#[test]
+fn schedules_order_every_iteration() {
+    let order = |schedule: &str| {
+        let yaml = format!("{}\nexperiment_schedule: {}\nexperiment_seed: 7\nexperiment_modes: [ping]\nexperiment_durations: [5]\n", CONFIG.replace("experiment_modes", "unused_modes").replace("experiment_durations", "unused_durations"), schedule);
+        let config = ClientConfig::from_yaml(&YamlLoader::load_from_str(&yaml).unwrap()[0]).unwrap();
+        ExperimentPlan::expand(&config)
+    };
+    let names = |plan: &ExperimentPlan, iteration: usize| plan.points.iter().filter(|point| point.iteration == iteration).map(|point| format!("{}{}", point.target.name, point.payload_size)).collect::<Vec<_>>();
+
+    let sequential = order("sequential");
+    assert_eq!(names(&sequential, 0), ["Edge100", "Edge200", "Cloud64"]);
+    assert_eq!(sequential.seed, None);
+    let interleaved = order("interleaved");
+    assert_eq!(names(&interleaved, 1), ["Edge100", "Cloud64", "Edge200"]);
+    assert_eq!(interleaved.points.iter().map(|point| point.position).collect::<Vec<_>>(), [0, 1, 2, 0, 1, 2]);
+
+    let random = order("random");
+    assert_eq!(random.seed, Some(7));
+    assert_eq!(random, order("random"));
+    for iteration in 0..2 {
+        let mut shuffled = names(&random, iteration);
+        shuffled.sort();
+        assert_eq!(shuffled, ["Cloud64", "Edge100", "Edge200"]);
+    }
+
+    let path = std::env::temp_dir().join(format!("udpbenchmark-plan-{}.csv", std::process::id()));
+    random.write_csv(&path).unwrap();
+    let mut rdr = csv::Reader::from_path(&path).unwrap();
+    let rows = rdr.records().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(rows.len(), 6);
+    assert_eq!((&rows[4][0], &rows[4][1], &rows[4][3], &rows[4][4]), ("random", "7", "1", random.points[4].id().as_str()));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn logged_schedule_seeds_replay_the_plan() {
+    let config = |seed: Option<u64>| {
+        let seed = seed.map(|seed| format!("experiment_seed: {}\n", seed)).unwrap_or_default();
+        let yaml = format!("{}\nexperiment_schedule: random\n{}", CONFIG, seed);
+        ClientConfig::from_yaml(&YamlLoader::load_from_str(&yaml).unwrap()[0]).unwrap()
+    };
+
+    // Drawn seeds are above i64::MAX half of the time
+    for _ in 0..8 {
+        let drawn = ExperimentPlan::expand(&config(None));
+        assert_eq!(ExperimentPlan::expand(&config(drawn.seed)), drawn);
+    }
+    assert_eq!(config(Some(17386264056990141951)).experiment_seed, Some(17386264056990141951));
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use udpbenchmark::client::{BitrateScale, SpeedtestEnum};
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::{Client, ClientBuilder, QosProfile, Server, ServerHandle, TestResult};
+use common::{assert_within, loss_rate, sent_packets, start_server};
+
+fn client(network: &SimNetwork, server: &ServerHandle) -> ClientBuilder {
+    Client::builder()
+        .network(Arc::new(network.clone()))
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .payload_size(500)
+        .receive_timeout(Duration::from_secs(1))
+}
+
+fn ping(link: LinkConfig, seed: u64, interval: Duration, duration: Duration) -> TestResult {
+    let network = SimNetwork::new(seed).with_link(link);
+    let server = start_server(&network, "127.0.0.1:8080", QosProfile::Default);
+    let result = client(&network, &server)
+        .mode(SpeedtestEnum::Ping)
+        .interval(interval)
+        .duration(duration)
+        .build()
+        .run()
+        .expect("Test run failed");
+    server.stop();
+    result
+}
+
+fn lossy_link() -> LinkConfig {
+    LinkConfig {
+        delay: Duration::from_millis(10),
+        jitter: Duration::from_millis(5),
+        loss: 10.0,
+        ..LinkConfig::default()
+    }
+}
+
+#[test]
+fn same_seed_gives_identical_results() {
+    let first = ping(lossy_link(), 42, Duration::from_millis(10), Duration::from_secs(5));
+    let second = ping(lossy_link(), 42, Duration::from_millis(10), Duration::from_secs(5));
+    let other = ping(lossy_link(), 43, Duration::from_millis(10), Duration::from_secs(5));
+
+    assert_eq!((&first.histogram, &first.delivery, &first.jitter), (&second.histogram, &second.delivery, &second.jitter));
+    assert_eq!(first.bytes_received, second.bytes_received);
+    assert_ne!(first.histogram, other.histogram);
+}
+
+#[test]
+fn seeded_server_replays_impairments() {
+    let run = |seed| {
+        let network = SimNetwork::new(1);
+        let server = Server::builder()
+            .network(Arc::new(network.clone()))
+            .qos_profile(QosProfile::Reorder { reorder: 30, reorder_delay: 20 })
+            .threads(1)
+            .seed(seed)
+            .build()
+            .expect("Couldn't start server")
+            .spawn()
+            .expect("Couldn't spawn server");
+        let result = client(&network, &server)
+            .mode(SpeedtestEnum::Ping)
+            .interval(Duration::from_millis(50))
+            .duration(Duration::from_secs(5))
+            .packet_log(true)
+            .build()
+            .run()
+            .expect("Test run failed");
+        assert_eq!(result.server_seed, Some(seed));
+        result.rtt_times.get_rtts()
+    };
+
+    assert_eq!(run(7), run(7));
+    assert_ne!(run(7), run(8));
+}
+
+#[test]
+fn link_loss_applies_in_both_directions() {
+    let result = ping(lossy_link(), 1, Duration::from_millis(1), Duration::from_secs(10));
+
+    assert_eq!(sent_packets(&result), 10_000);
+    // A round trip survives with a probability of 0.9 * 0.9
+    assert_within("loss rate", loss_rate(&result), 0.17, 0.21);
+}
+
+#[test]
+fn fixed_delays_give_exact_rtts() {
+    let network = SimNetwork::new(1).with_link(LinkConfig { delay: Duration::from_millis(5), bandwidth: 0, ..LinkConfig::default() });
+    let server = start_server(&network, "127.0.0.1:8080", QosProfile::Delay(20));
+    let result = client(&network, &server)
+        .mode(SpeedtestEnum::ByPacketCount)
+        .packet_count(50)
+        .packet_log(true)
+        .build()
+        .run()
+        .expect("Test run failed");
+    server.stop();
+
+    assert_eq!(loss_rate(&result), 0.0);
+    // Server workers handle one request at a time, so every response waits for the ones before it.
+    let rtts = result.rtt_times.get_rtts();
+    for (index, rtt) in rtts.iter().enumerate() {
+        assert_eq!(*rtt, 30_000 + index as u128 * 20_000);
+    }
+}
+
+#[test]
+fn wrapped_session_ids_match_the_latest_request() {
+    let link = LinkConfig { delay: Duration::from_millis(1), bandwidth: 0, ..LinkConfig::default() };
+    let result = ping(link, 1, Duration::from_micros(100), Duration::from_secs(8));
+
+    assert_eq!(sent_packets(&result), 80_000);
+    assert_eq!(loss_rate(&result), 0.0);
+    assert_eq!((result.histogram.len(), result.histogram.min(), result.histogram.max()), (80_000, Some(2_000), Some(2_000)));
+}
+
+#[test]
+fn bitrate_mode_hits_target_bitrate_exactly() {
+    let network = SimNetwork::new(1).with_link(LinkConfig { delay: Duration::from_millis(20), ..LinkConfig::default() });
+    let server = start_server(&network, "127.0.0.1:8080", QosProfile::Default);
+    let result = client(&network, &server)
+        .mode(SpeedtestEnum::ByDurationCustomBitrate)
+        .bitrate(10, BitrateScale::Mbps)
+        .duration(Duration::from_secs(10))
+        .build()
+        .run()
+        .expect("Test run failed");
+    server.stop();
+
+    let target = BitrateScale::Mbps as i64 as f64 * 10.0 * 504.0 / 500.0;
+    assert_within("sent throughput", result.throughput_sent(), target * 0.999, target * 1.001);
+    assert_eq!(result.bytes_received, result.bytes_sent);
+    assert_within("min RTT", result.histogram.min().unwrap() as f64, 40_000.0, 40_010.0);
+}
+
+#[test]
+fn virtual_time_runs_faster_than_real_time() {
+    let start = Instant::now();
+    let result = ping(LinkConfig { delay: Duration::from_millis(50), ..LinkConfig::default() }, 1, Duration::from_secs(1), Duration::from_secs(600));
+
+    assert_eq!(sent_packets(&result), 600);
+    assert_eq!(loss_rate(&result), 0.0);
+    assert!(result.elapsed >= Duration::from_secs(600));
+    assert!(start.elapsed() < Duration::from_secs(10), "simulation took {:?}", start.elapsed());
+}
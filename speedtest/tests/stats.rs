@@ -0,0 +1,105 @@
+use std::sync::Arc;
+use std::time::Duration;
+use udpbenchmark::analyze::ResultTable;
+use udpbenchmark::client::{RTTTimes, SpeedtestEnum};
+use udpbenchmark::histogram::LatencyHistogram;
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::stats::{self, RttSummary, SampleSummary};
+use udpbenchmark::{Client, QosProfile, Server};
+
+#[test]
+fn empty_sample_sets_have_no_statistics() {
+    assert_eq!(stats::mean(&[]), None);
+    assert_eq!(stats::variance(&[]), None);
+    assert_eq!(stats::percentile(&[], 99.0), None);
+    assert_eq!(RttSummary::from_samples(&[], &[50.0]), None);
+    assert_eq!(RttSummary::from_histogram(&LatencyHistogram::new(3), &[50.0]), None);
+
+    let rtt_times = RTTTimes::new();
+    assert_eq!(rtt_times.get_average_rtt(), None);
+    assert_eq!(rtt_times.get_min_rtt(), None);
+    assert_eq!(rtt_times.get_percentile_rtt(99.9), None);
+}
+
+#[test]
+fn percentiles_use_the_nearest_rank() {
+    let samples = (1..=1000).collect::<Vec<u128>>();
+    let summary = RttSummary::from_samples(&samples, &[50.0, 90.0, 99.0, 99.9, 99.99]).unwrap();
+
+    assert_eq!(summary.percentiles, vec![(50.0, 500), (90.0, 900), (99.0, 990), (99.9, 999), (99.99, 1000)]);
+    assert_eq!((summary.count, summary.min, summary.median, summary.max), (1000, 1, 500, 1000));
+    assert_eq!(summary.mean, 500.5);
+    assert_eq!(stats::percentile(&[7], 0.0), Some(7));
+}
+
+#[test]
+fn statistics_count_answered_packets_only() {
+    let network = SimNetwork::new(3).with_link(LinkConfig { delay: Duration::from_millis(5), loss: 20.0, bandwidth: 0, ..LinkConfig::default() });
+    let server = Server::builder().network(Arc::new(network.clone())).qos_profile(QosProfile::Default).threads(1).build().unwrap().spawn().unwrap();
+    let result = Client::builder()
+        .network(Arc::new(network))
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .mode(SpeedtestEnum::Ping)
+        .interval(Duration::from_millis(10))
+        .duration(Duration::from_secs(2))
+        .percentiles(&[50.0, 99.9])
+        .build()
+        .run()
+        .unwrap();
+    server.stop();
+
+    let summary = result.summary().unwrap();
+    assert!(summary.count < 200, "lost packets must not be counted");
+    assert_eq!(summary.count, result.delivery.delivered);
+    // Every answered packet takes exactly two link delays, lost ones must not pull the mean down.
+    assert_eq!((summary.min, summary.max, summary.mean), (10_000, 10_000, 10_000.0));
+    assert_eq!(summary.percentiles, vec![(50.0, 10_000), (99.9, 10_000)]);
+}
+
+#[test]
+fn grouped_means_skip_empty_cells() {
+    let table = ResultTable {
+        columns: ["Server Address", "Speedtest-Mode", "Average RTT"].iter().map(|column| column.to_string()).collect(),
+        rows: vec![
+            vec!["a".to_string(), "ping".to_string(), "100".to_string()],
+            vec!["a".to_string(), "ping".to_string(), String::new()],
+            vec!["a".to_string(), "ping".to_string(), "200".to_string()],
+            vec!["b".to_string(), "ping".to_string(), String::new()],
+        ],
+    };
+
+    let grouped = table.group_mean();
+    assert_eq!(grouped.rows[0][2], "150");
+    assert_eq!(grouped.rows[1][2], "");
+}
+
+#[test]
+fn results_files_with_other_columns_merge() {
+    let dir = std::env::temp_dir().join(format!("udpbenchmark-merge-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("run")).unwrap();
+    std::fs::write(dir.join("rtt_times_edge_ping_1.csv"), "Server Name,Speedtest-Mode,Average RTT\nEdge,ping,100\n").unwrap();
+    std::fs::write(dir.join("run").join("rtt_times_edge_ping_2.csv"), "Server Name,Jitter,Speedtest-Mode,Average RTT\nEdge,7,ping,200\n").unwrap();
+
+    let table = ResultTable::read_dir(&dir).unwrap();
+    assert_eq!(table.columns, ["Server Name", "Speedtest-Mode", "Average RTT", "Jitter"]);
+    assert_eq!(table.rows, [["Edge", "ping", "100", ""], ["Edge", "ping", "200", "7"]]);
+    let grouped = table.group_mean();
+    assert_eq!(grouped.rows, [["Edge", "ping", "150", "7"]]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn sample_summary_has_t_confidence_interval() {
+    let summary = SampleSummary::from_values(&[3.0, 1.0, 2.0, 6.0]).unwrap();
+    assert_eq!((summary.count, summary.mean, summary.median), (4, 3.0, 2.5));
+    let stddev = summary.stddev.unwrap();
+    assert!((stddev - (14f64 / 3.0).sqrt()).abs() < 1e-9, "stddev: {}", stddev);
+    // t(0.975, 3) = 3.182
+    let (low, high) = summary.ci95.unwrap();
+    assert!((high - 3.0 - 3.182 * stddev / 2.0).abs() < 1e-3 && (3.0 - low - (high - 3.0)).abs() < 1e-9);
+
+    let single = SampleSummary::from_values(&[5.0]).unwrap();
+    assert_eq!((single.median, single.stddev, single.ci95), (5.0, None, None));
+    assert_eq!(SampleSummary::from_values(&[]), None);
+}
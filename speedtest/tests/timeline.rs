@@ -0,0 +1,109 @@
+mod common;
+
+use std::fs;
+use std::net::UdpSocket;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use udpbenchmark::client::SpeedtestEnum;
+use udpbenchmark::events::Event;
+use udpbenchmark::sim::{LinkConfig, SimNetwork};
+use udpbenchmark::timeline::{TimelineEntry, TimelineWriter};
+use udpbenchmark::udp_application::{UDPApplication, UDPApplicationEnum};
+use udpbenchmark::{Client, QosProfile, Server};
+use common::temp_dir;
+
+fn read_rows(path: &PathBuf) -> Vec<csv::StringRecord> {
+    csv::Reader::from_path(path).unwrap().records().map(Result::unwrap).collect()
+}
+
+#[test]
+fn timeline_classifies_every_packet() {
+    let path = temp_dir("timeline").join("timeline.csv");
+    let writer = TimelineWriter::create(&path, "stream", 100, Duration::from_millis(50)).unwrap();
+    let timeline = writer.sender();
+    for session_id in 0..5u16 {
+        timeline.send(TimelineEntry::Sent { session_id, time: 1_000_000 + session_id as u128 * 10_000 }).unwrap();
+    }
+    // Probe 1 arrives after 2 and twice, probe 3 only after it counts as lost and probe 4 never
+    for (session_id, time) in [(0, 1_001_000), (2, 1_021_000), (1, 1_022_000), (1, 1_023_000), (3, 1_090_000), (7, 1_091_000)] {
+        timeline.send(TimelineEntry::Received { session_id, time, server_timestamps: Some((time - 500, time - 400)) }).unwrap();
+    }
+    drop(timeline);
+    let path = writer.finish(&[Event::new(1_050_000, "socket", "rules update")]).unwrap();
+
+    let rows = read_rows(&path);
+    let statuses = rows.iter().map(|row| (row[1].to_string(), row[8].to_string())).collect::<Vec<_>>();
+    let expected = [("0", "ok"), ("2", "ok"), ("1", "reordered"), ("1", "dup"), ("3", "lost"), ("3", "late"), ("4", "lost"), ("", "unsolicited"), ("", "event")];
+    assert_eq!(statuses, expected.iter().map(|(sequence, status)| (sequence.to_string(), status.to_string())).collect::<Vec<_>>());
+    // Stream, sequence, session ID, sent, server received, server sent, received, payload size
+    assert_eq!(rows[2].iter().take(8).collect::<Vec<_>>(), ["stream", "1", "1", "1010000", "1021500", "1021600", "1022000", "100"]);
+    assert_eq!((&rows[5][3], &rows[5][6]), ("1030000", "1090000"));
+    assert_eq!((&rows[8][6], &rows[8][9]), ("1050000", "rules update"));
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_exports_timeline_with_server_timestamps() {
+    let dir = temp_dir("timeline-run");
+    let network = SimNetwork::new(3).with_link(LinkConfig { delay: Duration::from_millis(2), ..LinkConfig::default() });
+    let server = Server::builder().network(Arc::new(network.clone())).qos_profile(QosProfile::Duplicate(20)).threads(1).seed(3).build().unwrap().spawn().unwrap();
+    let result = Client::builder()
+        .network(Arc::new(network))
+        .server_addr(server.local_addr().to_string())
+        .client_addr("127.0.0.1:0")
+        .mode(SpeedtestEnum::Ping)
+        .interval(Duration::from_millis(10))
+        .duration(Duration::from_secs(2))
+        .timeline(&dir)
+        .packet_log(true)
+        .build()
+        .run()
+        .unwrap();
+    server.stop();
+
+    let rows = read_rows(result.timeline.as_ref().unwrap());
+    let count = |status: &str| rows.iter().filter(|row| &row[8] == status).count() as u64;
+    assert_eq!(count("ok"), result.delivery.delivered);
+    assert_eq!(count("dup"), result.delivery.duplicates);
+    assert!(result.delivery.duplicates > 0);
+    assert_eq!(count("lost"), 0);
+    for row in rows.iter().filter(|row| &row[8] == "ok") {
+        let times = (3..7).map(|column| row[column].parse::<u128>().unwrap()).collect::<Vec<_>>();
+        // One link delay plus a few microseconds of transmission each way, answered without any server delay
+        assert_eq!(times[2], times[1], "row {:?}", row);
+        assert!(times[1] - times[0] >= 2000 && times[1] - times[0] < 2010, "row {:?}", row);
+        assert!(times[3] - times[2] >= 2000 && times[3] - times[2] < 2010, "row {:?}", row);
+    }
+    let packet = &result.rtt_times.rtt_times[0];
+    assert!(packet.server_received_time - packet.sent_time >= 2000 && packet.received_time >= packet.server_sent_time + 2000);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn server_timestamps_only_answer_timed_requests() {
+    let server = Server::builder().address("127.0.0.1:0").threads(1).build().unwrap().spawn().unwrap();
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    let payload = (0..32).collect::<Vec<u8>>();
+    let exchange = |type_field: UDPApplicationEnum| {
+        let request = UDPApplication { type_field: type_field as u16, session_id: 7, test_payload: payload.clone() };
+        socket.send_to(&request.to_bytes(), server.local_addr()).unwrap();
+        let mut buf = [0; 64];
+        let (len, _addr) = socket.recv_from(&mut buf).unwrap();
+        UDPApplication::from_bytes(&buf[..len])
+    };
+
+    // A plain request is echoed unchanged
+    let echo = exchange(UDPApplicationEnum::REQUEST);
+    assert_eq!((echo.type_field, echo.session_id), (UDPApplicationEnum::RESPONSE as u16, 7));
+    assert_eq!(echo.test_payload, payload);
+    assert_eq!(echo.server_timestamps(), None);
+
+    let timed = exchange(UDPApplicationEnum::TIMED_REQUEST);
+    assert_eq!(timed.type_field, UDPApplicationEnum::TIMED_RESPONSE as u16);
+    let (received, sent) = timed.server_timestamps().unwrap();
+    assert!(received > 0 && sent >= received);
+    assert_eq!(timed.test_payload[16..], payload[16..]);
+    server.stop();
+}